@@ -0,0 +1,284 @@
+use std::io::Cursor;
+
+use chrono::Duration;
+use rodio::{Decoder, Source};
+
+/// Length of the windows the loudness of the audio is measured over, in seconds.
+const WINDOW_LENGTH: f64 = 0.01;
+
+/// Loudness that normalization brings the audio to, as RMS in dB relative to full scale.
+const TARGET_RMS_DB: f32 = -18.0;
+
+/// Loudness below which the start and end of the audio count as silence when trimming, as RMS
+/// in dB relative to full scale.
+const TRIM_THRESHOLD_DB: f32 = -50.0;
+
+/// How far from a timestamp a zero-crossing is looked for, in seconds.
+const ZERO_CROSSING_SEARCH_LENGTH: f64 = 0.01;
+
+/// A fully decoded audio file, mixed down to mono for analysis.
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    /// Decodes the whole of `data`, averaging all channels into one.
+    pub fn decode(data: Vec<u8>) -> anyhow::Result<Self> {
+        let decoder = Decoder::new(Cursor::new(data))?;
+        let channels = decoder.channels().max(1) as usize;
+        let sample_rate = decoder.sample_rate();
+        let interleaved: Vec<f32> = decoder.convert_samples().collect();
+        let samples = interleaved
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+            .collect();
+        Ok(DecodedAudio {
+            samples,
+            sample_rate,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.time_of(self.samples.len())
+    }
+
+    fn time_of(&self, sample_index: usize) -> Duration {
+        Duration::milliseconds((sample_index as f64 * 1000.0 / self.sample_rate as f64) as i64)
+    }
+
+    /// The gain, in dB, that brings the RMS loudness of the audio to [`TARGET_RMS_DB`], limited
+    /// so that the peaks do not clip.
+    pub fn normalization_gain_db(&self) -> f32 {
+        let peak = self.samples.iter().fold(0f32, |peak, x| peak.max(x.abs()));
+        if peak <= 0.0 {
+            return 0.0;
+        }
+        let rms =
+            (self.samples.iter().map(|x| x * x).sum::<f32>() / self.samples.len() as f32).sqrt();
+        let gain = TARGET_RMS_DB - 20.0 * rms.log10();
+        gain.min(-20.0 * peak.log10())
+    }
+
+    /// Finds the spans quieter than `threshold_db` (relative to full scale) that last at least
+    /// `min_length`, as `(begin, end)` pairs in order.
+    pub fn detect_silences(
+        &self,
+        threshold_db: f32,
+        min_length: Duration,
+    ) -> Vec<(Duration, Duration)> {
+        let window = ((self.sample_rate as f64 * WINDOW_LENGTH) as usize).max(1);
+        let threshold = 10f32.powf(threshold_db / 20.0);
+
+        let mut silences = Vec::new();
+        let mut silence_start = None;
+        for (index, chunk) in self.samples.chunks(window).enumerate() {
+            let rms = (chunk.iter().map(|x| x * x).sum::<f32>() / chunk.len() as f32).sqrt();
+            let start = index * window;
+            match (rms < threshold, silence_start) {
+                (true, None) => silence_start = Some(start),
+                (false, Some(begin)) => {
+                    silences.push((begin, start));
+                    silence_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(begin) = silence_start {
+            silences.push((begin, self.samples.len()));
+        }
+
+        silences
+            .into_iter()
+            .map(|(begin, end)| (self.time_of(begin), self.time_of(end)))
+            .filter(|(begin, end)| *end - *begin >= min_length)
+            .collect()
+    }
+
+    /// The lengths of the silence before the first sound and after the last, measured in
+    /// windows quieter than [`TRIM_THRESHOLD_DB`]. Audio without any sound has none.
+    pub fn edge_silences(&self) -> (Duration, Duration) {
+        let window = ((self.sample_rate as f64 * WINDOW_LENGTH) as usize).max(1);
+        let threshold = 10f32.powf(TRIM_THRESHOLD_DB / 20.0);
+        let sounding = |chunk: &[f32]| {
+            (chunk.iter().map(|x| x * x).sum::<f32>() / chunk.len() as f32).sqrt() >= threshold
+        };
+        let mut chunks = self.samples.chunks(window);
+        let Some(first) = chunks.position(sounding) else {
+            return (Duration::zero(), Duration::zero());
+        };
+        let last = self
+            .samples
+            .chunks(window)
+            .rposition(sounding)
+            .unwrap_or(first);
+        let sound_end = ((last + 1) * window).min(self.samples.len());
+        (
+            self.time_of(first * window),
+            self.duration() - self.time_of(sound_end),
+        )
+    }
+
+    /// A copy of the audio without its first `leading` and last `trailing` stretches.
+    pub fn trimmed(&self, leading: Duration, trailing: Duration) -> DecodedAudio {
+        let index_of = |time: Duration| {
+            ((time.num_milliseconds().max(0) as f64 * self.sample_rate as f64 / 1000.0) as usize)
+                .min(self.samples.len())
+        };
+        let begin = index_of(leading);
+        let end = self.samples.len() - index_of(trailing);
+        DecodedAudio {
+            samples: self.samples[begin..end.max(begin)].to_vec(),
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Returns the sounding spans between the `silences` found by [`Self::detect_silences`].
+    pub fn voiced_regions(&self, silences: &[(Duration, Duration)]) -> Vec<(Duration, Duration)> {
+        let mut regions = Vec::new();
+        let mut begin = Duration::zero();
+        for (silence_begin, silence_end) in silences {
+            if *silence_begin > begin {
+                regions.push((begin, *silence_begin));
+            }
+            begin = *silence_end;
+        }
+        if self.duration() > begin {
+            regions.push((begin, self.duration()));
+        }
+        regions
+    }
+
+    /// The first zero-crossing at or after `time` if `forward` is set, or at or before it
+    /// otherwise. Returns `None` if there is none within [`ZERO_CROSSING_SEARCH_LENGTH`].
+    ///
+    /// The crossing is rounded to the millisecond, which is as precise as lyrics timestamps
+    /// are stored, so it can be up to half a millisecond off: 22 samples at 44.1 kHz.
+    pub fn zero_crossing(&self, time: Duration, forward: bool) -> Option<Duration> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+        let index =
+            (time.num_milliseconds().max(0) as f64 * self.sample_rate as f64 / 1000.0) as usize;
+        let index = index.clamp(1, self.samples.len() - 1);
+        let window = (self.sample_rate as f64 * ZERO_CROSSING_SEARCH_LENGTH) as usize;
+        let crosses = |i: &usize| (self.samples[i - 1] <= 0.0) != (self.samples[*i] <= 0.0);
+        let crossing = if forward {
+            (index..(index + window).min(self.samples.len())).find(crosses)
+        } else {
+            (index.saturating_sub(window).max(1)..=index)
+                .rev()
+                .find(crosses)
+        }?;
+        Some(Duration::milliseconds(
+            (crossing as f64 * 1000.0 / self.sample_rate as f64).round() as i64,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Audio at 1 kHz, so that each sample lasts a millisecond, made of `(length, level)`
+    /// stretches of constant samples.
+    fn audio(stretches: &[(usize, f32)]) -> DecodedAudio {
+        DecodedAudio {
+            samples: stretches
+                .iter()
+                .flat_map(|(length, level)| vec![*level; *length])
+                .collect(),
+            sample_rate: 1000,
+        }
+    }
+
+    fn ms(milliseconds: i64) -> Duration {
+        Duration::milliseconds(milliseconds)
+    }
+
+    #[test]
+    fn edge_silences_around_sound() {
+        let audio = audio(&[(100, 0.0), (300, 0.5), (600, 0.0)]);
+        assert_eq!(audio.edge_silences(), (ms(100), ms(600)));
+    }
+
+    #[test]
+    fn edge_silences_of_silent_audio() {
+        assert_eq!(audio(&[(1000, 0.0)]).edge_silences(), (ms(0), ms(0)));
+        assert_eq!(audio(&[]).edge_silences(), (ms(0), ms(0)));
+    }
+
+    #[test]
+    fn edge_silences_of_sound_touching_both_edges() {
+        let audio = audio(&[(50, 0.5), (100, 0.0), (55, -0.5)]);
+        assert_eq!(audio.edge_silences(), (ms(0), ms(0)));
+    }
+
+    #[test]
+    fn detect_silences_between_sounds() {
+        let audio = audio(&[(200, 0.5), (300, 0.0), (200, 0.5), (50, 0.0), (250, 0.5)]);
+        assert_eq!(
+            audio.detect_silences(-40.0, ms(0)),
+            vec![(ms(200), ms(500)), (ms(700), ms(750))]
+        );
+        assert_eq!(
+            audio.detect_silences(-40.0, ms(100)),
+            vec![(ms(200), ms(500))]
+        );
+    }
+
+    #[test]
+    fn detect_silences_runs_to_the_end() {
+        let audio = audio(&[(200, 0.5), (300, 0.001)]);
+        assert_eq!(
+            audio.detect_silences(-40.0, ms(100)),
+            vec![(ms(200), ms(500))]
+        );
+        assert_eq!(audio.detect_silences(-80.0, ms(0)), vec![]);
+    }
+
+    #[test]
+    fn trimmed_cuts_both_ends() {
+        let audio = audio(&[(100, 0.0), (300, 0.5), (600, 0.0)]);
+        let trimmed = audio.trimmed(ms(100), ms(600));
+        assert_eq!(trimmed.samples, vec![0.5; 300]);
+        assert_eq!(trimmed.sample_rate, 1000);
+    }
+
+    #[test]
+    fn trimmed_clamps_indices() {
+        let audio = audio(&[(1000, 0.5)]);
+        assert!(audio.trimmed(ms(2_000), ms(0)).samples.is_empty());
+        assert!(audio.trimmed(ms(0), ms(2_000)).samples.is_empty());
+        assert!(audio.trimmed(ms(600), ms(600)).samples.is_empty());
+        assert_eq!(audio.trimmed(ms(-100), ms(-100)).samples.len(), 1000);
+    }
+
+    #[test]
+    fn zero_crossing_within_search_length() {
+        let audio = audio(&[(100, -0.5), (100, 0.5)]);
+        assert_eq!(audio.zero_crossing(ms(95), true), Some(ms(100)));
+        assert_eq!(audio.zero_crossing(ms(105), false), Some(ms(100)));
+        assert_eq!(audio.zero_crossing(ms(100), false), Some(ms(100)));
+        assert_eq!(audio.zero_crossing(ms(50), true), None);
+        assert_eq!(audio.zero_crossing(ms(150), false), None);
+        assert_eq!(
+            DecodedAudio {
+                samples: vec![0.5],
+                sample_rate: 1000,
+            }
+            .zero_crossing(ms(0), true),
+            None
+        );
+    }
+
+    #[test]
+    fn zero_crossing_is_rounded_to_the_millisecond() {
+        let audio = DecodedAudio {
+            samples: [vec![-0.5; 4421], vec![0.5; 4410]].concat(),
+            sample_rate: 44_100,
+        };
+        // The crossing is at sample 4421, 100.25 ms in.
+        assert_eq!(audio.zero_crossing(ms(99), true), Some(ms(100)));
+    }
+}
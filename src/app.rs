@@ -1,38 +1,216 @@
 use chrono::Duration;
 use eframe::egui;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use eframe::egui::RichText;
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Instant, SystemTime};
 use tokio::sync::mpsc;
+use uuid::Uuid;
 
+use crate::analysis::DecodedAudio;
+use crate::audio::{
+    decode_source, AudioEffects, ChannelMode, SpeedMode, TrimSettings, EQUALIZER_BANDS,
+};
 use crate::component::colors::MfColors;
+use crate::component::karaoke::{karaoke_label, wipe_progress};
+use crate::component::waveform::{show_waveform, Waveform, WaveformState};
+use crate::convert;
+use crate::icons;
 use crate::icons::material_design_icons::MDI_CHECK;
 use crate::init::*;
-use crate::lyrics_editor::LyricsEditor;
+use crate::keymap::Keymap;
+use crate::lyrics_editor::{outline_armed_segment, LyricsEditor};
+use crate::metadata::{cover_art, embedded_lyrics};
+use crate::player::{PlayerClock, PlayerState};
+use crate::project::{read_project, write_project, Project, PROJECT_EXTENSION};
+use crate::tap_tempo::TapTempo;
+use crate::timestamp::{format_timestamp, parse_timestamp};
 
-use crate::lyrics::BabelLyrics;
-use crate::lyrics_editor::json_lyrics_file_loader;
+use crate::lyrics::{
+    align_segments, BabelLyrics, LineComparison, LyricsLine, LyricsSegment, TextDirection,
+    TranslationEntry, BABEL_LYRICS_VERSION,
+};
+use crate::lyrics_editor::{json_lyrics_file_loader, load_json_lyrics_file, lyrics_sets_loader};
 
-#[derive(PartialEq)]
-enum PlayerState {
-    Playing,
-    Paused,
-    Stopped,
-}
+/// Extensions offered by the audio file dialogs.
+const AUDIO_EXTENSIONS: [&str; 3] = ["mp3", "m4a", "aac"];
+
+/// Number of entries kept in each of the recent files lists.
+const MAX_RECENT_FILES: usize = 10;
+
+const RECENT_AUDIO_FILES_KEY: &str = "recent_audio_files";
+const RECENT_LYRICS_FILES_KEY: &str = "recent_lyrics_files";
+const SEEK_STEP_KEY: &str = "seek_step_seconds";
+const EDITOR_RECOVERY_KEY: &str = "lyrics_editor_recovery";
+const AUTOSAVE_INTERVAL_KEY: &str = "autosave_interval_seconds";
+const AUTOSAVE_DIRECTORY_KEY: &str = "autosave_directory";
+const KEYMAP_KEY: &str = "keymap";
+const EQUALIZER_KEY: &str = "equalizer";
+const FADE_LENGTH_KEY: &str = "fade_length_ms";
+const CROSSFADE_KEY: &str = "crossfade";
+const CROSSFADE_LENGTH_KEY: &str = "crossfade_length_seconds";
+const TIME_FORMAT_KEY: &str = "time_format";
+const TRIM_SILENCE_KEY: &str = "trim_silence";
+const WATCH_LYRICS_FILE_KEY: &str = "watch_lyrics_file";
+const PRE_ROLL_KEY: &str = "pre_roll_ms";
+const CAPTIONS_BACKGROUND_KEY: &str = "captions_background";
+
+/// Background offered for keying the captions window out of a video capture.
+const CHROMA_KEY_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 0, 255);
+
+/// Autosave intervals offered in the settings, in seconds.
+const AUTOSAVE_INTERVALS: [u64; 3] = [30, 60, 300];
+
+/// Name of the file the editor's lyrics are autosaved to in the autosave directory.
+const AUTOSAVE_FILE_NAME: &str = "babel_autosave.json";
+
+/// How far apart the end of the lyrics and the end of the audio can be, in seconds, before
+/// the lyrics are flagged as possibly being for another track or cut.
+const DURATION_MISMATCH_THRESHOLD: i64 = 20;
+
+/// Opacity of the lines disabled for export in the lyrics window, when they are dimmed.
+const DISABLED_LINE_OPACITY: f32 = 0.4;
+
+/// Space between the columns of the lyrics window in the column layout, in points.
+const LYRICS_COLUMN_SPACING: f32 = 16.0;
+
+/// How far past the end of the looped line the playhead can be, in milliseconds, for it to
+/// count as having run off the end rather than having been moved.
+const LINE_LOOP_TOLERANCE: i64 = 500;
+
+/// How long the sink keeps playing after a fade-out, so that the silence reaches the output
+/// before it is paused.
+const FADE_OUT_MARGIN: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Side of the cover art thumbnail in the audio details, in points.
+const COVER_ART_SIZE: f32 = 96.0;
+
+/// Longest crossfade between playlist tracks offered in the playlist, in seconds.
+const MAX_CROSSFADE_LENGTH: f32 = 12.0;
+
+/// Width of the output level meter, in points.
+const LEVEL_METER_WIDTH: f32 = 120.0;
+
+/// Level at the left end of the output level meter, in dBFS.
+const LEVEL_METER_FLOOR_DB: f32 = -60.0;
+
+/// How long the clip light stays lit after the output clips.
+const CLIP_HOLD: std::time::Duration = std::time::Duration::from_millis(1500);
 
+/// Frame rates offered for timecode display, in frames per second.
+const TIMECODE_FRAME_RATES: [u32; 5] = [24, 25, 30, 50, 60];
+
+/// How often the watched lyrics file is checked for changes.
+const LYRICS_WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Seek steps offered for the left/right arrow keys, in seconds.
+const SEEK_STEPS: [i64; 3] = [1, 5, 10];
+
+/// Seek step of Shift + left/right arrow, in seconds.
+const FINE_SEEK_STEP: i64 = 1;
+
+/// How long after the start of a line stepping back still goes to the line before it, in
+/// milliseconds.
+const PREVIOUS_LINE_GRACE_MS: i64 = 1000;
+
+/// The path, name, size, duration and sample rate of loaded audio, and the trim settings its
+/// source plays with.
 type AudioDetails = (
     Option<String>,
     Option<String>,
     Option<usize>,
     Option<Duration>,
+    Option<u32>,
+    Arc<Mutex<TrimSettings>>,
 );
 
+/// What a background audio load needs to hand the audio over to the app and the sink.
+struct AudioLoader {
+    /// Id of this load, which its results are tagged with.
+    id: u64,
+    arc_latest_id: Arc<Mutex<u64>>,
+    arc_loading_file: Arc<Mutex<bool>>,
+    details_tx: mpsc::Sender<(u64, AudioDetails)>,
+    data_tx: mpsc::Sender<(u64, Vec<u8>)>,
+    error_tx: mpsc::Sender<String>,
+    arc_sink: Arc<Mutex<Sink>>,
+    audio_effects: AudioEffects,
+}
+
+impl AudioLoader {
+    /// Starts this load, so that the results of any load still running are dropped.
+    fn start(&mut self) {
+        let mut latest_id = self.arc_latest_id.lock().unwrap();
+        *latest_id += 1;
+        self.id = *latest_id;
+    }
+
+    /// Whether no other audio load has started since this one.
+    fn is_latest(&self) -> bool {
+        *self.arc_latest_id.lock().unwrap() == self.id
+    }
+}
+
+/// What is worked out from the samples of the loaded audio once it is decoded.
+struct AudioAnalysis {
+    waveform: Waveform,
+
+    /// Gain that normalizes the loudness of the audio, in dB.
+    gain_db: f32,
+    decoded_audio: Arc<DecodedAudio>,
+
+    /// Lengths of the silence at the start and at the end of the audio.
+    edge_silences: (Duration, Duration),
+
+    /// Whether the silences are trimmed off `waveform` and `decoded_audio`.
+    trimmed: bool,
+}
+
+/// The next track of the playlist, already appended to the sink so that it starts without a gap.
+struct QueuedTrack {
+    index: usize,
+    data: Vec<u8>,
+    total_duration: Option<Duration>,
+    sample_rate: u32,
+
+    /// Trim settings of the track, which become the player's once it is switched to.
+    trim: Arc<Mutex<TrimSettings>>,
+
+    /// Whether the track is in the sink after the current one, as opposed to waiting to be
+    /// crossfaded in on a sink of its own.
+    appended: bool,
+}
+
+/// The next playlist track fading in on its own sink while the current track fades out.
+///
+/// The current track stays the one the player follows until it has played to the end, at
+/// which point this sink replaces the player's sink.
+struct Crossfade {
+    sink: Sink,
+    start: Instant,
+    length: std::time::Duration,
+}
+
 pub struct BabelPlayerApp {
-    audio_details_tx: mpsc::Sender<AudioDetails>,
-    audio_details_rx: mpsc::Receiver<AudioDetails>,
+    /// Id of the latest audio load. Results of the loaded audio are tagged with the id of its
+    /// load, so that those of an earlier load arriving late are told apart and dropped.
+    arc_audio_load_id: Arc<Mutex<u64>>,
+
+    audio_details_tx: mpsc::Sender<(u64, AudioDetails)>,
+    audio_details_rx: mpsc::Receiver<(u64, AudioDetails)>,
+
+    audio_data_tx: mpsc::Sender<(u64, Vec<u8>)>,
+    audio_data_rx: mpsc::Receiver<(u64, Vec<u8>)>,
 
-    audio_data_tx: mpsc::Sender<Vec<u8>>,
-    audio_data_rx: mpsc::Receiver<Vec<u8>>,
+    audio_error_tx: mpsc::Sender<String>,
+    audio_error_rx: mpsc::Receiver<String>,
+
+    /// The error from the last failed audio load, if any.
+    audio_error: Option<String>,
 
     selected_file: Option<String>,
     file_name: Option<String>,
@@ -46,51 +224,272 @@ pub struct BabelPlayerApp {
     lyrics_details_rx: mpsc::Receiver<(Option<String>, Option<String>)>,
     lyrics_data_tx: mpsc::Sender<BabelLyrics>,
     lyrics_data_rx: mpsc::Receiver<BabelLyrics>,
+    lyrics_error_tx: mpsc::Sender<String>,
+    lyrics_error_rx: mpsc::Receiver<String>,
+
+    /// The error from the last failed lyrics load, if any.
+    lyrics_error: Option<String>,
 
     arc_loading_lyrics: Arc<Mutex<bool>>,
     lyrics: Option<BabelLyrics>,
     selected_lyrics_file: Option<String>,
     lyrics_file_name: Option<String>,
 
+    /// Whether the selected lyrics file is reloaded whenever it changes on disk.
+    watch_lyrics_file: bool,
+
+    /// The watched lyrics file and its modification time when it was last loaded.
+    watched_lyrics_file: Option<(String, SystemTime)>,
+    last_lyrics_watch: Option<Instant>,
+
+    /// Whether a change to the watched file is waiting for the editor's changes to be saved.
+    lyrics_reload_held: bool,
+
+    /// Lyrics imported together as `(path, lyrics)`, of which one at a time is shown.
+    lyrics_sets: Vec<(String, BabelLyrics)>,
+
+    /// Index into `lyrics_sets` of the lyrics currently shown, if they came from a set.
+    active_lyrics_set: Option<usize>,
+
+    lyrics_set_tx: mpsc::Sender<(String, BabelLyrics)>,
+    lyrics_set_rx: mpsc::Receiver<(String, BabelLyrics)>,
+
+    /// Lyrics loaded to compare the shown lyrics with, and the name of their file.
+    compared_lyrics: Option<BabelLyrics>,
+    compared_file_name: Option<String>,
+    compare_details_tx: mpsc::Sender<(Option<String>, Option<String>)>,
+    compare_details_rx: mpsc::Receiver<(Option<String>, Option<String>)>,
+    compare_data_tx: mpsc::Sender<BabelLyrics>,
+    compare_data_rx: mpsc::Receiver<BabelLyrics>,
+
     _rodio_stream: OutputStream,
     _rodio_stream_handle: OutputStreamHandle,
     arc_rodio_sink: Arc<Mutex<Sink>>,
+    audio_effects: AudioEffects,
+
+    /// Names of the output devices available on the default host.
+    output_devices: Vec<String>,
+
+    /// The name of the output device in use, or `None` for the system default.
+    selected_output_device: Option<String>,
 
     total_duration: Option<Duration>,
 
-    /// Timestamp of the player.
-    ///
-    /// This is equal to `player_offset` + (`current_instant` - `player_start_instant`).
-    player_timestamp: Duration,
+    /// Sample rate of the loaded audio, in Hz.
+    sample_rate: Option<u32>,
 
-    /// The instant when the player started/resumed.
-    player_start_instant: Option<Instant>,
+    /// The outline of the loaded audio, once it has been decoded.
+    waveform: Option<Waveform>,
 
-    /// The offset of the timestamp of the player.
-    ///
-    /// This is used to calculate the timestamp after pausing and resuming.
-    player_offset: Duration,
+    /// Thumbnail of the artwork embedded in the loaded audio, if it has any.
+    cover_art: Option<egui::TextureHandle>,
+    cover_art_tx: mpsc::Sender<(u64, Option<egui::ColorImage>)>,
+    cover_art_rx: mpsc::Receiver<(u64, Option<egui::ColorImage>)>,
+
+    /// Synchronized lyrics found in the tags of the loaded audio, offered for import.
+    embedded_lyrics: Option<BabelLyrics>,
+    embedded_lyrics_tx: mpsc::Sender<(u64, Option<BabelLyrics>)>,
+    embedded_lyrics_rx: mpsc::Receiver<(u64, Option<BabelLyrics>)>,
+
+    /// The samples of the loaded audio, kept for snapping lyrics timings to zero-crossings.
+    decoded_audio: Option<Arc<DecodedAudio>>,
+
+    /// Whether the silence at the start and end of the audio is skipped, with timestamps
+    /// counted from the first sound.
+    trim_silence: bool,
+
+    /// Lengths of the silence found at the start and end of the loaded audio.
+    edge_silences: Option<(Duration, Duration)>,
+    waveform_state: WaveformState,
+    show_waveform: bool,
+    decoding_waveform: bool,
+    /// Results of analyzing the loaded audio.
+    waveform_tx: mpsc::Sender<(u64, Result<AudioAnalysis, String>)>,
+    waveform_rx: mpsc::Receiver<(u64, Result<AudioAnalysis, String>)>,
+
+    player: PlayerClock,
+
+    /// Start of the A/B loop region, set with the `A` key.
+    loop_start: Option<Duration>,
+
+    /// End of the A/B loop region, set with the `B` key.
+    loop_end: Option<Duration>,
 
-    player_state: PlayerState,
+    /// Where playback pauses when auditioning a single line from the editor.
+    audition_end: Option<Duration>,
+
+    /// Where the player goes back to once a word preview ends, and whether it was playing.
+    audition_return: Option<(Duration, bool)>,
+
+    /// Whether playback keeps replaying the lyric line under the playhead.
+    loop_line: bool,
+
+    /// How long before the start of a loop or audition playback starts, in milliseconds, to
+    /// give some lead-in.
+    pre_roll_ms: i64,
+
+    /// The `(begin, end)` of the line being looped.
+    looped_line: Option<(Duration, Duration)>,
+
+    /// When the sink is paused after the fade-out of a pause.
+    fade_out_end: Option<Instant>,
+
+    /// Whether loop markers snap to the nearest segment boundary of the loaded lyrics.
+    snap_loop_to_segments: bool,
+
+    repeat_mode: RepeatMode,
+
+    /// How the transport display shows the position of the player.
+    time_format: TimeFormat,
 
     show_main_lyrics_window: bool,
     show_captions_window: bool,
+
+    /// How many lines after the active one the captions window shows, dimmed, as a lead-in.
+    captions_upcoming_lines: usize,
+
+    /// Whether the captions window shows the label of the marker it is in between lines.
+    captions_show_markers: bool,
+
+    /// How long the captions take to glide into place when the active line changes, in
+    /// milliseconds, or 0 to jump straight there.
+    captions_transition_ms: u64,
+    captions_easing: Easing,
+
+    /// The line the captions last showed as active, and when it became active.
+    captions_active_line: Option<(Uuid, Instant)>,
+
+    /// A solid or translucent fill for the captions window, which is then drawn without a
+    /// border or shadow, or `None` for the usual window look.
+    captions_background: Option<egui::Color32>,
+
+    /// Whether the lyrics window only shows the active line and the lines around it.
+    lyrics_focus_mode: bool,
+
+    /// How many lines before and after the active line the focus mode shows.
+    lyrics_focus_context: usize,
+
+    /// Whether lines disabled for export are dimmed in the lyrics window.
+    dim_disabled_lines: bool,
+
+    /// Whether the lyrics window shows the ruby readings of segments above them.
+    show_ruby: bool,
+
+    /// Whether the lyrics window puts each translation in a column beside the original,
+    /// instead of below it.
+    lyrics_columns: bool,
+
+    /// Translation languages hidden from the lyrics and captions windows, by id. Languages
+    /// of newly loaded lyrics are shown until unticked.
+    hidden_translations: HashSet<Uuid>,
+    show_mini_player: bool,
+
+    /// Whether the lyrics and captions windows show the editor's lyrics as they are edited.
+    live_preview: bool,
+
+    /// How far the seek keys seek, in seconds.
+    seek_step_seconds: i64,
+
+    keymap: Keymap,
+
+    /// The binding of `keymap` waiting for a key press to be remapped, by its index in
+    /// [`Keymap::bindings_mut`].
+    remapping_binding: Option<usize>,
+
+    /// How often the editor's lyrics are autosaved, in seconds.
+    autosave_interval_seconds: u64,
+
+    /// A directory the editor's lyrics are also autosaved to as JSON, besides the app storage.
+    autosave_directory: Option<String>,
+
+    last_autosave: Option<Instant>,
+
+    /// The error from the last failed autosave to the autosave directory, if any.
+    autosave_error: Option<String>,
+
+    autosave_directory_tx: mpsc::Sender<String>,
+    autosave_directory_rx: mpsc::Receiver<String>,
+
+    /// Query of the lyrics search box in the main window.
+    lyrics_search_query: String,
+
+    lyrics_find: LyricsFind,
+
+    /// Text of the "Go to" field, and why it could not be parsed.
+    goto_input: String,
+    goto_error: Option<String>,
+
+    /// Paths of recently opened audio files, most recent first.
+    recent_audio_files: Vec<String>,
+
+    /// Paths of recently opened lyrics files, most recent first.
+    recent_lyrics_files: Vec<String>,
+
+    playlist: Vec<PathBuf>,
+
+    /// Index of the playlist track that is currently loaded, if it was opened from the playlist.
+    playlist_index: Option<usize>,
+
+    queued_track: Option<QueuedTrack>,
+
+    /// Whether the next playlist track is being read, so that it is only requested once.
+    queueing_track: bool,
+
+    /// Playlist track that could not be read or decoded, which is not requested again until
+    /// another track is opened.
+    failed_track: Option<usize>,
+
+    /// Taps of the "Tap" button next to the metronome tempo.
+    tap_tempo: TapTempo,
+
+    /// Whether playlist tracks overlap, the current one fading out as the next fades in.
+    crossfade: bool,
+    crossfade_seconds: f32,
+    active_crossfade: Option<Crossfade>,
+
+    playlist_add_tx: mpsc::Sender<Vec<PathBuf>>,
+    playlist_add_rx: mpsc::Receiver<Vec<PathBuf>>,
+
+    /// Contents of the next playlist track, along with its index and path at the time it was
+    /// requested.
+    next_track_tx: mpsc::Sender<(usize, PathBuf, Result<Vec<u8>, String>)>,
+    next_track_rx: mpsc::Receiver<(usize, PathBuf, Result<Vec<u8>, String>)>,
+
+    /// Opened project archives, with the file name of the archive.
+    project_tx: mpsc::Sender<(String, Project)>,
+    project_rx: mpsc::Receiver<(String, Project)>,
 }
 
 impl Default for BabelPlayerApp {
     fn default() -> Self {
         let (file_details_tx, file_details_rx) = mpsc::channel(32);
         let (file_data_tx, file_data_rx) = mpsc::channel(32);
+        let (audio_error_tx, audio_error_rx) = mpsc::channel(32);
         let (lyrics_details_tx, lyrics_details_rx) = mpsc::channel(32);
         let (lyrics_data_tx, lyrics_data_rx) = mpsc::channel(32);
+        let (lyrics_error_tx, lyrics_error_rx) = mpsc::channel(32);
+        let (lyrics_set_tx, lyrics_set_rx) = mpsc::channel(32);
+        let (compare_details_tx, compare_details_rx) = mpsc::channel(32);
+        let (compare_data_tx, compare_data_rx) = mpsc::channel(32);
+        let (playlist_add_tx, playlist_add_rx) = mpsc::channel(32);
+        let (next_track_tx, next_track_rx) = mpsc::channel(32);
+        let (project_tx, project_rx) = mpsc::channel(32);
+        let (waveform_tx, waveform_rx) = mpsc::channel(32);
+        let (cover_art_tx, cover_art_rx) = mpsc::channel(32);
+        let (embedded_lyrics_tx, embedded_lyrics_rx) = mpsc::channel(32);
+        let (autosave_directory_tx, autosave_directory_rx) = mpsc::channel(32);
         let (stream, stream_handle) = OutputStream::try_default().unwrap();
         let sink = Sink::try_new(&stream_handle).unwrap();
 
         BabelPlayerApp {
+            arc_audio_load_id: Arc::new(Mutex::new(0)),
             audio_details_tx: file_details_tx,
             audio_details_rx: file_details_rx,
             audio_data_tx: file_data_tx,
             audio_data_rx: file_data_rx,
+            audio_error_tx,
+            audio_error_rx,
+            audio_error: None,
             selected_file: None,
             file_name: None,
             file_size: None,
@@ -101,20 +500,108 @@ impl Default for BabelPlayerApp {
             lyrics_details_rx,
             lyrics_data_tx,
             lyrics_data_rx,
+            lyrics_error_tx,
+            lyrics_error_rx,
+            lyrics_error: None,
             arc_loading_lyrics: Arc::new(Mutex::new(false)),
             lyrics: None,
             selected_lyrics_file: None,
             lyrics_file_name: None,
-            player_timestamp: Duration::zero(),
-            player_start_instant: None,
-            player_offset: Duration::zero(),
-            player_state: PlayerState::Stopped,
+            watch_lyrics_file: false,
+            watched_lyrics_file: None,
+            last_lyrics_watch: None,
+            lyrics_reload_held: false,
+            lyrics_sets: Vec::new(),
+            active_lyrics_set: None,
+            lyrics_set_tx,
+            lyrics_set_rx,
+            compared_lyrics: None,
+            compared_file_name: None,
+            compare_details_tx,
+            compare_details_rx,
+            compare_data_tx,
+            compare_data_rx,
+            player: PlayerClock::default(),
+            loop_start: None,
+            loop_end: None,
+            audition_end: None,
+            audition_return: None,
+            fade_out_end: None,
+            loop_line: false,
+            pre_roll_ms: 0,
+            looped_line: None,
+            snap_loop_to_segments: false,
+            repeat_mode: RepeatMode::Off,
+            time_format: TimeFormat::Clock,
+            lyrics_search_query: String::new(),
+            lyrics_find: LyricsFind::default(),
+            goto_input: String::new(),
+            goto_error: None,
+            seek_step_seconds: 5,
+            keymap: Keymap::default(),
+            remapping_binding: None,
+            autosave_interval_seconds: AUTOSAVE_INTERVALS[0],
+            autosave_directory: None,
+            last_autosave: None,
+            autosave_error: None,
+            autosave_directory_tx,
+            autosave_directory_rx,
             show_main_lyrics_window: false,
             show_captions_window: false,
+            captions_upcoming_lines: 2,
+            captions_show_markers: true,
+            captions_transition_ms: 300,
+            captions_easing: Easing::EaseOut,
+            captions_active_line: None,
+            captions_background: None,
+            lyrics_focus_mode: false,
+            lyrics_focus_context: 2,
+            dim_disabled_lines: true,
+            show_ruby: true,
+            lyrics_columns: false,
+            hidden_translations: HashSet::new(),
+            show_mini_player: false,
+            live_preview: false,
+            recent_audio_files: Vec::new(),
+            recent_lyrics_files: Vec::new(),
+            playlist: Vec::new(),
+            playlist_index: None,
+            queued_track: None,
+            queueing_track: false,
+            failed_track: None,
+            tap_tempo: TapTempo::default(),
+            crossfade: false,
+            crossfade_seconds: 5.0,
+            active_crossfade: None,
+            playlist_add_tx,
+            playlist_add_rx,
+            next_track_tx,
+            next_track_rx,
+            project_tx,
+            project_rx,
             _rodio_stream: stream,
             _rodio_stream_handle: stream_handle,
             arc_rodio_sink: Arc::new(Mutex::new(sink)),
+            audio_effects: AudioEffects::default(),
+            output_devices: list_output_devices(),
+            selected_output_device: None,
             total_duration: None,
+            sample_rate: None,
+            waveform: None,
+            cover_art: None,
+            cover_art_tx,
+            cover_art_rx,
+            embedded_lyrics: None,
+            embedded_lyrics_tx,
+            embedded_lyrics_rx,
+            decoded_audio: None,
+            trim_silence: false,
+            edge_silences: None,
+            waveform_state: WaveformState::default(),
+            show_waveform: false,
+            decoding_waveform: false,
+            waveform_tx,
+            waveform_rx,
         }
     }
 }
@@ -124,12 +611,115 @@ impl BabelPlayerApp {
         setup_custom_fonts(&cc.egui_ctx);
         setup_custom_styles(&cc.egui_ctx);
 
-        Default::default()
+        let mut app: Self = Default::default();
+        if let Some(storage) = cc.storage {
+            app.recent_audio_files =
+                eframe::get_value(storage, RECENT_AUDIO_FILES_KEY).unwrap_or_default();
+            app.recent_lyrics_files =
+                eframe::get_value(storage, RECENT_LYRICS_FILES_KEY).unwrap_or_default();
+            if let Some(seek_step_seconds) = eframe::get_value(storage, SEEK_STEP_KEY) {
+                app.seek_step_seconds = seek_step_seconds;
+            }
+            if let Some(keymap) = eframe::get_value(storage, KEYMAP_KEY) {
+                app.keymap = keymap;
+            }
+            if let Some(equalizer) = eframe::get_value(storage, EQUALIZER_KEY) {
+                *app.audio_effects.equalizer.lock().unwrap() = equalizer;
+            }
+            if let Some(fade_length_ms) = eframe::get_value(storage, FADE_LENGTH_KEY) {
+                app.audio_effects.fade.lock().unwrap().length =
+                    std::time::Duration::from_millis(fade_length_ms);
+            }
+            if let Some(trim_silence) = eframe::get_value(storage, TRIM_SILENCE_KEY) {
+                app.trim_silence = trim_silence;
+            }
+            if let Some(pre_roll_ms) = eframe::get_value(storage, PRE_ROLL_KEY) {
+                app.pre_roll_ms = pre_roll_ms;
+            }
+            if let Some(watch_lyrics_file) = eframe::get_value(storage, WATCH_LYRICS_FILE_KEY) {
+                app.watch_lyrics_file = watch_lyrics_file;
+            }
+            if let Some(time_format) = eframe::get_value(storage, TIME_FORMAT_KEY) {
+                app.time_format = time_format;
+            }
+            if let Some(captions_background) = eframe::get_value(storage, CAPTIONS_BACKGROUND_KEY) {
+                app.captions_background = captions_background;
+            }
+            if let Some(crossfade) = eframe::get_value(storage, CROSSFADE_KEY) {
+                app.crossfade = crossfade;
+            }
+            if let Some(crossfade_seconds) = eframe::get_value(storage, CROSSFADE_LENGTH_KEY) {
+                app.crossfade_seconds = crossfade_seconds;
+            }
+            if let Some(interval) = eframe::get_value(storage, AUTOSAVE_INTERVAL_KEY) {
+                app.autosave_interval_seconds = interval;
+            }
+            app.autosave_directory =
+                eframe::get_value::<Option<String>>(storage, AUTOSAVE_DIRECTORY_KEY).flatten();
+            app.lyrics_editor.recovered_lyrics =
+                eframe::get_value::<Option<BabelLyrics>>(storage, EDITOR_RECOVERY_KEY).flatten();
+            if let Some(ref mut lyrics) = app.lyrics_editor.recovered_lyrics {
+                // The serde defaults already bring older stored lyrics to the current shape.
+                lyrics.version = BABEL_LYRICS_VERSION;
+            }
+        }
+        app.prune_recent_files();
+        app
     }
 }
 
 impl eframe::App for BabelPlayerApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, RECENT_AUDIO_FILES_KEY, &self.recent_audio_files);
+        eframe::set_value(storage, RECENT_LYRICS_FILES_KEY, &self.recent_lyrics_files);
+        eframe::set_value(storage, SEEK_STEP_KEY, &self.seek_step_seconds);
+        eframe::set_value(storage, KEYMAP_KEY, &self.keymap);
+        eframe::set_value(
+            storage,
+            EQUALIZER_KEY,
+            &*self.audio_effects.equalizer.lock().unwrap(),
+        );
+        eframe::set_value(
+            storage,
+            FADE_LENGTH_KEY,
+            &(self.audio_effects.fade.lock().unwrap().length.as_millis() as u64),
+        );
+        eframe::set_value(storage, TIME_FORMAT_KEY, &self.time_format);
+        eframe::set_value(storage, TRIM_SILENCE_KEY, &self.trim_silence);
+        eframe::set_value(storage, WATCH_LYRICS_FILE_KEY, &self.watch_lyrics_file);
+        eframe::set_value(storage, PRE_ROLL_KEY, &self.pre_roll_ms);
+        eframe::set_value(storage, CROSSFADE_KEY, &self.crossfade);
+        eframe::set_value(storage, CAPTIONS_BACKGROUND_KEY, &self.captions_background);
+        eframe::set_value(storage, CROSSFADE_LENGTH_KEY, &self.crossfade_seconds);
+        eframe::set_value(
+            storage,
+            AUTOSAVE_INTERVAL_KEY,
+            &self.autosave_interval_seconds,
+        );
+        eframe::set_value(storage, AUTOSAVE_DIRECTORY_KEY, &self.autosave_directory);
+        // Called every `auto_save_interval` as well as on exit, so this doubles as the editor's
+        // autosave.
+        self.autosave(storage);
+    }
+
+    fn auto_save_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.autosave_interval_seconds)
+    }
+
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.advance_playback(ctx);
+        self.handle_transport_keys(ctx);
+        self.handle_tap_key(ctx);
+        self.handle_loop_keys(ctx);
+        self.handle_seek_keys(ctx);
+        self.handle_line_step_keys(ctx);
+        self.lyrics_editor.show_recovery_prompt(ctx);
+        self.update_playlist();
+        self.finish_fade_out(ctx);
+        self.show_status_bar(ctx);
+        self.reload_changed_lyrics_file(ctx);
+        self.preview_editor_lyrics();
+
         egui::Window::new("Babel Player")
             .collapsible(true)
             .resizable(true)
@@ -138,29 +728,118 @@ impl eframe::App for BabelPlayerApp {
                 ui.add_enabled_ui(!loading_file, |ui| {
                     self.show_audio_file_picker(ui, loading_file);
                 });
-                if let Ok((selected_file, file_name, file_size, total_duration)) =
-                    self.audio_details_rx.try_recv()
+                if let Some(details) =
+                    latest_of_load(&mut self.audio_details_rx, &self.arc_audio_load_id)
                 {
+                    let (selected_file, file_name, file_size, total_duration, sample_rate, trim) =
+                        details;
+                    self.audio_effects.trim = trim;
                     *self.arc_loading_file.lock().unwrap() = false;
+                    // The sink holds only the new audio, which plays from its start.
+                    self.player.reset();
+                    if let Some(ref path) = selected_file {
+                        // Audio from a project has no path of its own to reopen.
+                        if Path::new(path).is_file() {
+                            push_recent_file(&mut self.recent_audio_files, path);
+                        }
+                    }
                     self.selected_file = selected_file;
                     self.file_name = file_name;
                     self.file_size = file_size;
                     self.total_duration = total_duration;
+                    self.sample_rate = sample_rate;
                 }
 
-                if let Ok(file_data) = self.audio_data_rx.try_recv() {
+                if let Some((project_name, project)) = latest(&mut self.project_rx) {
+                    self.load_project(project_name, project);
+                }
+
+                if let Some(file_data) =
+                    latest_of_load(&mut self.audio_data_rx, &self.arc_audio_load_id)
+                {
+                    self.analyze_audio(file_data.clone());
                     self.file_data = Some(file_data);
+                    self.audio_error = None;
+                    // Without lyrics, the window offers to import or create them.
+                    if self.lyrics.is_none() {
+                        self.show_main_lyrics_window = true;
+                    }
+                }
+
+                if let Some(analysis) =
+                    latest_of_load(&mut self.waveform_rx, &self.arc_audio_load_id)
+                {
+                    self.decoding_waveform = false;
+                    match analysis {
+                        Ok(analysis) => {
+                            self.audio_effects.loudness.lock().unwrap().measured_gain_db =
+                                Some(analysis.gain_db);
+                            self.apply_silence_trim(&analysis);
+                            self.waveform = Some(analysis.waveform);
+                            self.decoded_audio = Some(analysis.decoded_audio);
+                        }
+                        Err(e) => self.audio_error = Some(e),
+                    }
+                }
+
+                if let Some(image) = latest_of_load(&mut self.cover_art_rx, &self.arc_audio_load_id)
+                {
+                    self.cover_art = image.map(|image| {
+                        ctx.load_texture("cover_art", image, egui::TextureOptions::LINEAR)
+                    });
+                }
+
+                if let Some(lyrics) =
+                    latest_of_load(&mut self.embedded_lyrics_rx, &self.arc_audio_load_id)
+                {
+                    self.embedded_lyrics = lyrics;
+                }
+
+                if let Some(audio_error) = latest(&mut self.audio_error_rx) {
+                    *self.arc_loading_file.lock().unwrap() = false;
+                    self.audio_error = Some(audio_error);
+                }
+
+                if let Some(ref audio_error) = self.audio_error {
+                    ui.colored_label(MfColors::RED_400, audio_error);
                 }
 
                 ui.separator();
 
                 self.show_audio_file_details_grid(ui);
 
+                self.show_playlist(ui);
+
+                self.show_output_device_selector(ui);
+
+                self.show_channel_controls(ui);
+                self.show_loudness_controls(ui);
+                self.show_equalizer_controls(ui);
+                self.show_fade_controls(ui);
+                self.show_trim_controls(ui);
+
+                self.show_metronome_controls(ui);
+                self.show_speed_controls(ui);
+
                 ui.separator();
 
                 ui.horizontal(|ui| {
                     ui.toggle_value(&mut self.lyrics_editor.show_lyrics_editor, "Lyrics editor");
+                    ui.toggle_value(&mut self.show_mini_player, "Mini player");
                     ui.add_enabled_ui(self.lyrics_editor.lyrics.is_some(), |ui| {
+                        if ui
+                            .toggle_value(&mut self.live_preview, "Live preview")
+                            .on_hover_text("Show the lyrics being edited as they change")
+                            .changed()
+                            && self.live_preview
+                        {
+                            self.selected_lyrics_file = Some("From editor".to_string());
+                            self.lyrics_file_name = Some("From editor".to_string());
+                            self.active_lyrics_set = None;
+                            self.lyrics_error = None;
+                            self.show_main_lyrics_window = true;
+                            self.show_captions_window = true;
+                        }
                         if ui.button("Load from editor").clicked() {
                             let lyrics = self.lyrics_editor.lyrics.clone().unwrap();
                             let lyrics_data_tx = self.lyrics_data_tx.clone();
@@ -177,19 +856,77 @@ impl eframe::App for BabelPlayerApp {
                         }
                     });
                 });
+                self.show_autosave_settings(ui, frame);
+                self.show_keymap_settings(ui);
 
                 let loading_file = *self.arc_loading_lyrics.lock().unwrap();
                 ui.horizontal(|ui| {
                     ui.add_enabled_ui(!loading_file, |ui| {
                         if ui.button("Select lyrics file").clicked() {
-                            let details_tx = self.lyrics_details_tx.clone();
-                            let data_tx = self.lyrics_data_tx.clone();
+                            self.select_lyrics_file();
+                        }
+                        if ui
+                            .button("Import lyric sets")
+                            .on_hover_text("Load several TTML or JSON files to switch between")
+                            .clicked()
+                        {
+                            let set_tx = self.lyrics_set_tx.clone();
+                            let error_tx = self.lyrics_error_tx.clone();
                             let arc_loading_lyrics = self.arc_loading_lyrics.clone();
                             tokio::spawn(async move {
-                                json_lyrics_file_loader(arc_loading_lyrics, details_tx, data_tx)
-                                    .await;
+                                lyrics_sets_loader(arc_loading_lyrics, set_tx, error_tx).await;
+                            });
+                        }
+                        if ui
+                            .add_enabled(self.lyrics.is_some(), egui::Button::new("Unload lyrics"))
+                            .on_hover_text("Close the loaded lyrics and their windows")
+                            .clicked()
+                        {
+                            self.unload_lyrics();
+                        }
+                        if ui
+                            .add_enabled(self.lyrics.is_some(), egui::Button::new("Compare with…"))
+                            .on_hover_text(
+                                "Load another lyrics file and show how it differs from these \
+                                 lyrics",
+                            )
+                            .clicked()
+                        {
+                            let details_tx = self.compare_details_tx.clone();
+                            let data_tx = self.compare_data_tx.clone();
+                            let error_tx = self.lyrics_error_tx.clone();
+                            let arc_loading_lyrics = self.arc_loading_lyrics.clone();
+                            tokio::spawn(async move {
+                                json_lyrics_file_loader(
+                                    arc_loading_lyrics,
+                                    details_tx,
+                                    data_tx,
+                                    error_tx,
+                                )
+                                .await;
                             });
                         }
+                        if let Some(ref lyrics) = self.embedded_lyrics {
+                            if ui
+                                .button("Import embedded lyrics")
+                                .on_hover_text("Load the synchronized lyrics in the audio's tags")
+                                .clicked()
+                            {
+                                let lyrics = lyrics.clone();
+                                let name = format!(
+                                    "Embedded in {}",
+                                    self.file_name.as_deref().unwrap_or("audio")
+                                );
+                                let lyrics_data_tx = self.lyrics_data_tx.clone();
+                                let lyrics_details_tx = self.lyrics_details_tx.clone();
+                                tokio::spawn(async move {
+                                    let _ = lyrics_data_tx.send(lyrics).await;
+                                    let _ = lyrics_details_tx
+                                        .send((Some(name.clone()), Some(name)))
+                                        .await;
+                                });
+                            }
+                        }
                     });
 
                     if loading_file {
@@ -199,182 +936,285 @@ impl eframe::App for BabelPlayerApp {
                     }
                 });
 
-                if let Ok((selected_lyrics_file, lyrics_file_name)) =
-                    self.lyrics_details_rx.try_recv()
+                self.show_lyrics_watch_settings(ui);
+                self.show_lyrics_sets(ui);
+
+                if let Some((selected_lyrics_file, lyrics_file_name)) =
+                    latest(&mut self.lyrics_details_rx)
                 {
                     *self.arc_loading_lyrics.lock().unwrap() = false;
+                    if let Some(ref path) = selected_lyrics_file {
+                        if Path::new(path).is_file() {
+                            push_recent_file(&mut self.recent_lyrics_files, path);
+                        }
+                    }
                     self.selected_lyrics_file = selected_lyrics_file;
                     self.lyrics_file_name = lyrics_file_name;
+                    self.active_lyrics_set = None;
+                    // Lyrics loaded from elsewhere take the place of the preview.
+                    self.live_preview = false;
+                }
+
+                while let Ok((path, lyrics)) = self.lyrics_set_rx.try_recv() {
+                    self.lyrics_sets.push((path, lyrics));
+                    if self.active_lyrics_set.is_none() {
+                        self.activate_lyrics_set(self.lyrics_sets.len() - 1);
+                    }
                 }
 
-                if let Ok(lyrics) = self.lyrics_data_rx.try_recv() {
+                if let Some(lyrics) = latest(&mut self.lyrics_data_rx) {
                     self.lyrics = Some(lyrics);
+                    self.lyrics_error = None;
                     self.show_main_lyrics_window = true;
                     self.show_captions_window = true;
                 }
 
+                if let Some(lyrics_error) = latest(&mut self.lyrics_error_rx) {
+                    *self.arc_loading_lyrics.lock().unwrap() = false;
+                    self.lyrics_error = Some(lyrics_error);
+                }
+
+                if let Some(ref lyrics_error) = self.lyrics_error {
+                    ui.colored_label(MfColors::RED_400, lyrics_error);
+                }
+
                 ui.separator();
 
                 self.show_lyrics_file_details_grid(ui);
 
-                if self.lyrics.is_some() {
-                    ui.checkbox(&mut self.show_main_lyrics_window, "Main lyrics window");
+                ui.checkbox(&mut self.show_main_lyrics_window, "Main lyrics window");
+                ui.horizontal(|ui| {
                     ui.checkbox(&mut self.show_captions_window, "Captions window");
+                    if self.show_captions_window {
+                        ui.add(
+                            egui::DragValue::new(&mut self.captions_upcoming_lines)
+                                .range(0..=3)
+                                .suffix(" upcoming lines"),
+                        )
+                        .on_hover_text("Lines after the active one, shown dimmed");
+                        ui.checkbox(&mut self.captions_show_markers, "Markers")
+                            .on_hover_text(
+                                "Show \"♪ (instrumental)\" and the like between \
+                                 lines",
+                            );
+                    }
+                });
+                if self.show_captions_window {
+                    self.show_captions_transition_settings(ui);
+                    self.show_captions_background_settings(ui);
+                }
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.lyrics_focus_mode, "Focus mode")
+                        .on_hover_text("Only show the active line and the lines around it");
+                    if self.lyrics_focus_mode {
+                        ui.add(
+                            egui::DragValue::new(&mut self.lyrics_focus_context)
+                                .range(0..=10)
+                                .suffix(" lines of context"),
+                        );
+                    }
+                });
+                ui.checkbox(&mut self.lyrics_columns, "Translations in columns")
+                    .on_hover_text("Show each translation in a column beside the original");
+                ui.checkbox(&mut self.dim_disabled_lines, "Dim disabled lines")
+                    .on_hover_text("Dim the lines left out of exports in the lyrics window");
+                ui.checkbox(&mut self.show_ruby, "Show ruby readings")
+                    .on_hover_text("Show readings such as furigana in small text above segments");
+                self.show_translation_toggles(ui);
+                if self.lyrics.is_some() {
+                    self.show_lyrics_search(ui);
                 }
 
                 ui.separator();
 
                 ui.horizontal(|ui| {
-                    let original_timestamp = self.player_timestamp.num_milliseconds();
-                    let mut timestamp_ms = self.player_timestamp.num_milliseconds();
-                    ui.add(
+                    let original_timestamp = self.player.timestamp.num_milliseconds();
+                    let mut timestamp_ms = self.player.timestamp.num_milliseconds();
+                    // Scrubbing is only possible once the length of the audio is known.
+                    let total_ms = self.total_duration.map(|d| d.num_milliseconds().max(0));
+                    let time_format = self.time_format;
+                    ui.add_enabled(
+                        total_ms.is_some(),
                         egui::DragValue::new(&mut timestamp_ms)
                             .speed(100.0)
-                            .range(
-                                0.0..=self
-                                    .total_duration
-                                    .map(|d| d.num_milliseconds() as f64)
-                                    .unwrap_or(i64::MAX as f64),
-                            )
+                            .range(0..=total_ms.unwrap_or(0))
                             .custom_formatter(|n, _| {
-                                format!(
-                                    "{}:{:02}:{:02}.{:03}",
-                                    n as i64 / 3_600_000,
-                                    (n as i64 / 60_000) % 60,
-                                    (n as i64 / 1_000) % 60,
-                                    n as i64 % 1_000
-                                )
+                                time_format.format(Duration::milliseconds(n as i64))
+                            })
+                            .custom_parser(|text| {
+                                time_format
+                                    .parse(text)
+                                    .map(|timestamp| timestamp.num_milliseconds() as f64)
                             }),
-                    );
-                    self.player_timestamp = Duration::milliseconds(timestamp_ms);
-
-                    if self.player_state == PlayerState::Playing {
-                        self.player_offset +=
-                            Duration::milliseconds(timestamp_ms - original_timestamp);
-                    } else if self.player_state == PlayerState::Paused
-                        || self.player_state == PlayerState::Stopped
-                    {
-                        self.player_offset = self.player_timestamp;
+                    )
+                    .on_disabled_hover_text("The length of the audio is unknown");
+                    if timestamp_ms != original_timestamp {
+                        // Seek the sink right away, even while paused, so that the audio
+                        // position always matches the readout.
+                        self.seek_to(Duration::milliseconds(timestamp_ms));
                     }
 
                     ui.colored_label(MfColors::GRAY_500, "/");
-                    if self.total_duration.is_some() {
-                        ui.label(format!(
-                            "{}:{:02}:{:02}.{:03}",
-                            self.total_duration.unwrap().num_hours(),
-                            self.total_duration.unwrap().num_minutes() % 60,
-                            self.total_duration.unwrap().num_seconds() % 60,
-                            self.total_duration.unwrap().num_milliseconds() % 1000
-                        ));
+                    if let Some(total_duration) = self.total_duration {
+                        ui.label(self.time_format.format(total_duration));
                     } else {
                         ui.label("???");
                     }
+                    self.show_time_format_selector(ui);
                 });
+                self.show_level_meter(ui);
+                self.show_goto_timestamp(ui);
+                self.show_line_stepper(ui);
+
+                self.show_loop_controls(ui);
+                self.show_waveform(ui);
 
-                match self.player_state {
+                match self.player.state {
                     PlayerState::Stopped => {
-                        if ui.button("Play").clicked() {
-                            self.player_state = PlayerState::Playing;
-                            self.player_start_instant = Some(Instant::now());
-
-                            let _ = self
-                                .arc_rodio_sink
-                                .lock()
-                                .unwrap()
-                                .try_seek(self.player_timestamp.to_std().unwrap());
-                            self.arc_rodio_sink.lock().unwrap().play();
-                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Play").clicked() {
+                                self.play();
+                            }
+
+                            if ui
+                                .add_enabled(
+                                    self.player.timestamp > Duration::zero(),
+                                    egui::Button::new("Reset"),
+                                )
+                                .on_hover_text("Rewind to the start")
+                                .clicked()
+                            {
+                                self.reset();
+                            }
+                        });
                     }
                     PlayerState::Paused => {
                         ui.horizontal(|ui| {
                             if ui.button("Resume").clicked() {
-                                self.player_state = PlayerState::Playing;
-                                self.player_start_instant = Some(Instant::now());
-                                let _ = self
-                                    .arc_rodio_sink
-                                    .lock()
-                                    .unwrap()
-                                    .try_seek(self.player_timestamp.to_std().unwrap());
-                                self.arc_rodio_sink.lock().unwrap().play();
+                                self.play();
+                            }
+
+                            if ui
+                                .button("Stop")
+                                .on_hover_text("Stop playback, keeping the current position")
+                                .clicked()
+                            {
+                                self.stop();
                             }
 
-                            if ui.button("Reset").clicked() {
-                                self.player_state = PlayerState::Stopped;
-                                self.player_timestamp = Duration::zero();
-                                self.player_offset = Duration::zero();
-                                self.player_start_instant = None;
-                                self.arc_rodio_sink.lock().unwrap().pause();
-                                let _ = self
-                                    .arc_rodio_sink
-                                    .lock()
-                                    .unwrap()
-                                    .try_seek(std::time::Duration::from_secs(0));
+                            if ui
+                                .button("Reset")
+                                .on_hover_text("Stop playback and rewind to the start")
+                                .clicked()
+                            {
+                                self.reset();
                             }
                         });
                     }
                     PlayerState::Playing => {
-                        self.player_timestamp = self.player_offset
-                            + Duration::milliseconds(
-                                self.player_start_instant
-                                    .map(|start_instant| {
-                                        (Instant::now() - start_instant).as_millis() as i64
-                                    })
-                                    .unwrap_or(0),
-                            );
-
                         ui.horizontal(|ui| {
                             if ui.button("Pause").clicked() {
-                                self.player_state = PlayerState::Paused;
-                                self.player_offset = self.player_timestamp;
-                                self.arc_rodio_sink.lock().unwrap().pause();
+                                self.pause();
                             }
 
-                            if ui.button("Reset").clicked() {
-                                self.player_state = PlayerState::Stopped;
-                                self.player_timestamp = Duration::zero();
-                                self.player_offset = Duration::zero();
-                                self.player_start_instant = None;
-                                self.arc_rodio_sink.lock().unwrap().pause();
-                                let _ = self
-                                    .arc_rodio_sink
-                                    .lock()
-                                    .unwrap()
-                                    .try_seek(std::time::Duration::from_secs(0));
+                            if ui
+                                .button("Stop")
+                                .on_hover_text("Stop playback, keeping the current position")
+                                .clicked()
+                            {
+                                self.stop();
                             }
-                        });
 
-                        ctx.request_repaint();
+                            if ui
+                                .button("Reset")
+                                .on_hover_text("Stop playback and rewind to the start")
+                                .clicked()
+                            {
+                                self.reset();
+                            }
+                        });
                     }
                 }
             });
 
         if self.show_main_lyrics_window {
-            self.show_lyrics_window(ctx, self.lyrics.as_ref().unwrap());
+            // The find bar is taken out while the window borrows the rest of the app.
+            let mut find = std::mem::take(&mut self.lyrics_find);
+            let request = self.show_lyrics_window(ctx, self.lyrics.as_ref(), &mut find);
+            self.lyrics_find = find;
+            match request {
+                Some(LyricsWindowRequest::Seek(timestamp)) => self.seek_to(timestamp),
+                Some(LyricsWindowRequest::Preview(begin, end)) => self.preview(begin, end),
+                Some(LyricsWindowRequest::Import) => self.select_lyrics_file(),
+                Some(LyricsWindowRequest::Create) => {
+                    self.lyrics_editor.start_new_lyrics(self.total_duration)
+                }
+                None => {}
+            }
         }
         if self.lyrics_editor.show_lyrics_editor {
-            self.lyrics_editor.show_lyrics_editor_window(ctx).unwrap();
+            self.lyrics_editor
+                .show_lyrics_editor_window(
+                    ctx,
+                    self.player.timestamp,
+                    self.file_data.as_deref(),
+                    self.decoded_audio.as_deref(),
+                )
+                .unwrap();
+            if let Some((begin, end)) = self.lyrics_editor.audition_request.take() {
+                self.seek_to(self.pre_rolled(begin));
+                self.play();
+                self.audition_end = Some(end);
+                self.audition_return = None;
+            }
         }
         if self.show_captions_window {
+            let transition_remaining = self.captions_transition_remaining(ctx);
+            let frame = match self.captions_background {
+                Some(color) => egui::Frame::window(&ctx.style())
+                    .fill(color)
+                    .stroke(egui::Stroke::NONE)
+                    .shadow(egui::epaint::Shadow::NONE),
+                None => egui::Frame::window(&ctx.style()),
+            };
             egui::Window::new("Captions")
                 .title_bar(false)
+                .frame(frame)
                 .show(ctx, |ui| {
                     ui.style_mut().spacing.item_spacing = egui::vec2(0.0, 0.0);
-                    for line in &self.lyrics.as_ref().unwrap().lyrics.lines {
-                        let current_time = self.player_timestamp;
-                        if current_time > line.begin && current_time < line.end {
+                    // A new line starts a row lower, where it was shown as upcoming, and glides
+                    // up into place.
+                    if transition_remaining > 0.0 {
+                        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                        ui.add_space(row_height * transition_remaining);
+                    }
+                    if self.lyrics.is_none() {
+                        ui.colored_label(MfColors::GRAY_500, "No lyrics loaded");
+                    }
+                    for (line, direction) in self.lyrics.iter().flat_map(|lyrics| {
+                        lyrics
+                            .lyrics
+                            .lines
+                            .iter()
+                            .map(|line| (line, lyrics.line_direction(line)))
+                    }) {
+                        let current_time = self.player.timestamp;
+                        if line.is_active_at(current_time) {
                             let mut current_translations_index_vec = Vec::new();
-                            ui.horizontal(|ui| {
-                                for segment in &line.original {
-                                    if current_time > segment.begin && current_time < segment.end {
-                                        ui.colored_label(MfColors::ORANGE_500, &segment.text);
-                                        current_translations_index_vec
-                                            .extend(segment.translations.clone());
-                                    } else {
-                                        ui.label(&segment.text);
-                                    }
+                            show_segments(ui, line, direction, |ui, _, segment| {
+                                if segment.is_active_at(current_time) {
+                                    segment_karaoke_label(ui, segment, current_time, direction);
+                                    current_translations_index_vec
+                                        .extend(segment.translations.clone());
+                                } else {
+                                    let color = segment_color(segment, ui.visuals().text_color());
+                                    segment_label(ui, segment, color);
                                 }
                             });
                             for (id, words) in &line.translations {
+                                if self.hidden_translations.contains(id) {
+                                    continue;
+                                }
                                 let language_translations_index_vec =
                                     current_translations_index_vec
                                         .iter()
@@ -402,166 +1242,3467 @@ impl eframe::App for BabelPlayerApp {
                             }
                         }
                     }
+
+                    let current_time = self.player.timestamp;
+                    if let Some(lyrics) =
+                        self.lyrics.as_ref().filter(|_| self.captions_show_markers)
+                    {
+                        let in_line = lyrics
+                            .lyrics
+                            .lines
+                            .iter()
+                            .any(|line| line.is_active_at(current_time));
+                        if let Some(marker) = lyrics.marker_at(current_time).filter(|_| !in_line) {
+                            ui.colored_label(MfColors::GRAY_500, format!("♪ ({})", marker.label));
+                        }
+                    }
+
+                    // Upcoming lines fade out the further ahead they are.
+                    let upcoming = self
+                        .lyrics
+                        .iter()
+                        .flat_map(|lyrics| {
+                            lyrics
+                                .lyrics
+                                .lines
+                                .iter()
+                                .map(|line| (line, lyrics.line_direction(line)))
+                        })
+                        .filter(|(line, _)| line.begin > current_time)
+                        .take(self.captions_upcoming_lines);
+                    for (index, (line, direction)) in upcoming.enumerate() {
+                        let brightness =
+                            1.0 - (index + 1) as f32 / (self.captions_upcoming_lines + 1) as f32;
+                        show_segments(ui, line, direction, |ui, _, segment| {
+                            let color = segment_color(segment, ui.visuals().text_color());
+                            segment_label(ui, segment, color.gamma_multiply(brightness));
+                        });
+                    }
                 });
         }
+        if self.show_mini_player {
+            self.show_mini_player(ctx);
+        }
+        self.show_compare_window(ctx);
     }
 }
 
 impl BabelPlayerApp {
-    fn show_audio_file_picker(&mut self, ui: &mut egui::Ui, loading_file: bool) {
-        ui.horizontal(|ui| {
-            if ui.button("Select Audio File").clicked() {
-                let details_tx = self.audio_details_tx.clone();
-                let data_tx = self.audio_data_tx.clone();
-                let arc_loading_file = self.arc_loading_file.clone();
-                let arc_sink = self.arc_rodio_sink.clone();
-                tokio::spawn(async move {
-                    audio_file_loader(arc_loading_file, details_tx, data_tx, arc_sink).await;
+    /// Saves the editor's lyrics for recovery to `storage`, and to the autosave directory if
+    /// one is set.
+    fn autosave(&mut self, storage: &mut dyn eframe::Storage) {
+        let lyrics = self.lyrics_editor.recovery_lyrics();
+        eframe::set_value(storage, EDITOR_RECOVERY_KEY, &lyrics);
+        if let (Some(directory), Some(lyrics)) = (&self.autosave_directory, lyrics) {
+            let path = Path::new(directory).join(AUTOSAVE_FILE_NAME);
+            let result = convert::babel_to_json(lyrics)
+                .and_then(|json| std::fs::write(&path, json).map_err(anyhow::Error::from));
+            self.autosave_error = result
+                .err()
+                .map(|e| format!("Failed to autosave to {}: {}", path.display(), e));
+        }
+        self.last_autosave = Some(Instant::now());
+    }
+
+    /// Lists the keyboard shortcuts, each of which can be remapped by clicking it and pressing
+    /// the new key.
+    fn show_keymap_settings(&mut self, ui: &mut egui::Ui) {
+        if let Some(index) = self.remapping_binding {
+            // The key press is consumed so that it does not also activate the focused button.
+            let pressed = ui.input_mut(|i| {
+                let pressed = i.events.iter().find_map(|event| match event {
+                    egui::Event::Key {
+                        key, pressed: true, ..
+                    } => Some(*key),
+                    _ => None,
                 });
+                if let Some(key) = pressed {
+                    i.events.retain(
+                        |event| !matches!(event, egui::Event::Key { key: x, .. } if *x == key),
+                    );
+                }
+                pressed
+            });
+            match pressed {
+                Some(egui::Key::Escape) => self.remapping_binding = None,
+                Some(key) => {
+                    self.keymap.rebind(index, key);
+                    self.remapping_binding = None;
+                }
+                None => {}
             }
-            if loading_file {
-                ui.spinner();
-            } else if let Some(ref selected_file) = self.selected_file {
-                ui.label(selected_file);
+        }
+
+        ui.collapsing("Keyboard shortcuts", |ui| {
+            let remapping_binding = self.remapping_binding;
+            let mut start_remapping = None;
+            egui::Grid::new("keymap_grid").show(ui, |ui| {
+                for (index, (action, key)) in self.keymap.bindings_mut().into_iter().enumerate() {
+                    ui.label(action);
+                    let text = if remapping_binding == Some(index) {
+                        RichText::new("Press a key…").color(MfColors::ORANGE_500)
+                    } else {
+                        RichText::new(key.name())
+                    };
+                    if ui
+                        .button(text)
+                        .on_hover_text("Click, then press the new key, or Escape to cancel")
+                        .clicked()
+                    {
+                        start_remapping = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+            if start_remapping.is_some() {
+                self.remapping_binding = start_remapping;
+            }
+            if ui
+                .add_enabled(
+                    self.keymap != Keymap::default(),
+                    egui::Button::new("Reset to defaults"),
+                )
+                .clicked()
+            {
+                self.keymap = Keymap::default();
+                self.remapping_binding = None;
             }
         });
     }
 
-    fn show_audio_file_details_grid(&mut self, ui: &mut egui::Ui) {
-        egui::Grid::new("audio_file_details_grid").show(ui, |ui| {
-            ui.label("File name");
-            ui.label(self.file_name.as_deref().unwrap_or("-"));
-            ui.end_row();
+    fn show_autosave_settings(&mut self, ui: &mut egui::Ui, frame: &mut eframe::Frame) {
+        ui.collapsing("Autosave", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Interval");
+                egui::ComboBox::from_id_source("autosave_interval_combo_box")
+                    .selected_text(format_interval(self.autosave_interval_seconds))
+                    .show_ui(ui, |ui| {
+                        for interval in AUTOSAVE_INTERVALS {
+                            ui.selectable_value(
+                                &mut self.autosave_interval_seconds,
+                                interval,
+                                format_interval(interval),
+                            );
+                        }
+                    });
+            });
+            ui.horizontal(|ui| {
+                ui.label("Directory");
+                if ui.button("Choose").clicked() {
+                    let directory_tx = self.autosave_directory_tx.clone();
+                    tokio::spawn(async move {
+                        if let Some(directory) = rfd::FileDialog::new().pick_folder() {
+                            let _ = directory_tx
+                                .send(directory.to_string_lossy().to_string())
+                                .await;
+                        }
+                    });
+                }
+                if let Ok(directory) = self.autosave_directory_rx.try_recv() {
+                    self.autosave_directory = Some(directory);
+                    self.autosave_error = None;
+                }
+                match self.autosave_directory {
+                    Some(ref directory) => {
+                        ui.label(directory);
+                        if ui.button("Clear").clicked() {
+                            self.autosave_directory = None;
+                            self.autosave_error = None;
+                        }
+                    }
+                    None => {
+                        ui.colored_label(MfColors::GRAY_500, "App storage only");
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Save now").clicked() {
+                    if let Some(storage) = frame.storage_mut() {
+                        self.autosave(storage);
+                        storage.flush();
+                    }
+                }
+                match self.last_autosave {
+                    Some(last_autosave) => ui.colored_label(
+                        MfColors::GRAY_500,
+                        format!("Last saved {}s ago", last_autosave.elapsed().as_secs()),
+                    ),
+                    None => ui.colored_label(MfColors::GRAY_500, "Not saved yet"),
+                };
+            });
+            if let Some(ref autosave_error) = self.autosave_error {
+                ui.colored_label(MfColors::RED_400, autosave_error);
+            }
+        });
+    }
+
+    /// Shows the exact position of the playhead and the line and segment under it.
+    fn show_status_bar(&self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let timestamp = self.player.timestamp;
+                ui.monospace(self.time_format.format(timestamp));
+                ui.separator();
+                ui.monospace(format!("{} ms", timestamp.num_milliseconds()));
+                if let Some(sample_rate) = self.sample_rate {
+                    ui.separator();
+                    ui.monospace(format!(
+                        "sample {}",
+                        timestamp.num_milliseconds() * sample_rate as i64 / 1000
+                    ));
+                }
+                ui.separator();
+
+                let active_line = self.lyrics.as_ref().and_then(|lyrics| {
+                    lyrics
+                        .lyrics
+                        .lines
+                        .iter()
+                        .position(|line| line.is_active_at(timestamp))
+                        .map(|index| (index, &lyrics.lyrics.lines[index]))
+                });
+                match active_line {
+                    Some((line_index, line)) => {
+                        let segment_index = line
+                            .original
+                            .iter()
+                            .position(|segment| segment.is_active_at(timestamp));
+                        ui.monospace(match segment_index {
+                            Some(segment_index) => {
+                                format!("lines[{}].original[{}]", line_index, segment_index)
+                            }
+                            None => format!("lines[{}]", line_index),
+                        });
+                    }
+                    None => {
+                        ui.colored_label(MfColors::GRAY_500, "No active line");
+                    }
+                }
+            });
+        });
+    }
+
+    /// Shows how the compared lyrics differ from the shown ones, line by line and segment by
+    /// segment, while lyrics to compare with are loaded. Clicking a timestamp seeks to it.
+    fn show_compare_window(&mut self, ctx: &egui::Context) {
+        if let Some((_, file_name)) = latest(&mut self.compare_details_rx) {
+            *self.arc_loading_lyrics.lock().unwrap() = false;
+            self.compared_file_name = file_name;
+        }
+        if let Some(lyrics) = latest(&mut self.compare_data_rx) {
+            self.compared_lyrics = Some(lyrics);
+        }
+        let (Some(current), Some(compared)) = (&self.lyrics, &self.compared_lyrics) else {
+            return;
+        };
+
+        let mut open = true;
+        let mut seek = None;
+        egui::Window::new("Compare lyrics")
+            .open(&mut open)
+            .default_height(400.0)
+            .show(ctx, |ui| {
+                let diff = current.diff_lines(compared);
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} compared with {}:",
+                        self.lyrics_file_name.as_deref().unwrap_or("Current lyrics"),
+                        self.compared_file_name.as_deref().unwrap_or("other lyrics")
+                    ));
+                    ui.colored_label(MfColors::GREEN_400, format!("{} added", diff.added));
+                    ui.colored_label(MfColors::RED_400, format!("{} removed", diff.removed));
+                    ui.colored_label(MfColors::YELLOW_400, format!("{} changed", diff.changed));
+                });
+                ui.separator();
+                let text_color = ui.visuals().text_color();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("compare_lyrics_grid")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("Current");
+                            ui.label("Compared");
+                            ui.end_row();
+                            for comparison in current.compare_lines(compared) {
+                                let row = match comparison {
+                                    LineComparison::Same { old, new, .. }
+                                    | LineComparison::Edited { old, new } => {
+                                        let (old_colors, new_colors) =
+                                            compared_segment_colors(old, new, text_color);
+                                        let time_color = if old.begin == new.begin {
+                                            text_color
+                                        } else {
+                                            MfColors::YELLOW_400
+                                        };
+                                        [
+                                            show_compared_line(
+                                                ui,
+                                                Some(old),
+                                                time_color,
+                                                |index| old_colors[index],
+                                            ),
+                                            show_compared_line(
+                                                ui,
+                                                Some(new),
+                                                time_color,
+                                                |index| new_colors[index],
+                                            ),
+                                        ]
+                                    }
+                                    LineComparison::Removed(old) => [
+                                        show_compared_line(
+                                            ui,
+                                            Some(old),
+                                            MfColors::RED_400,
+                                            |_| MfColors::RED_400,
+                                        ),
+                                        show_compared_line(ui, None, text_color, |_| text_color),
+                                    ],
+                                    LineComparison::Added(new) => [
+                                        show_compared_line(ui, None, text_color, |_| text_color),
+                                        show_compared_line(
+                                            ui,
+                                            Some(new),
+                                            MfColors::GREEN_400,
+                                            |_| MfColors::GREEN_400,
+                                        ),
+                                    ],
+                                };
+                                seek = seek.or(row[0]).or(row[1]);
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+        if !open {
+            self.compared_lyrics = None;
+            self.compared_file_name = None;
+        }
+        if let Some(timestamp) = seek {
+            self.seek_to(timestamp);
+        }
+    }
+
+    /// Shows a small always-on-top window with the current line, a progress bar and the
+    /// transport buttons.
+    fn show_mini_player(&mut self, ctx: &egui::Context) {
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("mini_player"),
+            egui::ViewportBuilder::default()
+                .with_title("Babel Player")
+                .with_inner_size([360.0, 110.0])
+                .with_always_on_top(),
+            |ctx, _class| {
+                if ctx.input(|i| i.viewport().close_requested()) {
+                    self.show_mini_player = false;
+                }
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    let current_time = self.player.timestamp;
+                    let current_line = self.lyrics.as_ref().and_then(|lyrics| {
+                        lyrics
+                            .lyrics
+                            .lines
+                            .iter()
+                            .find(|line| line.is_active_at(current_time))
+                            .map(|line| (line, lyrics.line_direction(line)))
+                    });
+                    ui.horizontal(|ui| {
+                        ui.style_mut().spacing.item_spacing = egui::vec2(0.0, 0.0);
+                        let Some((line, direction)) = current_line else {
+                            ui.colored_label(MfColors::GRAY_500, "-");
+                            return;
+                        };
+                        show_segments(ui, line, direction, |ui, _, segment| {
+                            if segment.is_active_at(current_time) {
+                                segment_karaoke_label(ui, segment, current_time, direction);
+                            } else {
+                                let color = segment_color(segment, ui.visuals().text_color());
+                                segment_label(ui, segment, color);
+                            }
+                        });
+                    });
+
+                    let progress = self.total_duration.map_or(0.0, |total_duration| {
+                        wipe_progress(Duration::zero(), total_duration, current_time)
+                    });
+                    ui.add(egui::ProgressBar::new(progress).text(format!(
+                        "{} / {}",
+                        self.time_format.format(current_time),
+                        self.total_duration.map_or("???".to_string(), |total_duration| self
+                            .time_format
+                            .format(total_duration))
+                    )));
+
+                    ui.horizontal(|ui| match self.player.state {
+                        PlayerState::Playing => {
+                            if ui.button(icons::material_design_icons::MDI_PAUSE).clicked() {
+                                self.pause();
+                            }
+                            if ui.button(icons::material_design_icons::MDI_STOP).clicked() {
+                                self.stop();
+                            }
+                        }
+                        PlayerState::Paused | PlayerState::Stopped => {
+                            if ui.button(icons::material_design_icons::MDI_PLAY).clicked() {
+                                self.play();
+                            }
+                            if ui
+                                .button(icons::material_design_icons::MDI_SKIP_PREVIOUS)
+                                .on_hover_text("Reset")
+                                .clicked()
+                            {
+                                self.reset();
+                            }
+                        }
+                    });
+                });
+            },
+        );
+    }
+
+    fn show_audio_file_picker(&mut self, ui: &mut egui::Ui, loading_file: bool) {
+        ui.horizontal(|ui| {
+            self.show_recent_files_menu(ui);
+            if ui.button("Select Audio File").clicked() {
+                self.leave_playlist();
+                let loader = self.audio_loader();
+                tokio::spawn(async move {
+                    audio_file_loader(loader).await;
+                });
+            }
+            if ui
+                .button("Open project")
+                .on_hover_text("Load the audio and lyrics bundled in a project archive")
+                .clicked()
+            {
+                self.open_project();
+            }
+            let lyrics = self.lyrics_editor.lyrics.as_ref().or(self.lyrics.as_ref());
+            if ui
+                .add_enabled(
+                    self.file_data.is_some() && lyrics.is_some(),
+                    egui::Button::new("Save project"),
+                )
+                .on_hover_text(
+                    "Bundle the audio with the lyrics being edited, or the loaded lyrics, into \
+                     one file",
+                )
+                .on_disabled_hover_text("Load both audio and lyrics first")
+                .clicked()
+            {
+                self.save_project();
+            }
+            if ui
+                .add_enabled(self.file_data.is_some(), egui::Button::new("Unload"))
+                .on_hover_text("Stop playback and close the loaded audio")
+                .clicked()
+            {
+                self.unload_audio();
+            }
+            if loading_file {
+                ui.spinner();
+            } else if let Some(ref selected_file) = self.selected_file {
+                ui.label(selected_file);
+            }
+        });
+    }
+
+    /// Starts a new audio load, whose results replace those of any load still running, and
+    /// returns its id.
+    fn start_audio_load(&self) -> u64 {
+        let mut latest_id = self.arc_audio_load_id.lock().unwrap();
+        *latest_id += 1;
+        *latest_id
+    }
+
+    /// The id of the latest audio load.
+    fn audio_load_id(&self) -> u64 {
+        *self.arc_audio_load_id.lock().unwrap()
+    }
+
+    /// A new audio load, for [`load_audio_file`] to carry out in the background once it is
+    /// started with [`AudioLoader::start`].
+    fn audio_loader(&self) -> AudioLoader {
+        AudioLoader {
+            id: 0,
+            arc_latest_id: self.arc_audio_load_id.clone(),
+            arc_loading_file: self.arc_loading_file.clone(),
+            details_tx: self.audio_details_tx.clone(),
+            data_tx: self.audio_data_tx.clone(),
+            error_tx: self.audio_error_tx.clone(),
+            arc_sink: self.arc_rodio_sink.clone(),
+            // The new file is trimmed by its own settings, not those of the current one.
+            audio_effects: self.audio_effects.with_trim(Arc::default()),
+        }
+    }
+
+    /// Stops playback and forgets the loaded audio and everything worked out from it, as if
+    /// none had been loaded. Loads still running are dropped once they finish.
+    fn unload_audio(&mut self) {
+        self.start_audio_load();
+        *self.arc_loading_file.lock().unwrap() = false;
+        self.leave_playlist();
+        self.player.reset();
+        self.arc_rodio_sink.lock().unwrap().clear();
+        self.audition_end = None;
+        self.audition_return = None;
+        self.loop_start = None;
+        self.loop_end = None;
+
+        self.selected_file = None;
+        self.file_name = None;
+        self.file_size = None;
+        self.file_data = None;
+        self.total_duration = None;
+        self.sample_rate = None;
+        self.audio_error = None;
+        self.waveform = None;
+        self.decoded_audio = None;
+        self.decoding_waveform = false;
+        self.cover_art = None;
+        self.embedded_lyrics = None;
+        self.edge_silences = None;
+        self.audio_effects.loudness.lock().unwrap().measured_gain_db = None;
+        self.audio_effects.trim = Arc::default();
+    }
+
+    /// Forgets the shown lyrics, including imported lyric sets and lyrics being compared, and
+    /// closes the windows that show them. The lyrics editor keeps its own lyrics.
+    fn unload_lyrics(&mut self) {
+        *self.arc_loading_lyrics.lock().unwrap() = false;
+        self.lyrics = None;
+        self.selected_lyrics_file = None;
+        self.lyrics_file_name = None;
+        self.lyrics_error = None;
+        self.lyrics_sets.clear();
+        self.active_lyrics_set = None;
+        self.live_preview = false;
+        self.watched_lyrics_file = None;
+        self.lyrics_reload_held = false;
+        self.compared_lyrics = None;
+        self.compared_file_name = None;
+        self.hidden_translations.clear();
+        self.looped_line = None;
+        self.captions_active_line = None;
+        self.show_main_lyrics_window = false;
+        self.show_captions_window = false;
+    }
+
+    /// Lets the user pick a project archive, whose audio and lyrics are loaded once it has
+    /// been read.
+    fn open_project(&self) {
+        let project_tx = self.project_tx.clone();
+        let error_tx = self.audio_error_tx.clone();
+        let arc_loading_file = self.arc_loading_file.clone();
+        tokio::spawn(async move {
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("Babel Player project", &[PROJECT_EXTENSION])
+                .pick_file()
+            else {
+                return;
+            };
+            *arc_loading_file.lock().unwrap() = true;
+            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+            let result = tokio::fs::read(&path)
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|data| read_project(&data));
+            match result {
+                Ok(project) => {
+                    let _ = project_tx.send((file_name, project)).await;
+                }
+                Err(e) => {
+                    let _ = error_tx
+                        .send(format!("Failed to open project {}: {}", file_name, e))
+                        .await;
+                }
+            }
+        });
+    }
+
+    /// Replaces the loaded audio and lyrics with those of a project opened from the archive
+    /// named `project_name`.
+    fn load_project(&mut self, project_name: String, project: Project) {
+        let trim = Arc::<Mutex<TrimSettings>>::default();
+        let effects = self.audio_effects.with_trim(trim.clone());
+        let source = match decode_source(project.audio.clone(), &effects) {
+            Ok(source) => source,
+            Err(e) => {
+                *self.arc_loading_file.lock().unwrap() = false;
+                self.audio_error = Some(format!(
+                    "Unsupported audio file {} in {}: {}",
+                    project.audio_file_name, project_name, e
+                ));
+                return;
+            }
+        };
+        let details = (
+            Some(format!("{} in {}", project.audio_file_name, project_name)),
+            Some(project.audio_file_name),
+            Some(project.audio.len()),
+            source
+                .total_duration()
+                .and_then(|d| Duration::from_std(d).ok()),
+            Some(source.sample_rate()),
+            trim.clone(),
+        );
+        self.audio_effects.trim = trim;
+        self.leave_playlist();
+        self.player.reset();
+        let sink = self.arc_rodio_sink.lock().unwrap();
+        sink.clear();
+        sink.append(source);
+        sink.pause();
+        drop(sink);
+
+        let load_id = self.start_audio_load();
+        let lyrics_name = format!("Lyrics in {}", project_name);
+        let audio_details_tx = self.audio_details_tx.clone();
+        let audio_data_tx = self.audio_data_tx.clone();
+        let lyrics_data_tx = self.lyrics_data_tx.clone();
+        let lyrics_details_tx = self.lyrics_details_tx.clone();
+        tokio::spawn(async move {
+            let _ = audio_data_tx.send((load_id, project.audio)).await;
+            let _ = audio_details_tx.send((load_id, details)).await;
+            let _ = lyrics_data_tx.send(project.lyrics).await;
+            let _ = lyrics_details_tx
+                .send((Some(lyrics_name.clone()), Some(lyrics_name)))
+                .await;
+        });
+    }
+
+    /// Lets the user pick where to save the loaded audio, bundled with the lyrics being edited
+    /// or else the loaded lyrics, as a project archive.
+    fn save_project(&self) {
+        let (Some(audio), Some(lyrics)) = (
+            self.file_data.clone(),
+            self.lyrics_editor
+                .lyrics
+                .clone()
+                .or_else(|| self.lyrics.clone()),
+        ) else {
+            return;
+        };
+        let audio_file_name = self
+            .file_name
+            .clone()
+            .unwrap_or_else(|| "audio".to_string());
+        let project_file_name = format!(
+            "{}.{}",
+            Path::new(&audio_file_name)
+                .file_stem()
+                .map_or("project".into(), |stem| stem.to_string_lossy()),
+            PROJECT_EXTENSION
+        );
+        let error_tx = self.audio_error_tx.clone();
+        tokio::spawn(async move {
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("Babel Player project", &[PROJECT_EXTENSION])
+                .set_file_name(project_file_name)
+                .save_file()
+            else {
+                return;
+            };
+            let result = match write_project(&audio_file_name, &audio, &lyrics) {
+                Ok(archive) => tokio::fs::write(&path, archive)
+                    .await
+                    .map_err(anyhow::Error::from),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                let _ = error_tx
+                    .send(format!("Failed to save project: {}", e))
+                    .await;
+            }
+        });
+    }
+
+    fn show_recent_files_menu(&mut self, ui: &mut egui::Ui) {
+        let response = ui.menu_button("Recent", |ui| {
+            ui.label(RichText::new("Audio").color(MfColors::GRAY_500));
+            if self.recent_audio_files.is_empty() {
+                ui.label("-");
+            }
+            for path in self.recent_audio_files.clone() {
+                if ui.button(&path).clicked() {
+                    self.open_recent_audio_file(PathBuf::from(path));
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            ui.label(RichText::new("Lyrics").color(MfColors::GRAY_500));
+            if self.recent_lyrics_files.is_empty() {
+                ui.label("-");
+            }
+            for path in self.recent_lyrics_files.clone() {
+                if ui.button(&path).clicked() {
+                    self.open_recent_lyrics_file(PathBuf::from(path));
+                    ui.close_menu();
+                }
+            }
+        });
+        if response.response.clicked() {
+            self.prune_recent_files();
+        }
+    }
+
+    fn open_recent_audio_file(&mut self, path: PathBuf) {
+        self.leave_playlist();
+        let mut loader = self.audio_loader();
+        loader.start();
+        tokio::spawn(async move {
+            load_audio_file(path, loader).await;
+        });
+    }
+
+    fn show_playlist(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("Playlist", |ui| {
+            let mut to_play = None;
+            let mut to_remove = None;
+            for (index, path) in self.playlist.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    // The queued track is already in the sink and cannot be taken back out.
+                    let queued = self.queued_track.as_ref().map(|queued| queued.index);
+                    if ui
+                        .add_enabled(
+                            queued != Some(index),
+                            egui::Button::new(icons::material_design_icons::MDI_DELETE),
+                        )
+                        .clicked()
+                    {
+                        to_remove = Some(index);
+                    }
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    if ui
+                        .selectable_label(self.playlist_index == Some(index), name)
+                        .on_hover_text(path.to_string_lossy())
+                        .clicked()
+                    {
+                        to_play = Some(index);
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.crossfade, "Crossfade")
+                    .on_hover_text("Fade each track out while the next one fades in");
+                ui.add_enabled(
+                    self.crossfade,
+                    egui::Slider::new(&mut self.crossfade_seconds, 1.0..=MAX_CROSSFADE_LENGTH)
+                        .step_by(0.5)
+                        .suffix(" s"),
+                );
+            });
+            if ui.button("+ Add Files").clicked() {
+                let playlist_add_tx = self.playlist_add_tx.clone();
+                tokio::spawn(async move {
+                    if let Some(paths) = rfd::FileDialog::new()
+                        .add_filter("Audio Files", &AUDIO_EXTENSIONS)
+                        .pick_files()
+                    {
+                        let _ = playlist_add_tx.send(paths).await;
+                    }
+                });
+            }
+            if let Some(index) = to_play {
+                self.open_playlist_track(index);
+            } else if let Some(index) = to_remove {
+                self.remove_playlist_track(index);
+            }
+        });
+    }
+
+    /// Clears the sink and loads the playlist track at `index`.
+    fn open_playlist_track(&mut self, index: usize) {
+        self.arc_rodio_sink.lock().unwrap().clear();
+        self.player.reset();
+        self.playlist_index = Some(index);
+        self.queued_track = None;
+        self.queueing_track = false;
+        self.failed_track = None;
+        self.cancel_crossfade();
+
+        let path = self.playlist[index].clone();
+        let mut loader = self.audio_loader();
+        loader.start();
+        tokio::spawn(async move {
+            load_audio_file(path, loader).await;
+        });
+    }
+
+    fn remove_playlist_track(&mut self, index: usize) {
+        self.playlist.remove(index);
+        if self.playlist_index == Some(index) {
+            self.leave_playlist();
+            return;
+        }
+        // With repeat-all, the queued track may have wrapped around to before the current one,
+        // so each index is shifted on its own.
+        let shift = |track: usize| if track > index { track - 1 } else { track };
+        self.playlist_index = self.playlist_index.map(shift);
+        if self
+            .queued_track
+            .as_ref()
+            .is_some_and(|queued| queued.index == index)
+        {
+            self.drop_queued_track();
+        } else if let Some(ref mut queued) = self.queued_track {
+            queued.index = shift(queued.index);
+        }
+        self.failed_track = self
+            .failed_track
+            .filter(|failed| *failed != index)
+            .map(shift);
+    }
+
+    /// Forgets the queued playlist track so that the next one is queued in its place.
+    ///
+    /// A sink cannot drop a single source, so if the track was already appended, the current
+    /// track is moved to a new sink at the same position.
+    fn drop_queued_track(&mut self) {
+        self.cancel_crossfade();
+        let Some(queued) = self.queued_track.take() else {
+            return;
+        };
+        if !queued.appended {
+            return;
+        }
+        let sink = match Sink::try_new(&self._rodio_stream_handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                eprintln!("Failed to create sink: {}", e);
+                return;
+            }
+        };
+        sink.pause();
+        sink.set_speed(self.audio_effects.speed.lock().unwrap().sink_speed());
+        if let Some(ref file_data) = self.file_data {
+            match decode_source(file_data.clone(), &self.audio_effects) {
+                Ok(source) => {
+                    sink.append(source);
+                    let _ = sink.try_seek(self.sink_position());
+                }
+                Err(e) => {
+                    eprintln!("Failed to decode audio: {}", e);
+                }
+            }
+        }
+        if self.player.state == PlayerState::Playing {
+            // Restart the clock from the current position so it matches the new sink.
+            self.player.play(Instant::now());
+            sink.play();
+        }
+        *self.arc_rodio_sink.lock().unwrap() = sink;
+    }
+
+    /// Detaches the loaded audio from the playlist, e.g. when opening a file directly.
+    fn leave_playlist(&mut self) {
+        self.playlist_index = None;
+        self.queued_track = None;
+        self.queueing_track = false;
+        self.failed_track = None;
+        self.cancel_crossfade();
+    }
+
+    /// Handles playlist additions, queues the next track on the sink ahead of time, and moves
+    /// the player to the next track once the sink has started playing it.
+    fn update_playlist(&mut self) {
+        if let Ok(paths) = self.playlist_add_rx.try_recv() {
+            self.playlist.extend(paths);
+        }
+
+        if let Ok((index, path, data)) = self.next_track_rx.try_recv() {
+            self.queueing_track = false;
+            // Tracks removed while being read shift the index over to another track.
+            if self.next_playlist_index() == Some(index) && self.playlist[index] == path {
+                let data = match data {
+                    Ok(data) => data,
+                    Err(e) => {
+                        self.failed_track = Some(index);
+                        self.audio_error = Some(e);
+                        return;
+                    }
+                };
+                let trim = Arc::<Mutex<TrimSettings>>::default();
+                match decode_source(data.clone(), &self.audio_effects.with_trim(trim.clone())) {
+                    Ok(source) => {
+                        let total_duration = source
+                            .total_duration()
+                            .and_then(|d| Duration::from_std(d).ok());
+                        let sample_rate = source.sample_rate();
+                        // Crossfading needs to know when the current track ends.
+                        let appended = !self.crossfade || self.total_duration.is_none();
+                        if appended {
+                            self.arc_rodio_sink.lock().unwrap().append(source);
+                        }
+                        self.queued_track = Some(QueuedTrack {
+                            index,
+                            data,
+                            total_duration,
+                            sample_rate,
+                            trim,
+                            appended,
+                        });
+                    }
+                    Err(e) => {
+                        self.failed_track = Some(index);
+                        self.audio_error = Some(format!(
+                            "Unsupported audio file {}: {}",
+                            self.playlist[index].display(),
+                            e
+                        ));
+                    }
+                }
+            }
+        }
+
+        self.update_crossfade();
+
+        let sink_len = self.arc_rodio_sink.lock().unwrap().len();
+        let appended = self.queued_track.as_ref().map(|queued| queued.appended);
+        if appended == Some(true) && sink_len <= 1 {
+            // The current track has finished and the queued one is now playing.
+            let queued = self.queued_track.take().unwrap();
+            let path = &self.playlist[queued.index];
+            let path_str = path.to_string_lossy().to_string();
+            push_recent_file(&mut self.recent_audio_files, &path_str);
+            self.selected_file = Some(path_str);
+            self.file_name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string());
+            self.file_size = Some(queued.data.len());
+            self.total_duration = queued.total_duration;
+            self.sample_rate = Some(queued.sample_rate);
+            self.audio_effects.trim = queued.trim;
+            // Results still coming in for the previous track no longer apply.
+            self.start_audio_load();
+            self.analyze_audio(queued.data.clone());
+            self.file_data = Some(queued.data);
+            self.playlist_index = Some(queued.index);
+
+            let position = self.arc_rodio_sink.lock().unwrap().get_pos();
+            self.player.seek(
+                Duration::from_std(position).unwrap_or_else(|_| Duration::zero()),
+                Instant::now(),
+            );
+            self.loop_start = None;
+            self.loop_end = None;
+        }
+
+        let Some(next) = self.next_playlist_index() else {
+            return;
+        };
+        if self.queued_track.is_none()
+            && !self.queueing_track
+            && self.failed_track != Some(next)
+            && self.file_data.is_some()
+            && sink_len == 1
+        {
+            self.queueing_track = true;
+            let path = self.playlist[next].clone();
+            let next_track_tx = self.next_track_tx.clone();
+            tokio::spawn(async move {
+                let data = tokio::fs::read(&path)
+                    .await
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e));
+                let _ = next_track_tx.send((next, path, data)).await;
+            });
+        }
+    }
+
+    /// Starts crossfading into the queued track once the current one is that close to its end,
+    /// ramps the volumes of both sinks, and hands the player over to the queued track's sink
+    /// once the current track has played out.
+    fn update_crossfade(&mut self) {
+        if let Some(ref crossfade) = self.active_crossfade {
+            if self.player.state != PlayerState::Playing && self.fade_out_end.is_none() {
+                // Paused or stopped; the crossfade starts over from where playback resumes.
+                self.cancel_crossfade();
+                return;
+            }
+            let progress = (crossfade.start.elapsed().as_secs_f32()
+                / crossfade.length.as_secs_f32().max(f32::EPSILON))
+            .min(1.0);
+            crossfade.sink.set_volume(progress);
+            let mut sink = self.arc_rodio_sink.lock().unwrap();
+            sink.set_volume(1.0 - progress);
+            if sink.empty() {
+                let crossfade = self.active_crossfade.take().unwrap();
+                crossfade.sink.set_volume(1.0);
+                *sink = crossfade.sink;
+                drop(sink);
+                // Now in the sink the player follows, the track is switched to as usual.
+                if let Some(ref mut queued) = self.queued_track {
+                    queued.appended = true;
+                }
+            }
+            return;
+        }
+
+        let Some(ref mut queued) = self.queued_track else {
+            return;
+        };
+        if queued.appended {
+            return;
+        }
+        let Some(total_duration) = self.total_duration.filter(|_| self.crossfade) else {
+            // Crossfading was turned off since the track was queued.
+            match decode_source(
+                queued.data.clone(),
+                &self.audio_effects.with_trim(queued.trim.clone()),
+            ) {
+                Ok(source) => self.arc_rodio_sink.lock().unwrap().append(source),
+                Err(e) => {
+                    self.fail_queued_track(e);
+                    return;
+                }
+            }
+            queued.appended = true;
+            return;
+        };
+        if self.player.state != PlayerState::Playing {
+            return;
+        }
+
+        let speed = self.audio_effects.speed.lock().unwrap().speed as f64;
+        let remaining = (total_duration - self.player.timestamp)
+            .to_std()
+            .unwrap_or_default()
+            .div_f64(speed);
+        let length = std::time::Duration::from_secs_f32(self.crossfade_seconds);
+        if remaining > length {
+            return;
+        }
+        let sink = match Sink::try_new(&self._rodio_stream_handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                eprintln!("Failed to create sink: {}", e);
+                return;
+            }
+        };
+        sink.set_speed(self.audio_effects.speed.lock().unwrap().sink_speed());
+        sink.set_volume(0.0);
+        match decode_source(
+            queued.data.clone(),
+            &self.audio_effects.with_trim(queued.trim.clone()),
+        ) {
+            Ok(source) => sink.append(source),
+            Err(e) => {
+                self.fail_queued_track(e);
+                return;
+            }
+        }
+        sink.play();
+        self.active_crossfade = Some(Crossfade {
+            sink,
+            start: Instant::now(),
+            // Resuming close to the end crossfades over what is left of the track.
+            length: remaining,
+        });
+    }
+
+    /// Reports that the queued track could not be decoded and drops it, so that it is not
+    /// tried again until another track is opened.
+    fn fail_queued_track(&mut self, e: anyhow::Error) {
+        let Some(queued) = self.queued_track.take() else {
+            return;
+        };
+        self.failed_track = Some(queued.index);
+        self.audio_error = Some(format!(
+            "Unsupported audio file {}: {}",
+            self.playlist[queued.index].display(),
+            e
+        ));
+    }
+
+    /// Drops the sink of a crossfade in progress, leaving the queued track to fade in again.
+    fn cancel_crossfade(&mut self) {
+        if self.active_crossfade.take().is_some() {
+            self.arc_rodio_sink.lock().unwrap().set_volume(1.0);
+        }
+    }
+
+    /// The playlist track that plays after the current one, following the repeat mode.
+    fn next_playlist_index(&self) -> Option<usize> {
+        let current = self.playlist_index?;
+        match self.repeat_mode {
+            // The current track is repeated by seeking back once the sink runs dry.
+            RepeatMode::One => None,
+            RepeatMode::All => Some((current + 1) % self.playlist.len()),
+            RepeatMode::Off => Some(current + 1).filter(|next| *next < self.playlist.len()),
+        }
+    }
+
+    /// Lets the user pick a lyrics file and loads it into the player.
+    fn select_lyrics_file(&self) {
+        let details_tx = self.lyrics_details_tx.clone();
+        let data_tx = self.lyrics_data_tx.clone();
+        let error_tx = self.lyrics_error_tx.clone();
+        let arc_loading_lyrics = self.arc_loading_lyrics.clone();
+        tokio::spawn(async move {
+            json_lyrics_file_loader(arc_loading_lyrics, details_tx, data_tx, error_tx).await;
+        });
+    }
+
+    fn open_recent_lyrics_file(&mut self, path: PathBuf) {
+        let details_tx = self.lyrics_details_tx.clone();
+        let data_tx = self.lyrics_data_tx.clone();
+        let error_tx = self.lyrics_error_tx.clone();
+        let arc_loading_lyrics = self.arc_loading_lyrics.clone();
+        tokio::spawn(async move {
+            load_json_lyrics_file(path, arc_loading_lyrics, details_tx, data_tx, error_tx).await;
+        });
+    }
+
+    fn show_lyrics_watch_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.watch_lyrics_file, "Watch file")
+                .on_hover_text("Reload the lyrics file whenever it is changed in another editor");
+            if self.watch_lyrics_file && self.lyrics_reload_held {
+                ui.colored_label(
+                    MfColors::YELLOW_400,
+                    "Not reloaded while the lyrics editor has unsaved changes",
+                );
+            }
+        });
+    }
+
+    /// Keeps the shown lyrics in step with the editor's while live preview is on.
+    fn preview_editor_lyrics(&mut self) {
+        if !self.live_preview {
+            return;
+        }
+        let Some(ref edited) = self.lyrics_editor.lyrics else {
+            return;
+        };
+        if self.lyrics.as_ref() != Some(edited) {
+            self.lyrics = Some(edited.clone());
+        }
+    }
+
+    /// Reloads the selected lyrics file through `lyrics_data_tx` once its modification time
+    /// changes, checking every [`LYRICS_WATCH_INTERVAL`] while "Watch file" is on. A change is
+    /// held back while the lyrics editor has unsaved changes, and picked up once they are saved.
+    fn reload_changed_lyrics_file(&mut self, ctx: &egui::Context) {
+        if !self.watch_lyrics_file {
+            return;
+        }
+        ctx.request_repaint_after(LYRICS_WATCH_INTERVAL);
+        if self
+            .last_lyrics_watch
+            .is_some_and(|last| last.elapsed() < LYRICS_WATCH_INTERVAL)
+        {
+            return;
+        }
+        self.last_lyrics_watch = Some(Instant::now());
+
+        let Some(path) = self.selected_lyrics_file.clone() else {
+            return;
+        };
+        let Ok(modified) = std::fs::metadata(&path).and_then(|metadata| metadata.modified()) else {
+            return;
+        };
+        match self.watched_lyrics_file {
+            Some((ref watched, last_modified)) if *watched == path => {
+                if last_modified == modified {
+                    return;
+                }
+            }
+            // A newly selected file is watched from the version that was just loaded.
+            _ => {
+                self.watched_lyrics_file = Some((path, modified));
+                self.lyrics_reload_held = false;
+                return;
+            }
+        }
+        self.lyrics_reload_held = self.lyrics_editor.has_unsaved_changes();
+        if self.lyrics_reload_held {
+            return;
+        }
+        self.watched_lyrics_file = Some((path.clone(), modified));
+
+        let data_tx = self.lyrics_data_tx.clone();
+        let error_tx = self.lyrics_error_tx.clone();
+        tokio::spawn(async move {
+            let path = PathBuf::from(path);
+            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+            match tokio::fs::read(&path).await {
+                Ok(contents) => match convert::parse_lyrics_file(&path, &contents) {
+                    Ok(lyrics) => {
+                        let _ = data_tx.send(lyrics).await;
+                    }
+                    Err(e) => {
+                        let _ = error_tx
+                            .send(format!("Failed to reload {}: {}", file_name, e))
+                            .await;
+                    }
+                },
+                Err(e) => {
+                    let _ = error_tx
+                        .send(format!("Failed to open {}: {}", file_name, e))
+                        .await;
+                }
+            }
+        });
+    }
+
+    /// Removes recent files that no longer exist on disk.
+    fn prune_recent_files(&mut self) {
+        self.recent_audio_files
+            .retain(|path| Path::new(path).is_file());
+        self.recent_lyrics_files
+            .retain(|path| Path::new(path).is_file());
+    }
+
+    fn show_audio_file_details_grid(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("audio_file_details_grid").show(ui, |ui| {
+            ui.label("Cover art");
+            let size = egui::vec2(COVER_ART_SIZE, COVER_ART_SIZE);
+            if let Some(ref cover_art) = self.cover_art {
+                ui.add(egui::Image::new(cover_art).max_size(size));
+            } else {
+                let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+                ui.painter().rect_filled(rect, 4.0, MfColors::GRAY_900);
+                ui.painter().text(
+                    rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    icons::material_design_icons::MDI_MUSIC_NOTE,
+                    egui::FontId::proportional(COVER_ART_SIZE / 2.0),
+                    MfColors::GRAY_700,
+                );
+            }
+            ui.end_row();
+
+            ui.label("File name");
+            ui.label(self.file_name.as_deref().unwrap_or("-"));
+            ui.end_row();
+
+            ui.label("File size");
+            ui.label(
+                self.file_size
+                    .map(|file_size| format!("{:.2} MiB", file_size as f64 / (1024.0 * 1024.0)))
+                    .unwrap_or("-".to_string()),
+            );
+
+            ui.end_row();
+
+            ui.label("File data");
+            ui.label(if self.file_data.is_some() {
+                format!("{} In memory", MDI_CHECK)
+            } else {
+                "-".to_string()
+            });
+            ui.end_row();
+
+            ui.label("Normalization gain");
+            let measured_gain_db = self.audio_effects.loudness.lock().unwrap().measured_gain_db;
+            ui.label(match measured_gain_db {
+                Some(gain_db) => format!("{:+.1} dB", gain_db),
+                None if self.decoding_waveform => "Measuring…".to_string(),
+                None => "-".to_string(),
+            });
+            ui.end_row();
+        });
+    }
+
+    /// Decodes `data` in the background, replacing the waveform with its outline and measuring
+    /// its normalization gain.
+    fn analyze_audio(&mut self, data: Vec<u8>) {
+        self.waveform = None;
+        self.decoded_audio = None;
+        self.cover_art = None;
+        let load_id = self.audio_load_id();
+        let cover_art_tx = self.cover_art_tx.clone();
+        let cover_art_data = data.clone();
+        tokio::spawn(async move {
+            if let Ok(image) = tokio::task::spawn_blocking(move || cover_art(cover_art_data)).await
+            {
+                let _ = cover_art_tx.send((load_id, image)).await;
+            }
+        });
+        self.embedded_lyrics = None;
+        let embedded_lyrics_tx = self.embedded_lyrics_tx.clone();
+        let embedded_lyrics_data = data.clone();
+        tokio::spawn(async move {
+            if let Ok(lyrics) =
+                tokio::task::spawn_blocking(move || embedded_lyrics(embedded_lyrics_data)).await
+            {
+                let _ = embedded_lyrics_tx.send((load_id, lyrics)).await;
+            }
+        });
+        self.audio_effects.loudness.lock().unwrap().measured_gain_db = None;
+        self.edge_silences = None;
+        self.decode_for_analysis(data);
+    }
+
+    /// Decodes the whole of the loaded audio in the background for the waveform, loudness and
+    /// silence trimming, trimming the samples if [`Self::trim_silence`] is on.
+    fn decode_for_analysis(&mut self, data: Vec<u8>) {
+        self.decoding_waveform = true;
+        let trim = self.trim_silence;
+        let load_id = self.audio_load_id();
+        let waveform_tx = self.waveform_tx.clone();
+        tokio::spawn(async move {
+            let result = tokio::task::spawn_blocking(move || {
+                DecodedAudio::decode(data).map(|audio| {
+                    let edge_silences = audio.edge_silences();
+                    let gain_db = audio.normalization_gain_db();
+                    let audio = if trim {
+                        audio.trimmed(edge_silences.0, edge_silences.1)
+                    } else {
+                        audio
+                    };
+                    AudioAnalysis {
+                        waveform: Waveform::new(&audio),
+                        gain_db,
+                        decoded_audio: Arc::new(audio),
+                        edge_silences,
+                        trimmed: trim,
+                    }
+                })
+            })
+            .await;
+            let result = match result {
+                Ok(Ok(analysis)) => Ok(analysis),
+                Ok(Err(e)) => Err(format!("Failed to analyze audio: {}", e)),
+                Err(e) => Err(format!("Failed to analyze audio: {}", e)),
+            };
+            let _ = waveform_tx.send((load_id, result)).await;
+        });
+    }
+
+    /// Shows the waveform of the loaded audio, where dragging selects the loop region.
+    fn show_waveform(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.toggle_value(&mut self.show_waveform, "Waveform");
+            if self.decoding_waveform {
+                ui.spinner();
+            }
+        });
+        if !self.show_waveform {
+            return;
+        }
+        let Some(ref waveform) = self.waveform else {
+            return;
+        };
+        let response = show_waveform(
+            ui,
+            waveform,
+            &mut self.waveform_state,
+            self.player.timestamp,
+            (self.loop_start, self.loop_end),
+            self.lyrics
+                .as_ref()
+                .map_or(&[][..], |lyrics| &lyrics.markers[..]),
+        );
+        ui.colored_label(
+            MfColors::GRAY_500,
+            "Drag to select the loop, click to seek, Ctrl+scroll to zoom",
+        );
+        if let Some((begin, end)) = response.selection {
+            self.loop_start = Some(self.loop_marker_at(begin));
+            self.loop_end = Some(self.loop_marker_at(end));
+            self.order_loop_markers();
+        }
+        if let Some(timestamp) = response.seek {
+            self.seek_to(timestamp);
+        }
+    }
+
+    /// Shows the level of the audio being played, with the RMS level as a bar and the peak as a
+    /// tick, and a light that stays lit for a while after the audio clips.
+    fn show_level_meter(&self, ui: &mut egui::Ui) {
+        let levels = *self.audio_effects.levels.lock().unwrap();
+        let playing = self.player.state == PlayerState::Playing;
+        ui.horizontal(|ui| {
+            ui.label("Level");
+            let (rect, response) = ui.allocate_exact_size(
+                egui::vec2(LEVEL_METER_WIDTH, ui.spacing().interact_size.y / 2.0),
+                egui::Sense::hover(),
+            );
+            let painter = ui.painter_at(rect);
+            painter.rect_filled(rect, 2.0, MfColors::GRAY_900);
+            let x_of = |level: f32| {
+                let db = 20.0 * level.max(f32::MIN_POSITIVE).log10();
+                let position = (1.0 - db / LEVEL_METER_FLOOR_DB).clamp(0.0, 1.0);
+                rect.left() + rect.width() * position
+            };
+            if playing {
+                let fill = if levels.rms >= 0.5 {
+                    MfColors::YELLOW_400
+                } else {
+                    MfColors::GREEN_400
+                };
+                painter.rect_filled(
+                    egui::Rect::from_x_y_ranges(rect.left()..=x_of(levels.rms), rect.y_range()),
+                    2.0,
+                    fill,
+                );
+                painter.vline(
+                    x_of(levels.peak),
+                    rect.y_range(),
+                    egui::Stroke::new(2.0, MfColors::WHITE),
+                );
+            }
+            response.on_hover_text(format!(
+                "Peak {:.1} dBFS, RMS {:.1} dBFS",
+                20.0 * levels.peak.log10(),
+                20.0 * levels.rms.log10()
+            ));
+            let clipped = levels
+                .last_clip
+                .is_some_and(|last_clip| last_clip.elapsed() < CLIP_HOLD);
+            ui.colored_label(
+                if clipped {
+                    MfColors::RED_500
+                } else {
+                    MfColors::GRAY_700
+                },
+                "CLIP",
+            )
+            .on_hover_text("Lights up when the output reaches full scale");
+        });
+    }
+
+    /// Lets the user pick how the transport display shows time, and the frame rate of timecode.
+    fn show_time_format_selector(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_source("time_format_combo_box")
+            .selected_text(self.time_format.label())
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.time_format, TimeFormat::Clock, "Clock");
+                ui.selectable_value(&mut self.time_format, TimeFormat::Seconds, "Seconds");
+                if ui
+                    .selectable_label(
+                        matches!(self.time_format, TimeFormat::Timecode { .. }),
+                        "Timecode",
+                    )
+                    .clicked()
+                {
+                    self.time_format = TimeFormat::Timecode {
+                        fps: TIMECODE_FRAME_RATES[1],
+                    };
+                }
+            });
+        if let TimeFormat::Timecode { ref mut fps } = self.time_format {
+            egui::ComboBox::from_id_source("timecode_fps_combo_box")
+                .selected_text(format!("{} fps", fps))
+                .show_ui(ui, |ui| {
+                    for rate in TIMECODE_FRAME_RATES {
+                        ui.selectable_value(fps, rate, format!("{} fps", rate));
+                    }
+                });
+        }
+    }
 
-            ui.label("File size");
+    fn show_loop_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Loop");
             ui.label(
-                self.file_size
-                    .map(|file_size| format!("{:.2} MiB", file_size as f64 / (1024.0 * 1024.0)))
+                self.loop_start
+                    .map(format_timestamp)
+                    .unwrap_or("-".to_string()),
+            )
+            .on_hover_text(format!(
+                "Press {} to set the loop start",
+                self.keymap.set_loop_start.name()
+            ));
+            ui.colored_label(MfColors::GRAY_500, "→");
+            ui.label(
+                self.loop_end
+                    .map(format_timestamp)
                     .unwrap_or("-".to_string()),
+            )
+            .on_hover_text(format!(
+                "Press {} to set the loop end",
+                self.keymap.set_loop_end.name()
+            ));
+            if ui
+                .add_enabled(
+                    self.loop_start.is_some() || self.loop_end.is_some(),
+                    egui::Button::new("Clear"),
+                )
+                .on_hover_text(format!(
+                    "Press {} to clear the loop",
+                    self.keymap.clear_loop.name()
+                ))
+                .clicked()
+            {
+                self.loop_start = None;
+                self.loop_end = None;
+            }
+            ui.checkbox(&mut self.snap_loop_to_segments, "Snap to segments");
+            ui.label("Pre-roll");
+            ui.add(
+                egui::DragValue::new(&mut self.pre_roll_ms)
+                    .range(0..=5000)
+                    .speed(10)
+                    .suffix(" ms"),
+            )
+            .on_hover_text("Start loops and auditions this long before their start");
+            if ui
+                .checkbox(&mut self.loop_line, "Loop current line")
+                .on_hover_text("Keep replaying the lyric line under the playhead")
+                .changed()
+            {
+                self.looped_line = None;
+            }
+            ui.label("Repeat");
+            egui::ComboBox::from_id_source("repeat_mode_combo_box")
+                .selected_text(self.repeat_mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in [RepeatMode::Off, RepeatMode::One, RepeatMode::All] {
+                        ui.selectable_value(&mut self.repeat_mode, mode, mode.label());
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Seek step");
+            egui::ComboBox::from_id_source("seek_step_combo_box")
+                .selected_text(format!("{}s", self.seek_step_seconds))
+                .show_ui(ui, |ui| {
+                    for step in SEEK_STEPS {
+                        ui.selectable_value(
+                            &mut self.seek_step_seconds,
+                            step,
+                            format!("{}s", step),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(format!(
+                    "{} and {} seek by this step, or by {}s with Shift",
+                    self.keymap.seek_backward.name(),
+                    self.keymap.seek_forward.name(),
+                    FINE_SEEK_STEP
+                ));
+        });
+    }
+
+    /// Whether the shortcuts of the keymap should be ignored this frame.
+    fn shortcuts_blocked(&self, ctx: &egui::Context) -> bool {
+        ctx.wants_keyboard_input() || self.remapping_binding.is_some()
+    }
+
+    fn handle_transport_keys(&mut self, ctx: &egui::Context) {
+        if self.shortcuts_blocked(ctx) {
+            return;
+        }
+        if ctx.input(|i| i.key_pressed(self.keymap.play_pause)) {
+            if self.player.state == PlayerState::Playing {
+                self.pause();
+            } else {
+                self.play();
+            }
+        }
+    }
+
+    /// Times the armed segment of the editor at the playhead while tap timing.
+    fn handle_tap_key(&mut self, ctx: &egui::Context) {
+        if self.shortcuts_blocked(ctx) {
+            return;
+        }
+        if ctx.input(|i| i.key_pressed(self.keymap.tap)) {
+            self.lyrics_editor.tap(self.player.timestamp);
+        }
+    }
+
+    /// Seeks with the seek keys: by the configured step, or by the fine step while Shift is
+    /// held.
+    fn handle_seek_keys(&mut self, ctx: &egui::Context) {
+        if self.shortcuts_blocked(ctx) {
+            return;
+        }
+        let (backward, forward, shift) = ctx.input(|i| {
+            (
+                i.key_pressed(self.keymap.seek_backward),
+                i.key_pressed(self.keymap.seek_forward),
+                i.modifiers.shift,
+            )
+        });
+        let step = Duration::seconds(if shift {
+            FINE_SEEK_STEP
+        } else {
+            self.seek_step_seconds
+        });
+        let target = if backward {
+            self.player.timestamp - step
+        } else if forward {
+            self.player.timestamp + step
+        } else {
+            return;
+        };
+        let end = self.total_duration.unwrap_or(target);
+        self.seek_to(target.max(Duration::zero()).min(end));
+    }
+
+    fn handle_line_step_keys(&mut self, ctx: &egui::Context) {
+        if self.shortcuts_blocked(ctx) {
+            return;
+        }
+        let (previous, next) = ctx.input(|i| {
+            (
+                i.key_pressed(self.keymap.previous_line),
+                i.key_pressed(self.keymap.next_line),
+            )
+        });
+        if previous {
+            self.step_line(false);
+        } else if next {
+            self.step_line(true);
+        }
+    }
+
+    /// Moves the playhead to the beginning of the next line, or of the previous one, whether
+    /// playing or not. Stepping back within the first moments of a line skips to the line
+    /// before, so that repeated steps keep going back while playing.
+    fn step_line(&mut self, forward: bool) {
+        let Some(ref lyrics) = self.lyrics else {
+            return;
+        };
+        let timestamp = self.player.timestamp;
+        let target = if forward {
+            lyrics.next_line_begin(timestamp)
+        } else {
+            lyrics.previous_line_begin(timestamp, Duration::milliseconds(PREVIOUS_LINE_GRACE_MS))
+        };
+        if let Some(target) = target {
+            self.seek_to(target);
+        }
+    }
+
+    /// Shows buttons that step the playhead from line to line.
+    fn show_line_stepper(&mut self, ui: &mut egui::Ui) {
+        let Some(ref lyrics) = self.lyrics else {
+            return;
+        };
+        let timestamp = self.player.timestamp;
+        let has_previous = lyrics
+            .previous_line_begin(timestamp, Duration::milliseconds(PREVIOUS_LINE_GRACE_MS))
+            .is_some();
+        let has_next = lyrics.next_line_begin(timestamp).is_some();
+        ui.horizontal(|ui| {
+            ui.label("Line");
+            if ui
+                .add_enabled(
+                    has_previous,
+                    egui::Button::new(icons::material_design_icons::MDI_CHEVRON_UP),
+                )
+                .on_hover_text(format!(
+                    "Previous line ({})",
+                    self.keymap.previous_line.name()
+                ))
+                .clicked()
+            {
+                self.step_line(false);
+            }
+            if ui
+                .add_enabled(
+                    has_next,
+                    egui::Button::new(icons::material_design_icons::MDI_CHEVRON_DOWN),
+                )
+                .on_hover_text(format!("Next line ({})", self.keymap.next_line.name()))
+                .clicked()
+            {
+                self.step_line(true);
+            }
+        });
+    }
+
+    fn handle_loop_keys(&mut self, ctx: &egui::Context) {
+        if self.shortcuts_blocked(ctx) {
+            return;
+        }
+        let (set_start, set_end, clear) = ctx.input(|i| {
+            (
+                i.key_pressed(self.keymap.set_loop_start),
+                i.key_pressed(self.keymap.set_loop_end),
+                i.key_pressed(self.keymap.clear_loop),
+            )
+        });
+        if set_start {
+            self.loop_start = Some(self.loop_marker_at(self.player.timestamp));
+        }
+        if set_end {
+            self.loop_end = Some(self.loop_marker_at(self.player.timestamp));
+        }
+        if clear {
+            self.loop_start = None;
+            self.loop_end = None;
+        }
+        self.order_loop_markers();
+    }
+
+    /// Swaps the loop markers if they are the wrong way round, and drops the end marker if
+    /// both are at the same timestamp, as such a loop would seek back on every frame.
+    fn order_loop_markers(&mut self) {
+        if let (Some(loop_start), Some(loop_end)) = (self.loop_start, self.loop_end) {
+            if loop_start > loop_end {
+                self.loop_start = Some(loop_end);
+                self.loop_end = Some(loop_start);
+            } else if loop_start == loop_end {
+                self.loop_end = None;
+            }
+        }
+    }
+
+    /// Returns the position of a loop marker placed at `timestamp`, snapped to the nearest
+    /// segment boundary if snapping is enabled and lyrics are loaded.
+    fn loop_marker_at(&self, timestamp: Duration) -> Duration {
+        if !self.snap_loop_to_segments {
+            return timestamp;
+        }
+        self.lyrics
+            .iter()
+            .flat_map(|lyrics| &lyrics.lyrics.lines)
+            .flat_map(|line| &line.original)
+            .flat_map(|segment| [segment.begin, segment.end])
+            .min_by_key(|boundary| (*boundary - timestamp).abs())
+            .unwrap_or(timestamp)
+    }
+
+    /// Starts or resumes playback from the current timestamp.
+    fn play(&mut self) {
+        self.fade_out_end = None;
+        if let Some(total_duration) = self.total_duration {
+            if self.player.timestamp >= total_duration {
+                self.player.seek(Duration::zero(), Instant::now());
+            }
+        }
+        self.player.play(Instant::now());
+        let sink = self.arc_rodio_sink.lock().unwrap();
+        if sink.empty() {
+            // The sink drops the source once it has been played to the end.
+            if let Some(ref file_data) = self.file_data {
+                match decode_source(file_data.clone(), &self.audio_effects) {
+                    Ok(source) => sink.append(source),
+                    Err(e) => eprintln!("Failed to decode audio: {}", e),
+                }
+            }
+        }
+        let _ = sink.try_seek(self.sink_position());
+        let mut fade = self.audio_effects.fade.lock().unwrap();
+        fade.audible = true;
+        fade.fade_in_generation += 1;
+        sink.play();
+    }
+
+    /// Fades the audio out, leaving the sink playing until the fade is done.
+    fn fade_out(&mut self) {
+        let mut fade = self.audio_effects.fade.lock().unwrap();
+        fade.audible = false;
+        if fade.length.is_zero() {
+            self.arc_rodio_sink.lock().unwrap().pause();
+            self.fade_out_end = None;
+        } else {
+            self.fade_out_end = Some(Instant::now() + fade.length + FADE_OUT_MARGIN);
+        }
+    }
+
+    /// Pauses the sink once a fade-out has finished.
+    fn finish_fade_out(&mut self, ctx: &egui::Context) {
+        let Some(fade_out_end) = self.fade_out_end else {
+            return;
+        };
+        if Instant::now() >= fade_out_end {
+            self.arc_rodio_sink.lock().unwrap().pause();
+            self.fade_out_end = None;
+        } else {
+            ctx.request_repaint();
+        }
+    }
+
+    /// Stops the player once the sink has played the audio to the end, so that the timestamp
+    /// does not keep running past the end of the track.
+    fn check_playback_ended(&mut self) {
+        let total_duration = self.total_duration.unwrap_or(self.player.timestamp);
+        if self.player.timestamp > total_duration {
+            self.player.seek(total_duration, Instant::now());
+        }
+        // A crossfade hands over to the next track once the sink has run dry.
+        if self.file_data.is_some()
+            && self.active_crossfade.is_none()
+            && self.arc_rodio_sink.lock().unwrap().empty()
+        {
+            if self.repeat_mode != RepeatMode::Off {
+                // Playing again appends the audio anew, as the sink has dropped it.
+                self.player.seek(Duration::zero(), Instant::now());
+                self.play();
+                return;
+            }
+            self.stop();
+            self.player.seek(total_duration, Instant::now());
+        }
+    }
+
+    fn pause(&mut self) {
+        self.audition_end = None;
+        self.audition_return = None;
+        self.player.pause();
+        self.fade_out();
+    }
+
+    /// Halts playback without rewinding, so that playing again continues from the same point.
+    fn stop(&mut self) {
+        self.audition_end = None;
+        self.audition_return = None;
+        self.player.stop();
+        self.fade_out();
+    }
+
+    /// Halts playback and rewinds to the start.
+    fn reset(&mut self) {
+        self.audition_end = None;
+        self.audition_return = None;
+        self.player.reset();
+        let sink = self.arc_rodio_sink.lock().unwrap();
+        sink.pause();
+        let _ = sink.try_seek(self.sink_position());
+    }
+
+    /// Advances the clock to this frame while playing, and applies the loop region, line
+    /// looping and auditioning to the new position. This runs once at the start of every frame,
+    /// before anything is drawn, so that every window shows the same playhead.
+    fn advance_playback(&mut self, ctx: &egui::Context) {
+        if self.player.state != PlayerState::Playing {
+            return;
+        }
+        self.player.tick(Instant::now());
+        self.check_playback_ended();
+
+        if let (Some(loop_start), Some(loop_end)) = (self.loop_start, self.loop_end) {
+            if self.player.timestamp >= loop_end {
+                self.seek_to(self.pre_rolled(loop_start));
+            }
+        }
+        self.loop_current_line();
+
+        if let Some(audition_end) = self.audition_end {
+            if self.player.timestamp >= audition_end {
+                let audition_return = self.audition_return.take();
+                self.pause();
+                if let Some((timestamp, was_playing)) = audition_return {
+                    self.seek_to(timestamp);
+                    if was_playing {
+                        self.play();
+                    }
+                }
+            }
+        }
+
+        ctx.request_repaint();
+    }
+
+    /// With "Loop current line" on, seeks back to the start of the line being looped once the
+    /// playhead runs past its end. Moving the playhead into another line loops that one instead.
+    fn loop_current_line(&mut self) {
+        if !self.loop_line {
+            return;
+        }
+        let timestamp = self.player.timestamp;
+        if let Some((begin, end)) = self.looped_line {
+            // Only the playhead running off the end loops, not seeking far past it.
+            if timestamp >= end && timestamp < end + Duration::milliseconds(LINE_LOOP_TOLERANCE) {
+                self.seek_to(self.pre_rolled(begin));
+                return;
+            }
+            // The pre-roll plays the end of the line before, which is not looped instead.
+            if self.pre_rolled(begin) <= timestamp && timestamp < begin {
+                return;
+            }
+        }
+        let line = self
+            .lyrics
+            .iter()
+            .flat_map(|lyrics| &lyrics.lyrics.lines)
+            .find(|line| line.is_active_at(timestamp));
+        if let Some(line) = line {
+            self.looped_line = Some((line.begin, line.end));
+        }
+    }
+
+    /// Where playback starts for a loop or audition beginning at `begin`, allowing for the
+    /// pre-roll.
+    fn pre_rolled(&self, begin: Duration) -> Duration {
+        (begin - Duration::milliseconds(self.pre_roll_ms)).max(Duration::zero())
+    }
+
+    /// Plays the span from `begin` to `end` once, then returns to where the player was.
+    fn preview(&mut self, begin: Duration, end: Duration) {
+        // A preview started during another one returns to where the first one started.
+        let audition_return = self.audition_return.take().unwrap_or((
+            self.player.timestamp,
+            self.player.state == PlayerState::Playing,
+        ));
+        self.seek_to(self.pre_rolled(begin));
+        self.play();
+        self.audition_end = Some(end);
+        self.audition_return = Some(audition_return);
+    }
+
+    /// Shows a field to type a timestamp in and seek to it.
+    fn show_goto_timestamp(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Go to");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.goto_input)
+                    .hint_text("mm:ss.mmm")
+                    .desired_width(100.0),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if submitted || ui.button("Go").clicked() {
+                match parse_timestamp(&self.goto_input) {
+                    Ok(timestamp) => {
+                        self.seek_to(timestamp);
+                        self.goto_error = None;
+                    }
+                    Err(e) => self.goto_error = Some(e),
+                }
+            }
+            if response.changed() {
+                self.goto_error = None;
+            }
+            if let Some(ref goto_error) = self.goto_error {
+                ui.colored_label(MfColors::RED_400, goto_error);
+            }
+        });
+    }
+
+    /// Moves the player to `timestamp`, seeking the sink and keeping the clock consistent.
+    ///
+    /// The timestamp is clamped to the length of the audio, if known.
+    fn seek_to(&mut self, timestamp: Duration) {
+        let mut timestamp = timestamp.max(Duration::zero());
+        if let Some(total_duration) = self.total_duration {
+            timestamp = timestamp.min(total_duration);
+        }
+        self.player.seek(timestamp, Instant::now());
+        self.cancel_crossfade();
+        let _ = self
+            .arc_rodio_sink
+            .lock()
+            .unwrap()
+            .try_seek(self.sink_position());
+    }
+
+    /// The position the sink should be at for the current timestamp.
+    ///
+    /// When resampling, the sink scales seek positions by its speed, so the position is divided
+    /// by the speed to land on the timestamp in the audio file.
+    fn sink_position(&self) -> std::time::Duration {
+        let sink_speed = self.audio_effects.speed.lock().unwrap().sink_speed();
+        self.player.sink_position().div_f32(sink_speed)
+    }
+
+    fn show_speed_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = *self.audio_effects.speed.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.label("Speed");
+            ui.add(
+                egui::Slider::new(&mut settings.speed, 0.25..=2.0)
+                    .step_by(0.05)
+                    .custom_formatter(|n, _| format!("{:.2}x", n)),
+            );
+            if ui.button("Reset").clicked() {
+                settings.speed = 1.0;
+            }
+            egui::ComboBox::from_id_source("speed_mode_combo_box")
+                .selected_text(settings.mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in [SpeedMode::PreservePitch, SpeedMode::Resample] {
+                        ui.selectable_value(&mut settings.mode, mode, mode.label());
+                    }
+                });
+        });
+
+        let mut current = self.audio_effects.speed.lock().unwrap();
+        if settings.speed != current.speed || settings.mode != current.mode {
+            *current = settings;
+            drop(current);
+            self.player.set_speed(settings.speed as f64, Instant::now());
+            let sink = self.arc_rodio_sink.lock().unwrap();
+            sink.set_speed(settings.sink_speed());
+        }
+    }
+
+    fn show_loudness_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.audio_effects.loudness.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut settings.normalize, "Normalize loudness")
+                .on_hover_text("Bring the audio to a common loudness, without clipping");
+            ui.label("Trim");
+            ui.add(
+                egui::Slider::new(&mut settings.trim_db, -12.0..=12.0)
+                    .step_by(0.5)
+                    .suffix(" dB"),
+            );
+            if ui.button("Reset").clicked() {
+                settings.trim_db = 0.0;
+            }
+        });
+    }
+
+    fn show_fade_controls(&mut self, ui: &mut egui::Ui) {
+        let mut fade = self.audio_effects.fade.lock().unwrap();
+        let mut length_ms = fade.length.as_millis() as u64;
+        ui.horizontal(|ui| {
+            ui.label("Fade");
+            ui.add(
+                egui::DragValue::new(&mut length_ms)
+                    .range(0..=500)
+                    .suffix(" ms"),
+            )
+            .on_hover_text("Fade in on play and out on pause, to avoid clicks");
+        });
+        fade.length = std::time::Duration::from_millis(length_ms);
+    }
+
+    fn show_trim_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .checkbox(&mut self.trim_silence, "Trim silence")
+                .on_hover_text(
+                    "Skip the silence at the start and end of the audio, counting timestamps \
+                     from the first sound",
+                )
+                .changed()
+            {
+                if let Some(data) = self.file_data.clone() {
+                    self.decode_for_analysis(data);
+                }
+            }
+            if let Some((leading, trailing)) = self.edge_silences {
+                ui.colored_label(
+                    MfColors::GRAY_500,
+                    format!(
+                        "{:.3} s at the start, {:.3} s at the end",
+                        leading.num_milliseconds() as f64 / 1000.0,
+                        trailing.num_milliseconds() as f64 / 1000.0
+                    ),
+                );
+            }
+        });
+    }
+
+    /// Applies the silence trim of a finished analysis to playback. The player stays at the
+    /// same moment of the audio, which has a different timestamp once the start is trimmed.
+    fn apply_silence_trim(&mut self, analysis: &AudioAnalysis) {
+        self.edge_silences = Some(analysis.edge_silences);
+        let leading = analysis.edge_silences.0;
+        let trim = if analysis.trimmed {
+            TrimSettings {
+                start: leading.to_std().unwrap_or_default(),
+                end: (leading + analysis.decoded_audio.duration()).to_std().ok(),
+            }
+        } else {
+            TrimSettings::default()
+        };
+        let previous = std::mem::replace(&mut *self.audio_effects.trim.lock().unwrap(), trim);
+        if previous == trim {
+            return;
+        }
+        let start_of = |trim: &TrimSettings| {
+            Duration::from_std(trim.start).unwrap_or_else(|_| Duration::zero())
+        };
+        self.total_duration = Some(analysis.decoded_audio.duration());
+        self.seek_to(self.player.timestamp + start_of(&previous) - start_of(&trim));
+    }
+
+    fn show_equalizer_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.audio_effects.equalizer.lock().unwrap();
+        ui.collapsing("Equalizer", |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.enabled, "Enabled")
+                    .on_hover_text("Untick to bypass the equalizer");
+                if ui.button("Reset").clicked() {
+                    settings.gains_db = Default::default();
+                }
+            });
+            ui.add_enabled_ui(settings.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    for (band, (_, _, label)) in EQUALIZER_BANDS.iter().enumerate() {
+                        ui.vertical(|ui| {
+                            ui.add(
+                                egui::Slider::new(&mut settings.gains_db[band], -12.0..=12.0)
+                                    .vertical()
+                                    .step_by(0.5)
+                                    .suffix(" dB"),
+                            );
+                            ui.label(*label);
+                        });
+                    }
+                });
+            });
+        });
+    }
+
+    fn show_channel_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.audio_effects.channels.lock().unwrap();
+        ui.horizontal(|ui| {
+            ui.label("Channels");
+            egui::ComboBox::from_id_source("channel_mode_combo_box")
+                .selected_text(settings.mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in ChannelMode::ALL {
+                        ui.selectable_value(&mut settings.mode, mode, mode.label());
+                    }
+                });
+            ui.label("Balance");
+            ui.add(
+                egui::Slider::new(&mut settings.balance, -1.0..=1.0).custom_formatter(|n, _| {
+                    if n < 0.0 {
+                        format!("L {:.0}%", -n * 100.0)
+                    } else if n > 0.0 {
+                        format!("R {:.0}%", n * 100.0)
+                    } else {
+                        "Center".to_string()
+                    }
+                }),
+            );
+            if ui.button("Reset").clicked() {
+                settings.balance = 0.0;
+            }
+        });
+    }
+
+    fn show_metronome_controls(&mut self, ui: &mut egui::Ui) {
+        let mut settings = self.audio_effects.metronome.lock().unwrap();
+        let bpm_range = 20.0..=400.0;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut settings.enabled, "Metronome");
+            ui.add(
+                egui::DragValue::new(&mut settings.bpm)
+                    .speed(0.1)
+                    .range(bpm_range.clone())
+                    .suffix(" BPM"),
+            );
+            let now = Instant::now();
+            if ui
+                .button("Tap")
+                .on_hover_text("Tap in rhythm with the music to measure its tempo")
+                .clicked()
+            {
+                self.tap_tempo.tap(now);
+                if let Some(estimate) = self.tap_tempo.estimate() {
+                    // Taps far apart or very close can measure a tempo the metronome does not play.
+                    settings.bpm = ((estimate.bpm * 10.0).round() / 10.0)
+                        .clamp(*bpm_range.start(), *bpm_range.end());
+                }
+            }
+            if let Some(estimate) = self.tap_tempo.estimate() {
+                let color = if self.tap_tempo.timed_out(now) {
+                    MfColors::GRAY_500
+                } else if estimate.confidence >= 0.7 {
+                    MfColors::GREEN_400
+                } else if estimate.confidence >= 0.3 {
+                    MfColors::YELLOW_400
+                } else {
+                    MfColors::RED_400
+                };
+                ui.colored_label(color, format!("{:.1} BPM", estimate.bpm))
+                    .on_hover_text(format!(
+                        "{} taps, {:.0}% confidence",
+                        estimate.taps,
+                        estimate.confidence * 100.0
+                    ));
+            }
+            ui.add(
+                egui::DragValue::new(&mut settings.offset_ms)
+                    .speed(1.0)
+                    .range(0.0..=60_000.0)
+                    .prefix("offset ")
+                    .suffix(" ms"),
+            )
+            .on_hover_text("Position of the first beat");
+            ui.add(
+                egui::DragValue::new(&mut settings.beats_per_bar)
+                    .range(1..=16)
+                    .suffix(" beats/bar"),
+            );
+            ui.label("Volume");
+            ui.add(egui::Slider::new(&mut settings.volume, 0.0..=1.0).show_value(false));
+        });
+    }
+
+    fn show_output_device_selector(&mut self, ui: &mut egui::Ui) {
+        let mut selected_output_device = self.selected_output_device.clone();
+        ui.horizontal(|ui| {
+            ui.label("Output device");
+            egui::ComboBox::from_id_source("output_device_combo_box")
+                .selected_text(
+                    selected_output_device
+                        .as_deref()
+                        .unwrap_or("System default"),
+                )
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected_output_device, None, "System default");
+                    for device_name in &self.output_devices {
+                        ui.selectable_value(
+                            &mut selected_output_device,
+                            Some(device_name.clone()),
+                            device_name,
+                        );
+                    }
+                });
+            if ui
+                .button(icons::material_design_icons::MDI_REFRESH)
+                .on_hover_text("Refresh device list")
+                .clicked()
+            {
+                self.output_devices = list_output_devices();
+            }
+        });
+        if selected_output_device != self.selected_output_device {
+            self.switch_output_device(selected_output_device);
+        }
+    }
+
+    /// Rebuilds the output stream and sink on the given device, re-appending the loaded audio
+    /// and restoring the playback position.
+    fn switch_output_device(&mut self, device_name: Option<String>) {
+        let stream = match &device_name {
+            Some(name) => rodio::cpal::default_host()
+                .output_devices()
+                .map_err(anyhow::Error::from)
+                .and_then(|mut devices| {
+                    devices
+                        .find(|device| device.name().ok().as_deref() == Some(name.as_str()))
+                        .ok_or_else(|| anyhow::anyhow!("Device not found: {}", name))
+                })
+                .and_then(|device| Ok(OutputStream::try_from_device(&device)?)),
+            None => OutputStream::try_default().map_err(anyhow::Error::from),
+        };
+        let (stream, stream_handle) = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to open output device: {}", e);
+                return;
+            }
+        };
+        let sink = match Sink::try_new(&stream_handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                eprintln!("Failed to create sink: {}", e);
+                return;
+            }
+        };
+        sink.pause();
+        sink.set_speed(self.audio_effects.speed.lock().unwrap().sink_speed());
+
+        if let Some(ref file_data) = self.file_data {
+            match decode_source(file_data.clone(), &self.audio_effects) {
+                Ok(source) => {
+                    sink.append(source);
+                    let _ = sink.try_seek(self.sink_position());
+                }
+                Err(e) => {
+                    eprintln!("Failed to decode audio: {}", e);
+                }
+            }
+        }
+
+        if self.player.state == PlayerState::Playing {
+            // Restart the clock from the current position so it matches the new sink.
+            self.player.play(Instant::now());
+            sink.play();
+        }
+
+        *self.arc_rodio_sink.lock().unwrap() = sink;
+        // The queued playlist track stayed in the old sink; queue it again on the new one.
+        self.queued_track = None;
+        self.queueing_track = false;
+        self.active_crossfade = None;
+        self._rodio_stream = stream;
+        self._rodio_stream_handle = stream_handle;
+        self.selected_output_device = device_name;
+    }
+
+    fn show_lyrics_file_details_grid(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("lyrics_details_grid").show(ui, |ui| {
+            ui.label("File name");
+            ui.label(self.lyrics_file_name.as_deref().unwrap_or("-"));
+            ui.end_row();
+
+            ui.label("Lyrics data");
+            ui.label(if self.lyrics.is_some() {
+                format!("{} In memory", MDI_CHECK)
+            } else {
+                "-".to_string()
+            });
+            ui.end_row();
+
+            let lyrics_end = self
+                .lyrics
+                .iter()
+                .flat_map(|lyrics| &lyrics.lyrics.lines)
+                .map(|line| line.end)
+                .max();
+            ui.label("Lyrics end");
+            ui.label(lyrics_end.map_or("-".to_string(), format_timestamp));
+            ui.end_row();
+
+            if let (Some(lyrics_end), Some(total_duration)) = (lyrics_end, self.total_duration) {
+                ui.label("Audio length");
+                ui.label(format_timestamp(total_duration));
+                ui.end_row();
+
+                if (lyrics_end - total_duration).num_seconds().abs() > DURATION_MISMATCH_THRESHOLD {
+                    ui.label("");
+                    ui.colored_label(
+                        MfColors::YELLOW_500,
+                        "The lyrics and the audio end far apart",
+                    )
+                    .on_hover_text(
+                        "These lyrics may be for another track or cut, or be timed from a \
+                         different starting point",
+                    );
+                    ui.end_row();
+                }
+            }
+        });
+    }
+
+    /// Shows a picker for the imported lyric sets, if there are any.
+    fn show_lyrics_sets(&mut self, ui: &mut egui::Ui) {
+        if self.lyrics_sets.is_empty() {
+            return;
+        }
+        let set_name = |path: &str| {
+            Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.to_string())
+        };
+
+        let mut selected = self.active_lyrics_set;
+        let mut remove = None;
+        ui.horizontal(|ui| {
+            ui.label("Lyric set");
+            egui::ComboBox::from_id_source("lyrics_set_combo_box")
+                .selected_text(
+                    selected
+                        .map(|index| set_name(&self.lyrics_sets[index].0))
+                        .unwrap_or_else(|| "-".to_string()),
+                )
+                .show_ui(ui, |ui| {
+                    for (index, (path, _)) in self.lyrics_sets.iter().enumerate() {
+                        ui.selectable_value(&mut selected, Some(index), set_name(path))
+                            .on_hover_text(path);
+                    }
+                });
+            if let Some(index) = self.active_lyrics_set {
+                if ui
+                    .button(icons::material_design_icons::MDI_CLOSE)
+                    .on_hover_text("Remove this set")
+                    .clicked()
+                {
+                    remove = Some(index);
+                }
+            }
+            ui.colored_label(
+                MfColors::GRAY_500,
+                format!("{} loaded", self.lyrics_sets.len()),
+            );
+        });
+
+        if let Some(index) = remove {
+            self.lyrics_sets.remove(index);
+            self.active_lyrics_set = None;
+            if !self.lyrics_sets.is_empty() {
+                self.activate_lyrics_set(index.min(self.lyrics_sets.len() - 1));
+            }
+        } else if let Some(index) = selected.filter(|_| selected != self.active_lyrics_set) {
+            self.activate_lyrics_set(index);
+        }
+    }
+
+    /// Shows the lyric set at `index` in the lyrics and captions windows.
+    fn activate_lyrics_set(&mut self, index: usize) {
+        let (path, lyrics) = &self.lyrics_sets[index];
+        self.selected_lyrics_file = Some(path.clone());
+        self.lyrics_file_name = Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string());
+        self.lyrics = Some(lyrics.clone());
+        self.lyrics_error = None;
+        self.active_lyrics_set = Some(index);
+        self.live_preview = false;
+        self.show_main_lyrics_window = true;
+        self.show_captions_window = true;
+    }
+
+    /// Shows a checkbox for each translation language of the lyrics, choosing which ones the
+    /// lyrics and captions windows show.
+    fn show_translation_toggles(&mut self, ui: &mut egui::Ui) {
+        let Some(ref lyrics) = self.lyrics else {
+            return;
+        };
+        if lyrics.metadata.translations.is_empty() {
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Translations");
+            for entry in &lyrics.metadata.translations {
+                let mut visible = !self.hidden_translations.contains(&entry.id);
+                if ui.checkbox(&mut visible, &entry.language).changed() {
+                    if visible {
+                        self.hidden_translations.remove(&entry.id);
+                    } else {
+                        self.hidden_translations.insert(entry.id);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Shows a search box that matches lyric lines by their text and seeks to the selected one.
+    fn show_lyrics_search(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(icons::material_design_icons::MDI_MAGNIFY);
+            ui.add(
+                egui::TextEdit::singleline(&mut self.lyrics_search_query)
+                    .hint_text("Search lyrics"),
             );
+        });
+        let query = self.lyrics_search_query.trim().to_lowercase();
+        if query.is_empty() {
+            return;
+        }
 
-            ui.end_row();
+        let Some(ref lyrics) = self.lyrics else {
+            return;
+        };
+        let results: Vec<(Duration, String)> = lyrics
+            .lyrics
+            .lines
+            .iter()
+            .filter_map(|line| {
+                let text: String = line.original.iter().map(|seg| seg.text.as_str()).collect();
+                let matches = text.to_lowercase().contains(&query)
+                    || line
+                        .translations
+                        .iter()
+                        .any(|(_, words)| words.concat().to_lowercase().contains(&query));
+                matches.then_some((line.begin, text))
+            })
+            .collect();
 
-            ui.label("File data");
-            ui.label(if self.file_data.is_some() {
-                format!("{} In memory", MDI_CHECK)
-            } else {
-                "-".to_string()
+        let mut seek_request = None;
+        if results.is_empty() {
+            ui.colored_label(MfColors::GRAY_500, "No matches");
+        }
+        egui::ScrollArea::vertical()
+            .id_source("lyrics_search_results")
+            .max_height(120.0)
+            .show(ui, |ui| {
+                for (begin, text) in results {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(MfColors::GRAY_500, format_timestamp(begin));
+                        if ui.selectable_label(false, text).clicked() {
+                            seek_request = Some(begin);
+                        }
+                    });
+                }
             });
-            ui.end_row();
-        });
+        if let Some(timestamp) = seek_request {
+            self.seek_to(timestamp);
+        }
     }
 
-    fn show_lyrics_file_details_grid(&mut self, ui: &mut egui::Ui) {
-        egui::Grid::new("lyrics_details_grid").show(ui, |ui| {
-            ui.label("File name");
-            ui.label(self.lyrics_file_name.as_deref().unwrap_or("-"));
-            ui.end_row();
+    /// Shows the find bar of the lyrics window, opened with Ctrl+F and closed with Escape.
+    /// Enter steps to the next match and Shift+Enter to the previous one.
+    fn show_lyrics_find(find: &mut LyricsFind, ui: &mut egui::Ui, match_count: usize) {
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::F)) {
+            find.open = true;
+            ui.memory_mut(|memory| memory.request_focus(egui::Id::new("lyrics_find_query")));
+        }
+        if !find.open {
+            return;
+        }
+        ui.horizontal(|ui| {
+            ui.label(icons::material_design_icons::MDI_MAGNIFY);
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut find.query)
+                    .id(egui::Id::new("lyrics_find_query"))
+                    .hint_text("Find in lyrics"),
+            );
+            if response.changed() {
+                find.current = 0;
+                find.scroll_pending = true;
+            }
+            let mut step = None;
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                step = Some(!ui.input(|i| i.modifiers.shift));
+                response.request_focus();
+            }
+            if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                find.open = false;
+            }
+            if !find.query.trim().is_empty() {
+                if match_count == 0 {
+                    ui.colored_label(MfColors::GRAY_500, "No matches");
+                } else {
+                    ui.label(format!("{} of {}", find.current + 1, match_count));
+                }
+            }
+            ui.add_enabled_ui(match_count > 0, |ui| {
+                if ui
+                    .button(icons::material_design_icons::MDI_ARROW_UP)
+                    .on_hover_text("Previous match (Shift+Enter)")
+                    .clicked()
+                {
+                    step = Some(false);
+                }
+                if ui
+                    .button(icons::material_design_icons::MDI_ARROW_DOWN)
+                    .on_hover_text("Next match (Enter)")
+                    .clicked()
+                {
+                    step = Some(true);
+                }
+            });
+            if ui.button(icons::material_design_icons::MDI_CLOSE).clicked() {
+                find.open = false;
+            }
+            if let (Some(forward), true) = (step, match_count > 0) {
+                find.current = if forward {
+                    (find.current + 1) % match_count
+                } else {
+                    (find.current + match_count - 1) % match_count
+                };
+                find.scroll_pending = true;
+            }
+        });
+    }
 
-            ui.label("Lyrics data");
-            ui.label(if self.lyrics.is_some() {
-                format!("{} In memory", MDI_CHECK)
-            } else {
-                "-".to_string()
+    /// Shows the settings for how the captions move when the active line changes.
+    fn show_captions_transition_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Transition");
+            ui.add(
+                egui::DragValue::new(&mut self.captions_transition_ms)
+                    .range(0..=2000)
+                    .speed(10)
+                    .suffix(" ms"),
+            )
+            .on_hover_text("How long new lines take to glide into place, or 0 to jump");
+            ui.add_enabled_ui(self.captions_transition_ms > 0, |ui| {
+                egui::ComboBox::from_id_source("captions_easing")
+                    .selected_text(self.captions_easing.label())
+                    .show_ui(ui, |ui| {
+                        for easing in [Easing::Linear, Easing::EaseOut, Easing::EaseInOut] {
+                            ui.selectable_value(&mut self.captions_easing, easing, easing.label());
+                        }
+                    });
             });
-            ui.end_row();
         });
     }
 
-    fn show_lyrics_window(&self, ctx: &egui::Context, lyrics: &BabelLyrics) {
+    /// How far the captions are still to glide up into place, from 1 when the active line has
+    /// just changed to 0 once the transition is over. Only the layout moves; the highlighting
+    /// always follows the player.
+    fn captions_transition_remaining(&mut self, ctx: &egui::Context) -> f32 {
+        let timestamp = self.player.timestamp;
+        let active_line = self
+            .lyrics
+            .iter()
+            .flat_map(|lyrics| &lyrics.lyrics.lines)
+            .find(|line| line.is_active_at(timestamp))
+            .map(|line| line.uuid);
+        let now = Instant::now();
+        match (active_line, self.captions_active_line) {
+            (Some(uuid), Some((previous, _))) if uuid == previous => {}
+            (Some(uuid), _) => self.captions_active_line = Some((uuid, now)),
+            // Gaps between lines keep the last line, so that the next one glides in from the
+            // upcoming lines.
+            (None, _) => {}
+        }
+        let Some((_, changed)) = self.captions_active_line else {
+            return 0.0;
+        };
+        if self.captions_transition_ms == 0 {
+            return 0.0;
+        }
+        let t =
+            now.duration_since(changed).as_secs_f32() * 1000.0 / self.captions_transition_ms as f32;
+        if t >= 1.0 {
+            return 0.0;
+        }
+        ctx.request_repaint();
+        1.0 - self.captions_easing.apply(t)
+    }
+
+    /// Shows the settings for the background of the captions window, for keying it out of a
+    /// video capture.
+    fn show_captions_background_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut custom = self.captions_background.is_some();
+            if ui
+                .checkbox(&mut custom, "Custom background")
+                .on_hover_text(
+                    "Fill the captions window with a solid or translucent color, without a \
+                     border or shadow, to key it out or blend it over video",
+                )
+                .changed()
+            {
+                self.captions_background = custom.then_some(CHROMA_KEY_COLOR);
+            }
+            if let Some(ref mut color) = self.captions_background {
+                ui.color_edit_button_srgba(color)
+                    .on_hover_text("Color and opacity of the background");
+                if ui
+                    .button("Chroma key")
+                    .on_hover_text("Solid magenta, to key out in OBS and the like")
+                    .clicked()
+                {
+                    *color = CHROMA_KEY_COLOR;
+                }
+            }
+        });
+    }
+
+    /// Shows the lyrics window, returning a timestamp to seek to if one was requested.
+    fn show_lyrics_window(
+        &self,
+        ctx: &egui::Context,
+        lyrics: Option<&BabelLyrics>,
+        find: &mut LyricsFind,
+    ) -> Option<LyricsWindowRequest> {
+        let mut request = None;
         egui::Window::new("Lyrics").show(ctx, |ui| {
+            let Some(lyrics) = lyrics else {
+                ui.colored_label(MfColors::GRAY_500, "No lyrics loaded — import or create");
+                ui.horizontal(|ui| {
+                    if ui.button("Select lyrics file").clicked() {
+                        request = Some(LyricsWindowRequest::Import);
+                    }
+                    if ui
+                        .button("New lyrics")
+                        .on_hover_text("Start transcribing the loaded audio in the lyrics editor")
+                        .clicked()
+                    {
+                        request = Some(LyricsWindowRequest::Create);
+                    }
+                });
+                return;
+            };
+            let next_flagged = lyrics.next_flagged_segment(self.player.timestamp);
+            ui.add_enabled_ui(next_flagged.is_some(), |ui| {
+                if ui
+                    .button(format!(
+                        "{} Next flagged",
+                        icons::material_design_icons::MDI_FLAG
+                    ))
+                    .on_hover_text("Jump to the next segment flagged for review")
+                    .clicked()
+                {
+                    request = next_flagged.map(LyricsWindowRequest::Seek);
+                }
+            });
+            if let Some((position, count)) = self.lyrics_editor.tap_progress() {
+                if position > count {
+                    ui.colored_label(MfColors::GREEN_400, "Tap timing: all segments timed");
+                } else {
+                    ui.colored_label(
+                        MfColors::ORANGE_500,
+                        format!("Tap timing: segment {} of {}", position, count),
+                    );
+                }
+            }
+            let armed_segment = self.lyrics_editor.armed_segment();
+            let lines = &lyrics.lyrics.lines;
+            let find_matches = find.matching_lines(lines, &self.hidden_translations);
+            Self::show_lyrics_find(find, ui, find_matches.len());
+            let current_match = find
+                .open
+                .then(|| find_matches.get(find.current).copied())
+                .flatten();
+            ui.separator();
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.style_mut().spacing.item_spacing = egui::vec2(0.0, 0.0);
-                for line in &lyrics.lyrics.lines {
-                    let current_time = self.player_timestamp;
-                    if current_time > line.begin && current_time < line.end {
-                        let mut current_translations_index_vec = Vec::new();
-                        ui.horizontal(|ui| {
-                            for segment in &line.original {
-                                if current_time > segment.begin && current_time < segment.end {
-                                    ui.colored_label(MfColors::ORANGE_500, &segment.text);
+                let (first, last) = if self.lyrics_focus_mode {
+                    let focus = focus_line_index(lines, self.player.timestamp);
+                    let first = focus.saturating_sub(self.lyrics_focus_context);
+                    let last = (focus + self.lyrics_focus_context + 1).min(lines.len());
+                    (first.min(last), last)
+                } else {
+                    (0, lines.len())
+                };
+                let columns = self.lyrics_columns;
+                let languages: Vec<&TranslationEntry> = lyrics
+                    .metadata
+                    .translations
+                    .iter()
+                    .filter(|entry| !self.hidden_translations.contains(&entry.id))
+                    .collect();
+                let opacity = ui.opacity();
+                let mut show_lines = |ui: &mut egui::Ui| {
+                    if columns {
+                        ui.colored_label(MfColors::GRAY_500, "Original");
+                        for entry in &languages {
+                            ui.colored_label(MfColors::GRAY_500, &entry.language);
+                        }
+                        ui.end_row();
+                    }
+                    for (line_index, line) in lines.iter().enumerate().take(last).skip(first) {
+                        // Filled in once the line is laid out, if it matches the find query.
+                        let highlight = ui.painter().add(egui::Shape::Noop);
+                        let top = ui.cursor().top();
+                        ui.set_opacity(if line.disabled && self.dim_disabled_lines {
+                            opacity * DISABLED_LINE_OPACITY
+                        } else {
+                            opacity
+                        });
+                        let current_time = self.player.timestamp;
+                        let direction = lyrics.line_direction(line);
+                        if line.is_active_at(current_time) {
+                            let mut current_translations_index_vec = Vec::new();
+                            let has_romanization = line_has_romanization(line);
+                            let has_ruby = self.show_ruby && line_has_ruby(line);
+                            show_segments(ui, line, direction, |ui, segment_index, segment| {
+                                ui.vertical(|ui| {
+                                    let ruby = has_ruby.then_some(segment.ruby.as_str());
+                                    let response = with_ruby(ui, ruby, MfColors::GRAY_500, |ui| {
+                                        if segment.is_active_at(current_time) {
+                                            current_translations_index_vec
+                                                .extend(segment.translations.clone());
+                                            segment_karaoke_label(
+                                                ui,
+                                                segment,
+                                                current_time,
+                                                direction,
+                                            )
+                                        } else if segment.needs_review {
+                                            segment_label(ui, segment, MfColors::YELLOW_500)
+                                        } else {
+                                            let color =
+                                                segment_color(segment, ui.visuals().text_color());
+                                            segment_label(ui, segment, color)
+                                        }
+                                    });
+                                    if armed_segment == Some((line.uuid, segment_index)) {
+                                        outline_armed_segment(ui, &response);
+                                    }
+                                    if let Some((begin, end)) =
+                                        segment_preview_request(response, segment)
+                                    {
+                                        request = Some(LyricsWindowRequest::Preview(begin, end));
+                                    }
+                                    if has_romanization {
+                                        show_romanization(ui, segment, MfColors::GRAY_500);
+                                    }
+                                });
+                            });
+                            for entry in &languages {
+                                let language_translations_index_vec =
                                     current_translations_index_vec
-                                        .extend(segment.translations.clone());
-                                } else {
-                                    ui.label(&segment.text);
-                                }
+                                        .iter()
+                                        .filter_map(|(translation_id, word_index_list)| {
+                                            if *translation_id == entry.id {
+                                                Some(word_index_list)
+                                            } else {
+                                                None
+                                            }
+                                        })
+                                        .flatten()
+                                        .copied()
+                                        .collect::<Vec<usize>>();
+                                show_translation_words(
+                                    ui,
+                                    translation_words(line, entry.id),
+                                    &language_translations_index_vec,
+                                    MfColors::GRAY_500,
+                                    columns,
+                                );
                             }
-                        });
-                        for (id, words) in &line.translations {
-                            let language_translations_index_vec = current_translations_index_vec
-                                .iter()
-                                .filter_map(|(translation_id, word_index_list)| {
-                                    if translation_id == id {
-                                        Some(word_index_list)
+                        } else {
+                            let has_romanization = line_has_romanization(line);
+                            let has_ruby = self.show_ruby && line_has_ruby(line);
+                            show_segments(ui, line, direction, |ui, segment_index, segment| {
+                                ui.vertical(|ui| {
+                                    let color = if segment.needs_review {
+                                        MfColors::YELLOW_700
                                     } else {
-                                        None
+                                        segment_color(segment, MfColors::GRAY_700)
+                                    };
+                                    let ruby = has_ruby.then_some(segment.ruby.as_str());
+                                    let response = with_ruby(ui, ruby, MfColors::GRAY_700, |ui| {
+                                        segment_label(ui, segment, color)
+                                    });
+                                    if armed_segment == Some((line.uuid, segment_index)) {
+                                        outline_armed_segment(ui, &response);
                                     }
-                                })
-                                .flatten()
-                                .copied()
-                                .collect::<Vec<usize>>();
-                            if !words.is_empty() {
-                                ui.horizontal(|ui| {
-                                    for (index, word) in words.iter().enumerate() {
-                                        if language_translations_index_vec.contains(&index) {
-                                            ui.colored_label(MfColors::ORANGE_500, word);
-                                        } else {
-                                            ui.colored_label(MfColors::GRAY_500, word);
-                                        }
+                                    if let Some((begin, end)) =
+                                        segment_preview_request(response, segment)
+                                    {
+                                        request = Some(LyricsWindowRequest::Preview(begin, end));
+                                    }
+                                    if has_romanization {
+                                        show_romanization(ui, segment, MfColors::GRAY_700);
                                     }
                                 });
+                            });
+                            // Stacked translations are only shown for the active line, but columns
+                            // are for reading along, so they are always filled in.
+                            if columns {
+                                for entry in &languages {
+                                    show_translation_words(
+                                        ui,
+                                        translation_words(line, entry.id),
+                                        &[],
+                                        MfColors::GRAY_700,
+                                        columns,
+                                    );
+                                }
                             }
                         }
-                    } else {
-                        ui.horizontal(|ui| {
-                            for segment in &line.original {
-                                ui.colored_label(MfColors::GRAY_700, &segment.text);
+                        if columns {
+                            ui.end_row();
+                        }
+
+                        if find.open && find_matches.contains(&line_index) {
+                            let rect = egui::Rect::from_x_y_ranges(
+                                ui.max_rect().x_range(),
+                                top..=ui.cursor().top(),
+                            );
+                            let color = if current_match == Some(line_index) {
+                                MfColors::ORANGE_500.gamma_multiply(0.3)
+                            } else {
+                                MfColors::YELLOW_500.gamma_multiply(0.15)
+                            };
+                            ui.painter()
+                                .set(highlight, egui::Shape::rect_filled(rect, 2.0, color));
+                            if find.scroll_pending && current_match == Some(line_index) {
+                                ui.scroll_to_rect(rect, Some(egui::Align::Center));
+                                find.scroll_pending = false;
                             }
-                        });
+                        }
                     }
+                };
+                if columns {
+                    egui::Grid::new("lyrics_columns_grid")
+                        .spacing(egui::vec2(LYRICS_COLUMN_SPACING, 0.0))
+                        .show(ui, show_lines);
+                } else {
+                    show_lines(ui);
                 }
             });
         });
+        request
     }
 }
 
-async fn audio_file_loader(
-    arc_loading_file: Arc<Mutex<bool>>,
-    details_tx: mpsc::Sender<AudioDetails>,
-    data_tx: mpsc::Sender<Vec<u8>>,
-    arc_sink: Arc<Mutex<Sink>>,
+/// What happens when playback reaches the end of the track.
+#[derive(PartialEq, Clone, Copy)]
+enum RepeatMode {
+    Off,
+
+    /// Replay the current track.
+    One,
+
+    /// Carry on through the playlist, going back to its first track after the last, or replay
+    /// the current track outside a playlist.
+    All,
+}
+
+impl RepeatMode {
+    fn label(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "Off",
+            RepeatMode::One => "Track",
+            RepeatMode::All => "All",
+        }
+    }
+}
+
+/// How the transport display shows a timestamp.
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+enum TimeFormat {
+    /// `M:SS.mmm`, or `H:MM:SS.mmm` from an hour on.
+    Clock,
+
+    /// Total seconds, to the millisecond.
+    Seconds,
+
+    /// Video timecode, `HH:MM:SS:FF`, at `fps` frames per second.
+    Timecode { fps: u32 },
+}
+
+impl TimeFormat {
+    fn label(&self) -> String {
+        match self {
+            TimeFormat::Clock => "Clock".to_string(),
+            TimeFormat::Seconds => "Seconds".to_string(),
+            TimeFormat::Timecode { fps } => format!("Timecode ({} fps)", fps),
+        }
+    }
+
+    fn format(&self, timestamp: Duration) -> String {
+        match *self {
+            TimeFormat::Clock => format_timestamp(timestamp),
+            TimeFormat::Seconds => {
+                format!("{:.3} s", timestamp.num_milliseconds() as f64 / 1000.0)
+            }
+            TimeFormat::Timecode { fps } => {
+                let milliseconds = timestamp.num_milliseconds();
+                format!(
+                    "{:02}:{:02}:{:02}:{:02}",
+                    timestamp.num_hours(),
+                    timestamp.num_minutes() % 60,
+                    timestamp.num_seconds() % 60,
+                    // The frame being shown, not the nearest one.
+                    (milliseconds % 1000) * fps as i64 / 1000
+                )
+            }
+        }
+    }
+
+    /// Reads a timestamp typed in this format, or `None` if it does not fit or is negative.
+    fn parse(&self, text: &str) -> Option<Duration> {
+        let text = text.trim();
+        match *self {
+            TimeFormat::Clock => parse_timestamp(text).ok(),
+            TimeFormat::Seconds => {
+                let seconds: f64 = text.trim_end_matches('s').trim().parse().ok()?;
+                let milliseconds = (seconds * 1000.0).round();
+                if !(0.0..i64::MAX as f64).contains(&milliseconds) {
+                    return None;
+                }
+                Duration::try_milliseconds(milliseconds as i64)
+            }
+            TimeFormat::Timecode { fps } => {
+                let parts = text
+                    .split(':')
+                    .map(|part| part.parse::<i64>().ok().filter(|x| *x >= 0))
+                    .collect::<Option<Vec<_>>>()?;
+                let [hours, minutes, seconds, frames] = parts[..] else {
+                    return None;
+                };
+                if minutes >= 60 || seconds >= 60 || frames >= fps as i64 {
+                    return None;
+                }
+                // Rounded up, so that the frame is shown again once formatted.
+                let frame_ms = (frames * 1000 + fps as i64 - 1) / fps as i64;
+                let milliseconds = hours
+                    .checked_mul(60)?
+                    .checked_add(minutes)?
+                    .checked_mul(60)?
+                    .checked_add(seconds)?
+                    .checked_mul(1000)?
+                    .checked_add(frame_ms)?;
+                Duration::try_milliseconds(milliseconds)
+            }
+        }
+    }
+}
+
+/// How the captions glide into place when the active line changes.
+#[derive(PartialEq, Clone, Copy)]
+enum Easing {
+    Linear,
+
+    /// Fast at first, settling gently.
+    EaseOut,
+
+    /// Gentle at both ends.
+    EaseInOut,
+}
+
+impl Easing {
+    fn label(&self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::EaseOut => "Ease out",
+            Easing::EaseInOut => "Ease in and out",
+        }
+    }
+
+    /// Maps the elapsed fraction `t` of a transition, from 0 to 1, to how far along it is.
+    fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// The Ctrl+F find bar of the lyrics window.
+#[derive(Default)]
+struct LyricsFind {
+    open: bool,
+    query: String,
+
+    /// Position of the current match among the matching lines.
+    current: usize,
+
+    /// Whether the current match still has to be scrolled into view.
+    scroll_pending: bool,
+}
+
+impl LyricsFind {
+    /// Indices of the lines whose original text, or one of whose translations not in
+    /// `hidden_translations`, contains the query, ignoring case.
+    fn matching_lines(
+        &self,
+        lines: &[LyricsLine],
+        hidden_translations: &HashSet<Uuid>,
+    ) -> Vec<usize> {
+        let query = self.query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                let text: String = line.original.iter().map(|seg| seg.text.as_str()).collect();
+                text.to_lowercase().contains(&query)
+                    || line.translations.iter().any(|(id, words)| {
+                        !hidden_translations.contains(id)
+                            && words.concat().to_lowercase().contains(&query)
+                    })
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Something the user asked for in the lyrics window.
+enum LyricsWindowRequest {
+    /// Move the playhead to a position.
+    Seek(Duration),
+
+    /// Play a `(begin, end)` span once and return to the current position.
+    Preview(Duration, Duration),
+
+    /// Pick a lyrics file to load.
+    Import,
+
+    /// Start new lyrics for the loaded audio in the editor.
+    Create,
+}
+
+/// Moves `path` to the front of a recent files list, keeping at most [`MAX_RECENT_FILES`].
+fn push_recent_file(recent_files: &mut Vec<String>, path: &str) {
+    recent_files.retain(|x| x != path);
+    recent_files.insert(0, path.to_string());
+    recent_files.truncate(MAX_RECENT_FILES);
+}
+
+/// The index of the line active at `timestamp`, or of the next line to come if none is.
+fn focus_line_index(lines: &[LyricsLine], timestamp: Duration) -> usize {
+    lines
+        .iter()
+        .position(|line| line.is_active_at(timestamp))
+        .unwrap_or_else(|| lines.iter().filter(|line| line.begin <= timestamp).count())
+}
+
+/// Shows the segments of `line` with `show_segment`, which is given the index of each, in a row
+/// in reading order, or one under the other for vertical text.
+fn show_segments<'a>(
+    ui: &mut egui::Ui,
+    line: &'a LyricsLine,
+    direction: TextDirection,
+    mut show_segment: impl FnMut(&mut egui::Ui, usize, &'a LyricsSegment),
+) {
+    let show_all = |ui: &mut egui::Ui| {
+        let segments = line.original.iter().enumerate();
+        if direction == TextDirection::RightToLeft {
+            for (index, segment) in segments.rev() {
+                show_segment(ui, index, segment);
+            }
+        } else {
+            for (index, segment) in segments {
+                show_segment(ui, index, segment);
+            }
+        }
+    };
+    if direction == TextDirection::Vertical {
+        ui.vertical(show_all);
+    } else {
+        ui.horizontal(show_all);
+    }
+}
+
+/// Shows a line of the compare window, its start in `time_color` and each segment in the color
+/// `segment_color` gives for its index, or a dash for a line missing on this side. Returns the
+/// start of the line if it is clicked.
+fn show_compared_line(
+    ui: &mut egui::Ui,
+    line: Option<&LyricsLine>,
+    time_color: egui::Color32,
+    segment_color: impl Fn(usize) -> egui::Color32,
+) -> Option<Duration> {
+    let Some(line) = line else {
+        ui.colored_label(MfColors::GRAY_500, "-");
+        return None;
+    };
+    ui.horizontal(|ui| {
+        let clicked = ui
+            .add(
+                egui::Label::new(
+                    RichText::new(format_timestamp(line.begin))
+                        .monospace()
+                        .color(time_color),
+                )
+                .sense(egui::Sense::click()),
+            )
+            .on_hover_text("Seek here")
+            .clicked();
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for (index, segment) in line.original.iter().enumerate() {
+            ui.colored_label(segment_color(index), &segment.text);
+        }
+        clicked.then_some(line.begin)
+    })
+    .inner
+}
+
+/// Colors for the segments of two versions of a line in the compare window: `color` for the
+/// segments found in both with the same timing, and otherwise yellow for a changed timing, red
+/// for a segment only in `old` and green for one only in `new`.
+fn compared_segment_colors(
+    old: &LyricsLine,
+    new: &LyricsLine,
+    color: egui::Color32,
+) -> (Vec<egui::Color32>, Vec<egui::Color32>) {
+    let mut old_colors = vec![MfColors::RED_400; old.original.len()];
+    let mut new_colors = vec![MfColors::GREEN_400; new.original.len()];
+    for (old_index, new_index) in align_segments(old, new) {
+        let (a, b) = (&old.original[old_index], &new.original[new_index]);
+        let same_timing = a.begin == b.begin && a.end == b.end;
+        let matched_color = if same_timing {
+            color
+        } else {
+            MfColors::YELLOW_400
+        };
+        old_colors[old_index] = matched_color;
+        new_colors[new_index] = matched_color;
+    }
+    (old_colors, new_colors)
+}
+
+/// A clickable label showing the text of a segment in its style.
+fn segment_label(
+    ui: &mut egui::Ui,
+    segment: &LyricsSegment,
+    color: egui::Color32,
+) -> egui::Response {
+    karaoke_label(
+        ui,
+        segment_text(ui, segment),
+        0.0,
+        color,
+        color,
+        false,
+        segment.style.bold,
+    )
+}
+
+/// A label for the segment being sung at `current_time`, filled as far as it has been sung.
+fn segment_karaoke_label(
+    ui: &mut egui::Ui,
+    segment: &LyricsSegment,
+    current_time: Duration,
+    direction: TextDirection,
+) -> egui::Response {
+    karaoke_label(
+        ui,
+        segment_text(ui, segment),
+        wipe_progress(segment.begin, segment.end, current_time),
+        segment_color(segment, ui.visuals().text_color()),
+        MfColors::ORANGE_500,
+        direction == TextDirection::RightToLeft,
+        segment.style.bold,
+    )
+}
+
+/// The text of a segment, italic and sized as its style says. Boldness and color are applied
+/// by [`karaoke_label`].
+fn segment_text(ui: &egui::Ui, segment: &LyricsSegment) -> RichText {
+    let size = egui::TextStyle::Body.resolve(ui.style()).size * segment.style.size;
+    let text = RichText::new(&segment.text).size(size);
+    if segment.style.italic {
+        text.italics()
+    } else {
+        text
+    }
+}
+
+/// The color of the segment's style, or `color` if it has none.
+fn segment_color(segment: &LyricsSegment, color: egui::Color32) -> egui::Color32 {
+    segment
+        .style
+        .color
+        .map_or(color, |[r, g, b]| egui::Color32::from_rgb(r, g, b))
+}
+
+/// Shows the timing of a segment when its label is hovered, and returns the span of the
+/// segment when the label is clicked with the command modifier held.
+fn segment_preview_request(
+    response: egui::Response,
+    segment: &LyricsSegment,
+) -> Option<(Duration, Duration)> {
+    let response = response.on_hover_text(format!(
+        "{} → {}\nCtrl+click to preview",
+        format_timestamp(segment.begin),
+        format_timestamp(segment.end)
+    ));
+    let command = response.ctx.input(|i| i.modifiers.command);
+    (response.clicked() && command).then_some((segment.begin, segment.end))
+}
+
+fn format_interval(seconds: u64) -> String {
+    if seconds.is_multiple_of(60) {
+        format!("{} min", seconds / 60)
+    } else {
+        format!("{} s", seconds)
+    }
+}
+
+/// The words of the translation of `line` into the language `id`.
+fn translation_words(line: &LyricsLine, id: Uuid) -> &[String] {
+    line.translations
+        .iter()
+        .find(|(translation_id, _)| *translation_id == id)
+        .map_or(&[], |(_, words)| &words[..])
+}
+
+/// Shows the words of a translation in a row, with the `highlighted` ones in orange. In the
+/// column layout, a missing translation still fills its cell so the columns stay aligned.
+fn show_translation_words(
+    ui: &mut egui::Ui,
+    words: &[String],
+    highlighted: &[usize],
+    color: egui::Color32,
+    columns: bool,
 ) {
+    if words.is_empty() {
+        if columns {
+            ui.label("");
+        }
+        return;
+    }
+    ui.horizontal(|ui| {
+        for (index, word) in words.iter().enumerate() {
+            if highlighted.contains(&index) {
+                ui.colored_label(MfColors::ORANGE_500, word);
+            } else {
+                ui.colored_label(color, word);
+            }
+        }
+    });
+}
+
+fn line_has_romanization(line: &LyricsLine) -> bool {
+    line.original
+        .iter()
+        .any(|segment| !segment.romanization.is_empty())
+}
+
+fn line_has_ruby(line: &LyricsLine) -> bool {
+    line.original.iter().any(|segment| !segment.ruby.is_empty())
+}
+
+/// Shows a segment with `show_base`, with the `ruby` reading painted in small text centered
+/// above it. A row is kept for the reading even when it is empty, so that the segments of a
+/// line stay level, and nothing is added without one.
+fn with_ruby(
+    ui: &mut egui::Ui,
+    ruby: Option<&str>,
+    color: egui::Color32,
+    show_base: impl FnOnce(&mut egui::Ui) -> egui::Response,
+) -> egui::Response {
+    let Some(ruby) = ruby else {
+        return show_base(ui);
+    };
+    let font_id = egui::TextStyle::Small.resolve(ui.style());
+    let row_height = ui.fonts(|fonts| fonts.row_height(&font_id));
+    let galley = ui
+        .painter()
+        .layout_no_wrap(ruby.to_string(), font_id, color);
+    let (row, _) = ui.allocate_exact_size(
+        egui::vec2(galley.size().x, row_height),
+        egui::Sense::hover(),
+    );
+    let response = show_base(ui);
+    // Readings wider than their segment spill over both sides, but not past the column.
+    let left = (response.rect.center().x - galley.size().x / 2.0).max(row.left());
+    ui.painter()
+        .galley(egui::pos2(left, row.top()), galley, color);
+    response
+}
+
+/// Shows the romanization of a segment in small text beneath it, keeping the row height even
+/// for segments without one.
+fn show_romanization(ui: &mut egui::Ui, segment: &LyricsSegment, color: egui::Color32) {
+    let text = if segment.romanization.is_empty() {
+        " "
+    } else {
+        segment.romanization.as_str()
+    };
+    ui.label(RichText::new(text).small().color(color));
+}
+
+fn list_output_devices() -> Vec<String> {
+    match rodio::cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            eprintln!("Failed to list output devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+async fn audio_file_loader(mut loader: AudioLoader) {
     let file = rfd::FileDialog::new()
-        .add_filter("Audio Files", &["mp3"])
+        .add_filter("Audio Files", &AUDIO_EXTENSIONS)
         .pick_file();
 
+    // The load only starts once a file is picked, so that cancelling the dialog leaves the
+    // results of the current file coming in.
     if let Some(path) = file {
-        *arc_loading_file.lock().unwrap() = true;
-        let path_str = path.to_string_lossy().to_string();
-        let file_name_str = path.file_name().unwrap().to_string_lossy().to_string();
-        match tokio::fs::read(&path_str).await {
-            Ok(data) => {
-                let _ = data_tx.send(data.clone()).await;
-                let len = data.len();
-
-                let source = Decoder::new(std::io::Cursor::new(data)).unwrap();
-
-                let _ = details_tx
-                    .send((
+        loader.start();
+        load_audio_file(path, loader).await;
+    }
+}
+
+/// Loads the audio file at `path` into the sink without showing a file dialog, replacing the
+/// audio in it. Nothing is loaded if another load has started in the meantime.
+async fn load_audio_file(path: PathBuf, loader: AudioLoader) {
+    *loader.arc_loading_file.lock().unwrap() = true;
+    let path_str = path.to_string_lossy().to_string();
+    let file_name_str = path.file_name().unwrap().to_string_lossy().to_string();
+    match tokio::fs::read(&path_str).await {
+        Ok(data) => {
+            let len = data.len();
+            let source = match decode_source(data.clone(), &loader.audio_effects) {
+                Ok(source) => source,
+                Err(e) => {
+                    if loader.is_latest() {
+                        let _ = loader
+                            .error_tx
+                            .send(format!("Unsupported audio file {}: {}", file_name_str, e))
+                            .await;
+                    }
+                    return;
+                }
+            };
+            if !loader.is_latest() {
+                return;
+            }
+            let _ = loader.data_tx.send((loader.id, data)).await;
+
+            let _ = loader
+                .details_tx
+                .send((
+                    loader.id,
+                    (
                         Some(path_str),
                         Some(file_name_str),
                         Some(len),
                         source
                             .total_duration()
-                            .map(|d| Duration::from_std(d).unwrap()),
-                    ))
+                            .and_then(|d| Duration::from_std(d).ok()),
+                        Some(source.sample_rate()),
+                        loader.audio_effects.trim.clone(),
+                    ),
+                ))
+                .await;
+            // Checked again while holding the id, so that a load starting meanwhile cannot
+            // end up behind this one in the sink.
+            let latest_id = loader.arc_latest_id.lock().unwrap();
+            if *latest_id == loader.id {
+                let sink = loader.arc_sink.lock().unwrap();
+                sink.clear();
+                sink.append(source);
+                sink.pause();
+            }
+        }
+        Err(e) => {
+            if loader.is_latest() {
+                let _ = loader
+                    .error_tx
+                    .send(format!("Failed to read {}: {}", file_name_str, e))
                     .await;
-                arc_sink.lock().unwrap().append(source);
-                arc_sink.lock().unwrap().pause();
             }
-            Err(e) => {
-                eprintln!("Failed to read file: {}", e);
+        }
+    }
+}
+
+/// Takes every message waiting on `rx` and returns the last one, so that a burst of results
+/// leaves the latest in place.
+fn latest<T>(rx: &mut mpsc::Receiver<T>) -> Option<T> {
+    let mut latest = None;
+    while let Ok(message) = rx.try_recv() {
+        latest = Some(message);
+    }
+    latest
+}
+
+/// Takes every message waiting on `rx` and returns the last one from the latest audio load,
+/// whose id is in `arc_load_id`, dropping those of earlier loads as stale. The id is read for
+/// each message, as a load may start while they are taken.
+fn latest_of_load<T>(rx: &mut mpsc::Receiver<(u64, T)>, arc_load_id: &Mutex<u64>) -> Option<T> {
+    let mut latest = None;
+    while let Ok((id, message)) = rx.try_recv() {
+        if id == *arc_load_id.lock().unwrap() {
+            latest = Some(message);
+        }
+    }
+    latest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timecode_round_trip() {
+        for fps in [24, 25, 30] {
+            let format = TimeFormat::Timecode { fps };
+            for frame in 0..fps {
+                let text = format!("01:02:03:{:02}", frame);
+                let timestamp = format.parse(&text).unwrap();
+                assert_eq!(format.format(timestamp), text, "at {} fps", fps);
             }
+            assert_eq!(format.parse(&format!("00:00:00:{}", fps)), None);
+        }
+    }
+
+    #[test]
+    fn timecode_rejects_bad_fields() {
+        let format = TimeFormat::Timecode { fps: 25 };
+        assert_eq!(format.parse("00:-1:00:00"), None);
+        assert_eq!(format.parse("00:00:-5:00"), None);
+        assert_eq!(format.parse("00:00:00:-1"), None);
+        assert_eq!(format.parse("00:60:00:00"), None);
+        assert_eq!(format.parse("00:00:00"), None);
+        assert_eq!(format.parse("9223372036854775807:00:00:00"), None);
+    }
+
+    #[test]
+    fn seconds_round_trip() {
+        let format = TimeFormat::Seconds;
+        let timestamp = format.parse("12.345 s").unwrap();
+        assert_eq!(timestamp, Duration::milliseconds(12_345));
+        assert_eq!(format.format(timestamp), "12.345 s");
+        assert_eq!(format.parse(" 7 "), Some(Duration::seconds(7)));
+    }
+
+    #[test]
+    fn seconds_rejects_non_finite_and_negative() {
+        let format = TimeFormat::Seconds;
+        for text in ["-inf", "inf", "NaN", "-1", "1e300"] {
+            assert_eq!(format.parse(text), None, "{}", text);
         }
     }
 }
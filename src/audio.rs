@@ -0,0 +1,1288 @@
+use rodio::source::SeekError;
+use rodio::{Decoder, Source};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How the channels of a stereo source are routed to the output.
+#[derive(PartialEq, Clone, Copy)]
+pub enum ChannelMode {
+    Stereo,
+    LeftOnly,
+    RightOnly,
+    Mono,
+}
+
+impl ChannelMode {
+    pub const ALL: [ChannelMode; 4] = [
+        ChannelMode::Stereo,
+        ChannelMode::LeftOnly,
+        ChannelMode::RightOnly,
+        ChannelMode::Mono,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChannelMode::Stereo => "Stereo",
+            ChannelMode::LeftOnly => "Left only",
+            ChannelMode::RightOnly => "Right only",
+            ChannelMode::Mono => "Mono mix",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct ChannelSettings {
+    pub mode: ChannelMode,
+
+    /// Balance between the left (-1.0) and right (1.0) channels.
+    pub balance: f32,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        ChannelSettings {
+            mode: ChannelMode::Stereo,
+            balance: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct MetronomeSettings {
+    pub enabled: bool,
+
+    /// Tempo of the beat grid, in beats per minute.
+    pub bpm: f32,
+
+    /// Position of the first beat, in milliseconds.
+    pub offset_ms: f32,
+
+    /// Number of beats in a bar. The first beat of each bar is accented.
+    pub beats_per_bar: u32,
+
+    pub volume: f32,
+}
+
+impl Default for MetronomeSettings {
+    fn default() -> Self {
+        MetronomeSettings {
+            enabled: false,
+            bpm: 120.0,
+            offset_ms: 0.0,
+            beats_per_bar: 4,
+            volume: 0.5,
+        }
+    }
+}
+
+/// How the playback speed is changed.
+#[derive(PartialEq, Clone, Copy)]
+pub enum SpeedMode {
+    /// Resample the audio, changing the pitch along with the tempo.
+    Resample,
+
+    /// Time-stretch the audio, keeping the original pitch.
+    PreservePitch,
+}
+
+impl SpeedMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpeedMode::Resample => "Resample",
+            SpeedMode::PreservePitch => "Preserve pitch",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SpeedSettings {
+    pub speed: f32,
+    pub mode: SpeedMode,
+}
+
+impl Default for SpeedSettings {
+    fn default() -> Self {
+        SpeedSettings {
+            speed: 1.0,
+            mode: SpeedMode::PreservePitch,
+        }
+    }
+}
+
+impl SpeedSettings {
+    /// Whether the time-stretch effect is active for these settings.
+    fn stretches(&self) -> bool {
+        self.mode == SpeedMode::PreservePitch && self.speed != 1.0
+    }
+
+    /// The speed the sink should resample at for these settings.
+    pub fn sink_speed(&self) -> f32 {
+        match self.mode {
+            SpeedMode::Resample => self.speed,
+            SpeedMode::PreservePitch => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct LoudnessSettings {
+    /// Whether the measured normalization gain is applied.
+    pub normalize: bool,
+
+    /// The gain that brings the loaded audio to the target loudness, in dB, once measured.
+    pub measured_gain_db: Option<f32>,
+
+    /// A manual gain applied on top of the normalization, in dB.
+    pub trim_db: f32,
+}
+
+impl LoudnessSettings {
+    /// The factor the samples are multiplied by for these settings.
+    pub fn factor(&self) -> f32 {
+        let normalization_db = if self.normalize {
+            self.measured_gain_db.unwrap_or(0.0)
+        } else {
+            0.0
+        };
+        10f32.powf((normalization_db + self.trim_db) / 20.0)
+    }
+}
+
+/// Shape of an equalizer band.
+#[derive(Clone, Copy)]
+pub enum BandKind {
+    LowShelf,
+    Peak,
+    HighShelf,
+}
+
+/// The bands of the equalizer, as `(kind, frequency in Hz, label)`.
+pub const EQUALIZER_BANDS: [(BandKind, f32, &str); 5] = [
+    (BandKind::LowShelf, 100.0, "100 Hz"),
+    (BandKind::Peak, 400.0, "400 Hz"),
+    (BandKind::Peak, 1000.0, "1 kHz"),
+    (BandKind::Peak, 2500.0, "2.5 kHz"),
+    (BandKind::HighShelf, 8000.0, "8 kHz"),
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub struct EqualizerSettings {
+    pub enabled: bool,
+
+    /// Gain of each of the [`EQUALIZER_BANDS`], in dB.
+    pub gains_db: [f32; EQUALIZER_BANDS.len()],
+}
+
+impl EqualizerSettings {
+    /// Whether these settings change the sound at all.
+    fn is_active(&self) -> bool {
+        self.enabled && self.gains_db.iter().any(|gain| *gain != 0.0)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FadeSettings {
+    /// Length of the fade-in on play and of the fade-out on pause.
+    pub length: Duration,
+
+    /// Whether the audio should be heard, as opposed to faded out.
+    pub audible: bool,
+
+    /// Bumped on every play, so that the source fades in from silence even if it had not
+    /// finished fading out.
+    pub fade_in_generation: u64,
+}
+
+impl Default for FadeSettings {
+    fn default() -> Self {
+        FadeSettings {
+            length: Duration::from_millis(80),
+            audible: false,
+            fade_in_generation: 0,
+        }
+    }
+}
+
+/// The part of an audio file that is played, to skip the silence at its ends.
+///
+/// Each file has settings of its own, shared by the sources playing it, so that a trim never
+/// applies to another file, such as the next track of a playlist.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct TrimSettings {
+    /// Where in the file playback starts, and where timestamps count from.
+    pub start: Duration,
+
+    /// Where in the file playback ends, or `None` to play to the end.
+    pub end: Option<Duration>,
+}
+
+/// The level of the audio leaving the effects, measured by [`LevelMeter`].
+#[derive(Clone, Copy, Default)]
+pub struct OutputLevels {
+    /// Highest absolute sample of the last measured block.
+    pub peak: f32,
+
+    /// Root mean square of the samples of the last measured block.
+    pub rms: f32,
+
+    /// When a sample last reached full scale, if one has.
+    pub last_clip: Option<Instant>,
+}
+
+/// Playback settings shared between the UI and the sources playing in the sink.
+///
+/// Sources built with [`decode_source`] pick up changes while they play, so the settings apply
+/// to the loaded audio and survive seeking.
+#[derive(Clone, Default)]
+pub struct AudioEffects {
+    pub channels: Arc<Mutex<ChannelSettings>>,
+    pub metronome: Arc<Mutex<MetronomeSettings>>,
+    pub speed: Arc<Mutex<SpeedSettings>>,
+    pub loudness: Arc<Mutex<LoudnessSettings>>,
+    pub equalizer: Arc<Mutex<EqualizerSettings>>,
+    pub fade: Arc<Mutex<FadeSettings>>,
+    pub trim: Arc<Mutex<TrimSettings>>,
+
+    /// Written by the playing source rather than read by it.
+    pub levels: Arc<Mutex<OutputLevels>>,
+}
+
+impl AudioEffects {
+    /// A copy of the effects that trims sources by `trim`, for playing a file other than the
+    /// one the current trim settings belong to.
+    pub fn with_trim(&self, trim: Arc<Mutex<TrimSettings>>) -> AudioEffects {
+        AudioEffects {
+            trim,
+            ..self.clone()
+        }
+    }
+}
+
+/// Decodes audio file data into a source with the playback effects applied.
+pub fn decode_source(
+    data: Vec<u8>,
+    effects: &AudioEffects,
+) -> anyhow::Result<impl Source<Item = f32> + Send + 'static> {
+    let decoder = Decoder::new(std::io::Cursor::new(data))?;
+    let loudness = effects.loudness.clone();
+    let source = Trim::new(decoder.convert_samples(), effects.trim.clone());
+    let source = source
+        .amplify(1.0)
+        .periodic_access(LOUDNESS_REFRESH_PERIOD, move |source| {
+            source.set_factor(loudness.lock().unwrap().factor());
+        });
+    let source = Equalizer::new(source, effects.equalizer.clone());
+    let source = ChannelMixer::new(source, effects.channels.clone());
+    let source = Metronome::new(source, effects.metronome.clone());
+    let source = TimeStretch::new(source, effects.speed.clone());
+    let source = Fade::new(source, effects.fade.clone());
+    Ok(LevelMeter::new(source, effects.levels.clone()))
+}
+
+/// How often the playing source picks up changes to the [`LoudnessSettings`].
+const LOUDNESS_REFRESH_PERIOD: Duration = Duration::from_millis(50);
+
+/// Number of frames between refreshes of the cached settings of an effect.
+const SETTINGS_REFRESH_FRAMES: usize = 1024;
+
+/// A source adapter that applies [`ChannelSettings`] to a stereo source.
+///
+/// Sources with a channel count other than two are passed through unchanged.
+pub struct ChannelMixer<S> {
+    input: S,
+    settings: Arc<Mutex<ChannelSettings>>,
+    cached_settings: ChannelSettings,
+    frames_until_refresh: usize,
+
+    /// The processed left and right samples of the current frame.
+    frame: [f32; 2],
+    frame_position: usize,
+}
+
+impl<S> ChannelMixer<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: Arc<Mutex<ChannelSettings>>) -> Self {
+        let cached_settings = *settings.lock().unwrap();
+        ChannelMixer {
+            input,
+            settings,
+            cached_settings,
+            frames_until_refresh: SETTINGS_REFRESH_FRAMES,
+            frame: [0.0; 2],
+            frame_position: 2,
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<()> {
+        if self.frames_until_refresh == 0 {
+            if let Ok(settings) = self.settings.try_lock() {
+                self.cached_settings = *settings;
+            }
+            self.frames_until_refresh = SETTINGS_REFRESH_FRAMES;
+        }
+        self.frames_until_refresh -= 1;
+
+        let left = self.input.next()?;
+        let right = self.input.next().unwrap_or(0.0);
+        let (left, right) = match self.cached_settings.mode {
+            ChannelMode::Stereo => (left, right),
+            ChannelMode::LeftOnly => (left, left),
+            ChannelMode::RightOnly => (right, right),
+            ChannelMode::Mono => ((left + right) / 2.0, (left + right) / 2.0),
+        };
+        let balance = self.cached_settings.balance.clamp(-1.0, 1.0);
+        self.frame = [
+            left * (1.0 - balance).min(1.0),
+            right * (1.0 + balance).min(1.0),
+        ];
+        self.frame_position = 0;
+        Some(())
+    }
+}
+
+impl<S> Iterator for ChannelMixer<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.input.channels() != 2 && self.frame_position >= 2 {
+            return self.input.next();
+        }
+        if self.frame_position >= 2 {
+            self.next_frame()?;
+        }
+        let sample = self.frame[self.frame_position];
+        self.frame_position += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for ChannelMixer<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input
+            .current_frame_len()
+            .map(|len| len + 2 - self.frame_position.min(2))
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.frame_position = 2;
+        self.input.try_seek(pos)
+    }
+}
+
+/// A second-order IIR filter section.
+#[derive(Clone, Copy, Default)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+
+    /// The last two inputs and outputs.
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    /// A filter for an equalizer band, after the Audio EQ Cookbook by Robert Bristow-Johnson.
+    fn band(kind: BandKind, frequency: f32, gain_db: f32, sample_rate: u32) -> Self {
+        let a = 10f32.powf(gain_db / 40.0);
+        // Keep the band below the Nyquist frequency at low sample rates.
+        let frequency = frequency.min(sample_rate as f32 * 0.45);
+        let w0 = 2.0 * std::f32::consts::PI * frequency / sample_rate as f32;
+        let (sin, cos) = w0.sin_cos();
+        let (b0, b1, b2, a0, a1, a2) = match kind {
+            BandKind::Peak => {
+                // A Q of 1, about 1.4 octaves wide.
+                let alpha = sin / 2.0;
+                (
+                    1.0 + alpha * a,
+                    -2.0 * cos,
+                    1.0 - alpha * a,
+                    1.0 + alpha / a,
+                    -2.0 * cos,
+                    1.0 - alpha / a,
+                )
+            }
+            BandKind::LowShelf | BandKind::HighShelf => {
+                // A shelf slope of 1, the steepest without overshoot.
+                let beta = 2.0 * a.sqrt() * sin / std::f32::consts::SQRT_2;
+                let sign = if matches!(kind, BandKind::LowShelf) {
+                    1.0
+                } else {
+                    -1.0
+                };
+                (
+                    a * ((a + 1.0) - sign * (a - 1.0) * cos + beta),
+                    sign * 2.0 * a * ((a - 1.0) - sign * (a + 1.0) * cos),
+                    a * ((a + 1.0) - sign * (a - 1.0) * cos - beta),
+                    (a + 1.0) + sign * (a - 1.0) * cos + beta,
+                    -sign * 2.0 * ((a - 1.0) + sign * (a + 1.0) * cos),
+                    (a + 1.0) + sign * (a - 1.0) * cos - beta,
+                )
+            }
+        };
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.b1 * self.x[0] + self.b2 * self.x[1]
+            - self.a1 * self.y[0]
+            - self.a2 * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+/// A source adapter that applies [`EqualizerSettings`] to every channel of its input.
+pub struct Equalizer<S> {
+    input: S,
+    settings: Arc<Mutex<EqualizerSettings>>,
+    cached_settings: EqualizerSettings,
+    samples_until_refresh: usize,
+
+    /// The filters of each channel, one per band.
+    filters: Vec<[Biquad; EQUALIZER_BANDS.len()]>,
+
+    /// The channel of the next sample.
+    channel: usize,
+}
+
+impl<S> Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: Arc<Mutex<EqualizerSettings>>) -> Self {
+        let cached_settings = *settings.lock().unwrap();
+        let mut equalizer = Equalizer {
+            input,
+            settings,
+            cached_settings,
+            samples_until_refresh: 0,
+            filters: Vec::new(),
+            channel: 0,
+        };
+        equalizer.update_filters();
+        equalizer
+    }
+
+    /// Rebuilds the filters for the cached settings, keeping their state.
+    fn update_filters(&mut self) {
+        let channels = self.input.channels().max(1) as usize;
+        let sample_rate = self.input.sample_rate();
+        self.filters
+            .resize(channels, [Biquad::default(); EQUALIZER_BANDS.len()]);
+        for filters in &mut self.filters {
+            for (band, filter) in filters.iter_mut().enumerate() {
+                let (kind, frequency, _) = EQUALIZER_BANDS[band];
+                let gain_db = self.cached_settings.gains_db[band];
+                *filter = Biquad {
+                    x: filter.x,
+                    y: filter.y,
+                    ..Biquad::band(kind, frequency, gain_db, sample_rate)
+                };
+            }
+        }
+    }
+}
+
+impl<S> Iterator for Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.samples_until_refresh == 0 {
+            let settings = self.settings.try_lock().ok().map(|settings| *settings);
+            if let Some(settings) = settings {
+                if settings != self.cached_settings {
+                    self.cached_settings = settings;
+                    self.update_filters();
+                }
+            }
+            self.samples_until_refresh = SETTINGS_REFRESH_FRAMES * self.filters.len();
+        }
+        self.samples_until_refresh -= 1;
+
+        let sample = self.input.next()?;
+        let channel = self.channel % self.filters.len();
+        self.channel = channel + 1;
+        if !self.cached_settings.is_active() {
+            return Some(sample);
+        }
+        Some(
+            self.filters[channel]
+                .iter_mut()
+                .fold(sample, |sample, filter| filter.process(sample)),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        // Seeking lands on the first channel, and the old filter state would click.
+        self.channel = 0;
+        for filters in &mut self.filters {
+            for filter in filters.iter_mut() {
+                filter.x = [0.0; 2];
+                filter.y = [0.0; 2];
+            }
+        }
+        self.input.try_seek(pos)
+    }
+}
+
+/// Number of frames between refreshes of the cached [`FadeSettings`], kept short so that fades
+/// start right away.
+const FADE_REFRESH_FRAMES: usize = 128;
+
+/// A source adapter that ramps the volume up or down following [`FadeSettings`].
+pub struct Fade<S> {
+    input: S,
+    settings: Arc<Mutex<FadeSettings>>,
+    cached_settings: FadeSettings,
+    samples_until_refresh: usize,
+
+    /// The current volume, between 0.0 and 1.0.
+    gain: f32,
+
+    /// The channel of the next sample.
+    channel: usize,
+}
+
+impl<S> Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: Arc<Mutex<FadeSettings>>) -> Self {
+        let cached_settings = *settings.lock().unwrap();
+        Fade {
+            input,
+            settings,
+            cached_settings,
+            samples_until_refresh: 0,
+            // A source added during playback, e.g. the next track of a playlist, starts at full
+            // volume so that it follows on without a gap.
+            gain: if cached_settings.audible { 1.0 } else { 0.0 },
+            channel: 0,
+        }
+    }
+
+    /// How much the gain changes per frame.
+    fn step(&self) -> f32 {
+        let frames = self.cached_settings.length.as_secs_f32() * self.input.sample_rate() as f32;
+        if frames < 1.0 {
+            1.0
+        } else {
+            1.0 / frames
+        }
+    }
+}
+
+impl<S> Iterator for Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channels = self.input.channels().max(1) as usize;
+        if self.samples_until_refresh == 0 {
+            if let Ok(settings) = self.settings.try_lock() {
+                if settings.fade_in_generation != self.cached_settings.fade_in_generation {
+                    self.gain = 0.0;
+                }
+                self.cached_settings = *settings;
+            }
+            self.samples_until_refresh = FADE_REFRESH_FRAMES * channels;
+        }
+        self.samples_until_refresh -= 1;
+
+        let sample = self.input.next()?;
+        if self.channel == 0 {
+            let target = if self.cached_settings.audible {
+                1.0
+            } else {
+                0.0
+            };
+            let step = self.step();
+            self.gain = if self.gain < target {
+                (self.gain + step).min(target)
+            } else {
+                (self.gain - step).max(target)
+            };
+        }
+        self.channel = (self.channel + 1) % channels;
+        Some(sample * self.gain)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Fade<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.channel = 0;
+        self.input.try_seek(pos)
+    }
+}
+
+/// A source adapter that plays only the part of its input given by [`TrimSettings`], with
+/// positions counted from the start of that part.
+pub struct Trim<S> {
+    input: S,
+    settings: Arc<Mutex<TrimSettings>>,
+    cached_settings: TrimSettings,
+    samples_until_refresh: usize,
+
+    /// Position in the file of the next sample, counted in samples.
+    position: u64,
+}
+
+impl<S> Trim<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: Arc<Mutex<TrimSettings>>) -> Self {
+        let cached_settings = *settings.lock().unwrap();
+        Trim {
+            input,
+            settings,
+            cached_settings,
+            samples_until_refresh: 0,
+            position: 0,
+        }
+    }
+
+    /// The position of `time` in the file, in samples, rounded down to a whole frame.
+    fn samples_at(&self, time: Duration) -> u64 {
+        let channels = self.input.channels().max(1) as u64;
+        (time.as_secs_f64() * self.input.sample_rate() as f64) as u64 * channels
+    }
+}
+
+impl<S> Iterator for Trim<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.samples_until_refresh == 0 {
+            if let Ok(settings) = self.settings.try_lock() {
+                self.cached_settings = *settings;
+            }
+            self.samples_until_refresh =
+                SETTINGS_REFRESH_FRAMES * self.input.channels().max(1) as usize;
+        }
+        self.samples_until_refresh -= 1;
+
+        let TrimSettings { start, end } = self.cached_settings;
+        let start_samples = self.samples_at(start);
+        if self.position < start_samples {
+            if self.input.try_seek(start).is_ok() {
+                self.position = start_samples;
+            } else {
+                // Sources that cannot seek are read through to the start instead.
+                while self.position < start_samples {
+                    self.input.next()?;
+                    self.position += 1;
+                }
+            }
+        }
+        if end.is_some_and(|end| self.position >= self.samples_at(end)) {
+            return None;
+        }
+        let sample = self.input.next()?;
+        self.position += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.input.size_hint().1)
+    }
+}
+
+impl<S> Source for Trim<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let total = self.input.total_duration()?;
+        let TrimSettings { start, end } = self.cached_settings;
+        Some(
+            end.map_or(total, |end| end.min(total))
+                .saturating_sub(start),
+        )
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        // Seeks come from the interface right after it changes the settings, so they are
+        // picked up straight away.
+        if let Ok(settings) = self.settings.try_lock() {
+            self.cached_settings = *settings;
+        }
+        let target = pos + self.cached_settings.start;
+        self.input.try_seek(target)?;
+        self.position = self.samples_at(target);
+        Ok(())
+    }
+}
+
+/// Number of frames measured together by a [`LevelMeter`].
+const LEVEL_BLOCK_FRAMES: usize = 1024;
+
+/// A source adapter that passes its input through unchanged, measuring its level into
+/// [`OutputLevels`] every [`LEVEL_BLOCK_FRAMES`] frames.
+pub struct LevelMeter<S> {
+    input: S,
+    levels: Arc<Mutex<OutputLevels>>,
+
+    peak: f32,
+    sum_of_squares: f32,
+    samples: usize,
+}
+
+impl<S> LevelMeter<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, levels: Arc<Mutex<OutputLevels>>) -> Self {
+        LevelMeter {
+            input,
+            levels,
+            peak: 0.0,
+            sum_of_squares: 0.0,
+            samples: 0,
+        }
+    }
+}
+
+impl<S> Iterator for LevelMeter<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.input.next()?;
+        self.peak = self.peak.max(sample.abs());
+        self.sum_of_squares += sample * sample;
+        self.samples += 1;
+        if self.samples >= LEVEL_BLOCK_FRAMES * self.input.channels().max(1) as usize {
+            if let Ok(mut levels) = self.levels.try_lock() {
+                levels.peak = self.peak;
+                levels.rms = (self.sum_of_squares / self.samples as f32).sqrt();
+                if self.peak >= 1.0 {
+                    levels.last_clip = Some(Instant::now());
+                }
+            }
+            self.peak = 0.0;
+            self.sum_of_squares = 0.0;
+            self.samples = 0;
+        }
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for LevelMeter<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)
+    }
+}
+
+/// Length of a metronome click, in seconds.
+const CLICK_LENGTH: f64 = 0.03;
+
+/// A source adapter that mixes metronome clicks into its input at every beat.
+///
+/// The beats are placed from the position of the input, so they stay aligned after seeking.
+pub struct Metronome<S> {
+    input: S,
+    settings: Arc<Mutex<MetronomeSettings>>,
+    cached_settings: MetronomeSettings,
+
+    /// Index of the next sample, counted from the start of the input.
+    sample_index: u64,
+}
+
+impl<S> Metronome<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: Arc<Mutex<MetronomeSettings>>) -> Self {
+        let cached_settings = *settings.lock().unwrap();
+        Metronome {
+            input,
+            settings,
+            cached_settings,
+            sample_index: 0,
+        }
+    }
+
+    /// The click sample at `time` seconds from the start of the input.
+    fn click_at(&self, time: f64) -> f32 {
+        let settings = &self.cached_settings;
+        if !settings.enabled || settings.bpm <= 0.0 {
+            return 0.0;
+        }
+        let time = time - settings.offset_ms as f64 / 1000.0;
+        if time < 0.0 {
+            return 0.0;
+        }
+        let beat_length = 60.0 / settings.bpm as f64;
+        let beat = (time / beat_length).floor();
+        let phase = time - beat * beat_length;
+        if phase >= CLICK_LENGTH {
+            return 0.0;
+        }
+        let accented = settings.beats_per_bar > 0
+            && (beat as u64).is_multiple_of(settings.beats_per_bar as u64);
+        let frequency = if accented { 1500.0 } else { 1000.0 };
+        let envelope = (-phase / CLICK_LENGTH * 5.0).exp();
+        ((std::f64::consts::TAU * frequency * phase).sin() * envelope) as f32 * settings.volume
+    }
+}
+
+impl<S> Iterator for Metronome<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let channels = self.input.channels().max(1) as u64;
+        if self
+            .sample_index
+            .is_multiple_of(channels * SETTINGS_REFRESH_FRAMES as u64)
+        {
+            if let Ok(settings) = self.settings.try_lock() {
+                self.cached_settings = *settings;
+            }
+        }
+        let sample = self.input.next()?;
+        let time = (self.sample_index / channels) as f64 / self.input.sample_rate() as f64;
+        self.sample_index += 1;
+        Some(sample + self.click_at(time))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.input.size_hint()
+    }
+}
+
+impl<S> Source for Metronome<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        let frame = (pos.as_secs_f64() * self.input.sample_rate() as f64) as u64;
+        self.sample_index = frame * self.input.channels() as u64;
+        Ok(())
+    }
+}
+
+/// Length of a time-stretch frame, in seconds.
+const STRETCH_FRAME_LENGTH: f64 = 0.04;
+
+/// How far a time-stretch frame may be shifted to line up with the previous one, in seconds.
+const STRETCH_TOLERANCE: f64 = 0.008;
+
+/// Only every n-th sample is compared when searching for the best frame alignment.
+const STRETCH_CORRELATION_STEP: usize = 4;
+
+/// A source adapter that changes the tempo of its input without changing the pitch, using
+/// waveform-similarity overlap-add (WSOLA).
+///
+/// Frames of the input are taken at `speed` times the output rate, each shifted within a small
+/// tolerance to best continue the previous frame, and overlap-added with a Hann window. The
+/// input is passed through unchanged when [`SpeedSettings::stretches`] is false.
+pub struct TimeStretch<S> {
+    input: S,
+    settings: Arc<Mutex<SpeedSettings>>,
+    cached_settings: SpeedSettings,
+    channels: usize,
+
+    /// Frame length, synthesis hop and search tolerance, in frames.
+    frame_length: usize,
+    hop: usize,
+    tolerance: usize,
+    window: Vec<f32>,
+
+    /// Interleaved input samples, starting at the input frame `buffer_start`.
+    buffer: VecDeque<f32>,
+    buffer_start: u64,
+    input_done: bool,
+
+    /// Number of samples read from the input while passing through.
+    samples_read: u64,
+
+    /// Where the next analysis frame nominally starts in the input, in frames.
+    analysis_position: f64,
+
+    /// Where the previous analysis frame started in the input, in frames.
+    previous_frame: Option<u64>,
+
+    /// Overlap-add accumulator, one frame long.
+    overlap: Vec<f32>,
+
+    /// Samples ready to be played.
+    output: VecDeque<f32>,
+    stretching: bool,
+}
+
+impl<S> TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(input: S, settings: Arc<Mutex<SpeedSettings>>) -> Self {
+        let cached_settings = *settings.lock().unwrap();
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate() as f64;
+        let frame_length = ((sample_rate * STRETCH_FRAME_LENGTH) as usize / 2 * 2).max(2);
+        let window = (0..frame_length)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / frame_length as f32).cos())
+            .collect();
+        TimeStretch {
+            input,
+            settings,
+            cached_settings,
+            channels,
+            frame_length,
+            hop: frame_length / 2,
+            tolerance: (sample_rate * STRETCH_TOLERANCE) as usize,
+            window,
+            buffer: VecDeque::new(),
+            buffer_start: 0,
+            input_done: false,
+            samples_read: 0,
+            analysis_position: 0.0,
+            previous_frame: None,
+            overlap: vec![0.0; frame_length * channels],
+            output: VecDeque::new(),
+            stretching: false,
+        }
+    }
+
+    /// Clears the stretch state so that stretching restarts at the input frame `position`.
+    fn restart_at(&mut self, position: u64) {
+        self.buffer.clear();
+        self.buffer_start = position;
+        self.input_done = false;
+        self.analysis_position = position as f64;
+        self.previous_frame = None;
+        self.overlap.iter_mut().for_each(|x| *x = 0.0);
+        self.output.clear();
+    }
+
+    fn buffer_end(&self) -> u64 {
+        self.buffer_start + (self.buffer.len() / self.channels) as u64
+    }
+
+    /// Reads the input into the buffer until it holds the frames before `end`.
+    fn fill_to(&mut self, end: u64) {
+        while !self.input_done && self.buffer_end() < end {
+            for channel in 0..self.channels {
+                match self.input.next() {
+                    Some(sample) => self.buffer.push_back(sample),
+                    None => {
+                        // Pad an incomplete frame so that the buffer stays aligned.
+                        if channel != 0 {
+                            self.buffer
+                                .extend(std::iter::repeat_n(0.0, self.channels - channel));
+                        }
+                        self.input_done = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn sample(&self, frame: u64, channel: usize) -> f32 {
+        if frame < self.buffer_start {
+            return 0.0;
+        }
+        let index = (frame - self.buffer_start) as usize * self.channels + channel;
+        self.buffer.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// The sum of all channels of a frame, used for the similarity search.
+    fn mixed_sample(&self, frame: u64) -> f32 {
+        (0..self.channels)
+            .map(|channel| self.sample(frame, channel))
+            .sum()
+    }
+
+    /// Finds the start of the next analysis frame around its nominal position, so that it best
+    /// continues the previous frame.
+    fn find_next_frame(&mut self, nominal: u64) -> u64 {
+        let Some(previous_frame) = self.previous_frame else {
+            return nominal;
+        };
+        let natural = previous_frame + self.hop as u64;
+        let overlap_length = self.frame_length - self.hop;
+        let first = nominal
+            .saturating_sub(self.tolerance as u64)
+            .max(self.buffer_start);
+        let last = nominal + self.tolerance as u64;
+        self.fill_to((last + overlap_length as u64).max(natural + overlap_length as u64));
+
+        let mut best = nominal.max(first);
+        let mut best_score = f32::MIN;
+        for candidate in first..=last {
+            let score: f32 = (0..overlap_length)
+                .step_by(STRETCH_CORRELATION_STEP)
+                .map(|i| {
+                    self.mixed_sample(natural + i as u64) * self.mixed_sample(candidate + i as u64)
+                })
+                .sum();
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+        best
+    }
+
+    /// Produces the next hop of output. Returns `None` once the input is exhausted.
+    fn process_hop(&mut self) -> Option<()> {
+        let nominal = (self.analysis_position.round() as u64).max(self.buffer_start);
+        let frame = self.find_next_frame(nominal);
+        self.fill_to(frame + self.frame_length as u64);
+        if self.input_done && frame >= self.buffer_end() {
+            return None;
+        }
+
+        for i in 0..self.frame_length {
+            for channel in 0..self.channels {
+                self.overlap[i * self.channels + channel] +=
+                    self.window[i] * self.sample(frame + i as u64, channel);
+            }
+        }
+        let hop_samples = self.hop * self.channels;
+        self.output.extend(self.overlap.drain(..hop_samples));
+        self.overlap.extend(std::iter::repeat_n(0.0, hop_samples));
+
+        self.previous_frame = Some(frame);
+        self.analysis_position += self.hop as f64 * self.cached_settings.speed as f64;
+
+        // Drop the input that neither the next template nor the next search can reach.
+        let keep_from = (frame + self.hop as u64)
+            .min((self.analysis_position as u64).saturating_sub(self.tolerance as u64));
+        if keep_from > self.buffer_start {
+            let drop_frames = (keep_from - self.buffer_start) as usize;
+            let drop_samples = (drop_frames * self.channels).min(self.buffer.len());
+            self.buffer.drain(..drop_samples);
+            self.buffer_start += (drop_samples / self.channels) as u64;
+        }
+        Some(())
+    }
+}
+
+impl<S> Iterator for TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(sample) = self.output.pop_front() {
+            return Some(sample);
+        }
+
+        // While stretching, this point is reached once per hop.
+        if self.stretching
+            || self
+                .samples_read
+                .is_multiple_of((SETTINGS_REFRESH_FRAMES * self.channels) as u64)
+        {
+            if let Ok(settings) = self.settings.try_lock() {
+                self.cached_settings = *settings;
+            }
+        }
+
+        if !self.cached_settings.stretches() {
+            if self.stretching {
+                // Continue from where the stretched playback had reached in the input.
+                self.stretching = false;
+                self.samples_read = self.buffer_end() * self.channels as u64;
+            }
+            let sample = self.input.next()?;
+            self.samples_read += 1;
+            return Some(sample);
+        }
+
+        if !self.stretching {
+            self.stretching = true;
+            self.restart_at(self.samples_read / self.channels as u64);
+        }
+        self.process_hop()?;
+        self.output.pop_front()
+    }
+}
+
+impl<S> Source for TimeStretch<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        if self.stretching {
+            // The output is produced in hops that do not line up with the input frames.
+            Some(self.output.len().max(self.channels))
+        } else {
+            self.input.current_frame_len()
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        let frame = (pos.as_secs_f64() * self.input.sample_rate() as f64) as u64;
+        self.samples_read = frame * self.channels as u64;
+        self.restart_at(frame);
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::Duration;
+use eframe::egui::{self, Color32};
+
+/// Returns how far playback has progressed through the span from `begin` to `end`, from 0 to 1.
+pub fn wipe_progress(begin: Duration, end: Duration, timestamp: Duration) -> f32 {
+    let length = (end - begin).num_milliseconds();
+    if length <= 0 {
+        return 1.0;
+    }
+    ((timestamp - begin).num_milliseconds() as f32 / length as f32).clamp(0.0, 1.0)
+}
+
+/// How far bold text is drawn a second time to the right, as there is no bold font.
+const BOLD_OFFSET: f32 = 0.6;
+
+/// A label whose text is filled with `fill_color` up to `progress`, with the rest drawn in
+/// `base_color`. The fill runs from left to right, or from right to left for right-to-left
+/// text. The label can be clicked.
+pub fn karaoke_label(
+    ui: &mut egui::Ui,
+    text: impl Into<egui::WidgetText>,
+    progress: f32,
+    base_color: Color32,
+    fill_color: Color32,
+    right_to_left: bool,
+    bold: bool,
+) -> egui::Response {
+    let galley = text.into().into_galley(
+        ui,
+        Some(egui::TextWrapMode::Extend),
+        f32::INFINITY,
+        egui::TextStyle::Body,
+    );
+    let bold_offset = egui::vec2(if bold { BOLD_OFFSET } else { 0.0 }, 0.0);
+    let (rect, response) =
+        ui.allocate_exact_size(galley.size() + bold_offset, egui::Sense::click());
+    if ui.is_rect_visible(rect) {
+        let paint = |painter: &egui::Painter, color: Color32| {
+            painter.galley_with_override_text_color(rect.min, galley.clone(), color);
+            if bold {
+                painter.galley_with_override_text_color(
+                    rect.min + bold_offset,
+                    galley.clone(),
+                    color,
+                );
+            }
+        };
+        let painter = ui.painter_at(rect);
+        paint(&painter, base_color);
+
+        let mut fill_rect = rect;
+        if right_to_left {
+            fill_rect.min.x = rect.max.x - rect.width() * progress;
+        } else {
+            fill_rect.max.x = rect.min.x + rect.width() * progress;
+        }
+        paint(&painter.with_clip_rect(fill_rect), fill_color);
+    }
+    response
+}
@@ -1 +1,4 @@
 pub mod colors;
+pub mod karaoke;
+pub mod timeline;
+pub mod waveform;
@@ -0,0 +1,236 @@
+use chrono::Duration;
+use eframe::egui::{self, Align2, CursorIcon, FontId, Rect, Sense, Stroke};
+
+use crate::component::colors::MfColors;
+use crate::lyrics::{BabelLyrics, LyricsLine};
+
+const ROW_HEIGHT: f32 = 24.0;
+const AXIS_HEIGHT: f32 = 16.0;
+
+/// Width of the grab area at each edge of a segment block, in points.
+const EDGE_WIDTH: f32 = 6.0;
+
+/// View settings of the timeline editor.
+pub struct TimelineState {
+    /// Horizontal zoom, in points per second.
+    pub points_per_second: f32,
+}
+
+impl Default for TimelineState {
+    fn default() -> Self {
+        TimelineState {
+            points_per_second: 100.0,
+        }
+    }
+}
+
+/// Which part of a segment block is being dragged.
+enum DragTarget {
+    Begin,
+    End,
+    Whole,
+}
+
+/// Shows the lyrics on a horizontal time axis, one row per line, with each segment as a block.
+/// Markers are shown as labelled bands behind the lines.
+///
+/// Dragging the edges of a block changes the `begin`/`end` of its segment, and dragging the
+/// block moves the segment. The line is stretched to cover its segments after a drag. The
+/// segment under the playhead is highlighted, as in the lyrics and captions windows.
+pub fn show_timeline(
+    ui: &mut egui::Ui,
+    lyrics: &mut BabelLyrics,
+    state: &mut TimelineState,
+    player_timestamp: Duration,
+) {
+    ui.horizontal(|ui| {
+        ui.label("Zoom");
+        ui.add(
+            egui::Slider::new(&mut state.points_per_second, 10.0..=1000.0)
+                .logarithmic(true)
+                .suffix(" pt/s"),
+        );
+    });
+
+    let end = lyrics
+        .lyrics
+        .lines
+        .iter()
+        .flat_map(|line| {
+            line.original
+                .iter()
+                .map(|segment| segment.end)
+                .chain([line.end])
+        })
+        .max()
+        .unwrap_or_else(Duration::zero)
+        .max(player_timestamp)
+        + Duration::seconds(5);
+    let points_per_second = state.points_per_second;
+    let width = end.num_milliseconds() as f32 / 1000.0 * points_per_second;
+    let height = AXIS_HEIGHT + ROW_HEIGHT * lyrics.lyrics.lines.len() as f32;
+
+    egui::ScrollArea::both()
+        .id_source("timeline_scroll_area")
+        .max_height(300.0)
+        .show(ui, |ui| {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(width, height), Sense::hover());
+            let painter = ui.painter_at(rect);
+            let x_of = |time: Duration| {
+                rect.left() + time.num_milliseconds() as f32 / 1000.0 * points_per_second
+            };
+
+            // Time axis, with a tick every second and a label every five.
+            for second in 0..=end.num_seconds() {
+                let x = x_of(Duration::seconds(second));
+                let labelled = second % 5 == 0;
+                painter.line_segment(
+                    [
+                        egui::pos2(
+                            x,
+                            rect.top() + if labelled { 0.0 } else { AXIS_HEIGHT / 2.0 },
+                        ),
+                        egui::pos2(x, rect.top() + AXIS_HEIGHT),
+                    ],
+                    Stroke::new(1.0, MfColors::GRAY_700),
+                );
+                if labelled {
+                    painter.text(
+                        egui::pos2(x + 2.0, rect.top()),
+                        Align2::LEFT_TOP,
+                        format!("{}:{:02}", second / 60, second % 60),
+                        FontId::proportional(10.0),
+                        MfColors::GRAY_500,
+                    );
+                }
+            }
+
+            for marker in &lyrics.markers {
+                let band =
+                    Rect::from_x_y_ranges(x_of(marker.begin)..=x_of(marker.end), rect.y_range());
+                painter.rect_filled(band, 0.0, MfColors::PURPLE_700.gamma_multiply(0.3));
+                painter.with_clip_rect(band.intersect(rect)).text(
+                    egui::pos2(band.left() + 2.0, rect.top() + AXIS_HEIGHT / 2.0),
+                    Align2::LEFT_TOP,
+                    &marker.label,
+                    FontId::proportional(10.0),
+                    MfColors::PURPLE_300,
+                );
+            }
+
+            for (line_index, line) in lyrics.lyrics.lines.iter_mut().enumerate() {
+                let top = rect.top() + AXIS_HEIGHT + ROW_HEIGHT * line_index as f32;
+                let line_rect = Rect::from_x_y_ranges(
+                    x_of(line.begin)..=x_of(line.end),
+                    top + 1.0..=top + ROW_HEIGHT - 1.0,
+                );
+                painter.rect_filled(line_rect, 2.0, MfColors::GRAY_900);
+
+                let mut dragged = false;
+                for (segment_index, segment) in line.original.iter_mut().enumerate() {
+                    let block = Rect::from_x_y_ranges(
+                        x_of(segment.begin)..=x_of(segment.end).max(x_of(segment.begin) + 2.0),
+                        top + 3.0..=top + ROW_HEIGHT - 3.0,
+                    );
+                    let id = ui.id().with(("timeline_segment", line.uuid, segment_index));
+                    let edge_width = EDGE_WIDTH.min(block.width() / 3.0);
+                    // Later interactions take precedence, so the edges go after the body.
+                    let parts = [
+                        (DragTarget::Whole, block),
+                        (
+                            DragTarget::Begin,
+                            Rect::from_x_y_ranges(
+                                block.left()..=block.left() + edge_width,
+                                block.y_range(),
+                            ),
+                        ),
+                        (
+                            DragTarget::End,
+                            Rect::from_x_y_ranges(
+                                block.right() - edge_width..=block.right(),
+                                block.y_range(),
+                            ),
+                        ),
+                    ];
+
+                    let mut hovered = false;
+                    for (part_index, (target, part_rect)) in parts.into_iter().enumerate() {
+                        let response = ui.interact(part_rect, id.with(part_index), Sense::drag());
+                        hovered |= response.hovered() || response.dragged();
+                        let cursor = match target {
+                            DragTarget::Whole => CursorIcon::Grab,
+                            _ => CursorIcon::ResizeHorizontal,
+                        };
+                        let response = response.on_hover_cursor(cursor);
+                        if !response.dragged() {
+                            continue;
+                        }
+                        let delta = Duration::milliseconds(
+                            (response.drag_delta().x / points_per_second * 1000.0) as i64,
+                        );
+                        if delta.is_zero() {
+                            continue;
+                        }
+                        match target {
+                            DragTarget::Begin => {
+                                segment.begin = (segment.begin + delta)
+                                    .max(Duration::zero())
+                                    .min(segment.end);
+                            }
+                            DragTarget::End => {
+                                segment.end = (segment.end + delta).max(segment.begin);
+                            }
+                            DragTarget::Whole => {
+                                let delta = delta.max(-segment.begin);
+                                segment.begin += delta;
+                                segment.end += delta;
+                            }
+                        }
+                        dragged = true;
+                        // Only one part of a block is dragged at a time.
+                        break;
+                    }
+
+                    let fill = if segment.needs_review {
+                        MfColors::YELLOW_700
+                    } else if segment.is_active_at(player_timestamp) {
+                        MfColors::ORANGE_500
+                    } else if hovered {
+                        MfColors::BLUE_500
+                    } else {
+                        MfColors::BLUE_700
+                    };
+                    painter.rect_filled(block, 2.0, fill);
+                    painter.with_clip_rect(block.intersect(rect)).text(
+                        block.left_center() + egui::vec2(3.0, 0.0),
+                        Align2::LEFT_CENTER,
+                        &segment.text,
+                        FontId::proportional(12.0),
+                        MfColors::WHITE,
+                    );
+                }
+                if dragged {
+                    fit_line_to_segments(line);
+                }
+            }
+
+            let playhead = x_of(player_timestamp);
+            painter.line_segment(
+                [
+                    egui::pos2(playhead, rect.top()),
+                    egui::pos2(playhead, rect.bottom()),
+                ],
+                Stroke::new(1.0, MfColors::RED_500),
+            );
+        });
+}
+
+/// Stretches a line so that it covers all its segments.
+fn fit_line_to_segments(line: &mut LyricsLine) {
+    if let Some(begin) = line.original.iter().map(|segment| segment.begin).min() {
+        line.begin = line.begin.min(begin);
+    }
+    if let Some(end) = line.original.iter().map(|segment| segment.end).max() {
+        line.end = line.end.max(end);
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::Duration;
+use eframe::egui::{self, Align2, FontId, Rect, Sense, Stroke};
+
+use crate::analysis::DecodedAudio;
+use crate::component::colors::MfColors;
+use crate::lyrics::Marker;
+
+/// Number of peak pairs kept for each second of audio, as nearly as the sample rate allows.
+const PEAKS_PER_SECOND: usize = 1000;
+
+const WAVEFORM_HEIGHT: f32 = 80.0;
+
+/// The outline of an audio file, as the lowest and highest sample of about each millisecond.
+pub struct Waveform {
+    peaks: Vec<(f32, f32)>,
+
+    /// Actual number of peak pairs per second, which is only exactly [`PEAKS_PER_SECOND`] for
+    /// sample rates that are a multiple of it.
+    peaks_per_second: f64,
+
+    duration: Duration,
+}
+
+impl Waveform {
+    pub fn new(audio: &DecodedAudio) -> Self {
+        let chunk = (audio.sample_rate as usize / PEAKS_PER_SECOND).max(1);
+        let peaks = audio
+            .samples
+            .chunks(chunk)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold((0f32, 0f32), |(low, high), x| (low.min(*x), high.max(*x)))
+            })
+            .collect();
+        Waveform {
+            peaks,
+            peaks_per_second: audio.sample_rate as f64 / chunk as f64,
+            duration: audio.duration(),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The lowest and highest sample between `begin` and `end`.
+    fn peak_between(&self, begin: Duration, end: Duration) -> Option<(f32, f32)> {
+        let index_of = |time: Duration| {
+            ((time.num_milliseconds().max(0) as f64 / 1000.0 * self.peaks_per_second) as usize)
+                .min(self.peaks.len())
+        };
+        let (begin, end) = (index_of(begin), index_of(end));
+        self.peaks[begin..end.max(begin + 1).min(self.peaks.len())]
+            .iter()
+            .copied()
+            .reduce(|(low, high), (x, y)| (low.min(x), high.max(y)))
+    }
+}
+
+/// View settings of the waveform.
+pub struct WaveformState {
+    /// Horizontal zoom, in points per second.
+    pub points_per_second: f32,
+
+    /// Where the selection being dragged started.
+    selection_anchor: Option<Duration>,
+}
+
+impl Default for WaveformState {
+    fn default() -> Self {
+        WaveformState {
+            points_per_second: 50.0,
+            selection_anchor: None,
+        }
+    }
+}
+
+/// What the user did on the waveform.
+#[derive(Default)]
+pub struct WaveformResponse {
+    /// A position clicked on, to seek to.
+    pub seek: Option<Duration>,
+
+    /// A `(begin, end)` span selected by dragging, in order.
+    pub selection: Option<(Duration, Duration)>,
+}
+
+/// Shows the waveform with the markers, the loop region and the playhead over it.
+///
+/// Clicking seeks and dragging selects a span. Scrolling with the zoom modifier held, or
+/// pinching, zooms the time axis.
+pub fn show_waveform(
+    ui: &mut egui::Ui,
+    waveform: &Waveform,
+    state: &mut WaveformState,
+    player_timestamp: Duration,
+    loop_region: (Option<Duration>, Option<Duration>),
+    markers: &[Marker],
+) -> WaveformResponse {
+    let mut waveform_response = WaveformResponse::default();
+    ui.horizontal(|ui| {
+        ui.label("Zoom");
+        ui.add(
+            egui::Slider::new(&mut state.points_per_second, 10.0..=2000.0)
+                .logarithmic(true)
+                .suffix(" pt/s"),
+        );
+    });
+
+    let points_per_second = state.points_per_second;
+    let width = waveform.duration().num_milliseconds() as f32 / 1000.0 * points_per_second;
+
+    egui::ScrollArea::horizontal()
+        .id_source("waveform_scroll_area")
+        .show(ui, |ui| {
+            let (rect, response) = ui.allocate_exact_size(
+                egui::vec2(width.max(1.0), WAVEFORM_HEIGHT),
+                Sense::click_and_drag(),
+            );
+            let painter = ui.painter_at(rect);
+            let x_of = |time: Duration| {
+                rect.left() + time.num_milliseconds() as f32 / 1000.0 * points_per_second
+            };
+            let time_at = |x: f32| {
+                Duration::milliseconds(
+                    ((x - rect.left()).max(0.0) / points_per_second * 1000.0) as i64,
+                )
+                .min(waveform.duration())
+            };
+
+            painter.rect_filled(rect, 2.0, MfColors::GRAY_900);
+
+            for marker in markers {
+                let band =
+                    Rect::from_x_y_ranges(x_of(marker.begin)..=x_of(marker.end), rect.y_range());
+                painter.rect_filled(band, 0.0, MfColors::PURPLE_700.gamma_multiply(0.3));
+                painter.with_clip_rect(band.intersect(rect)).text(
+                    band.left_top() + egui::vec2(2.0, 2.0),
+                    Align2::LEFT_TOP,
+                    &marker.label,
+                    FontId::proportional(10.0),
+                    MfColors::PURPLE_300,
+                );
+            }
+
+            match loop_region {
+                (Some(begin), Some(end)) => {
+                    painter.rect_filled(
+                        Rect::from_x_y_ranges(x_of(begin)..=x_of(end), rect.y_range()),
+                        0.0,
+                        MfColors::BLUE_700.gamma_multiply(0.5),
+                    );
+                }
+                (begin, end) => {
+                    for marker in begin.into_iter().chain(end) {
+                        painter.vline(
+                            x_of(marker),
+                            rect.y_range(),
+                            Stroke::new(1.0, MfColors::BLUE_300),
+                        );
+                    }
+                }
+            }
+
+            // Only the columns in view are drawn, one per point.
+            let visible = rect.intersect(ui.clip_rect());
+            let center = rect.center().y;
+            let mut x = visible.left().floor();
+            while x < visible.right() {
+                if let Some((low, high)) = waveform.peak_between(time_at(x), time_at(x + 1.0)) {
+                    painter.vline(
+                        x,
+                        (center - high * WAVEFORM_HEIGHT / 2.0)
+                            ..=(center - low * WAVEFORM_HEIGHT / 2.0),
+                        Stroke::new(1.0, MfColors::BLUE_400),
+                    );
+                }
+                x += 1.0;
+            }
+
+            painter.vline(
+                x_of(player_timestamp),
+                rect.y_range(),
+                Stroke::new(1.0, MfColors::RED_500),
+            );
+
+            if response.hovered() {
+                let zoom = ui.input(|i| i.zoom_delta());
+                if zoom != 1.0 {
+                    state.points_per_second = (points_per_second * zoom).clamp(10.0, 2000.0);
+                }
+            }
+
+            if response.drag_started() {
+                // Anchor at where the button went down, not where the drag was recognized.
+                state.selection_anchor = ui
+                    .input(|i| i.pointer.press_origin())
+                    .map(|pos| time_at(pos.x));
+            }
+            if response.dragged() {
+                if let (Some(anchor), Some(pos)) =
+                    (state.selection_anchor, response.interact_pointer_pos())
+                {
+                    let time = time_at(pos.x);
+                    waveform_response.selection = Some((anchor.min(time), anchor.max(time)));
+                }
+            }
+            if response.drag_stopped() {
+                state.selection_anchor = None;
+            }
+            if response.clicked() {
+                waveform_response.seek = response.interact_pointer_pos().map(|pos| time_at(pos.x));
+            }
+        });
+    waveform_response
+}
@@ -0,0 +1,378 @@
+//! Conversion between Babel lyrics and other lyrics and subtitle formats, independent of the
+//! player's interface.
+
+use std::path::Path;
+
+use amll_lyric::ttml;
+use amll_lyric::ttml::TTMLLyric;
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::export;
+use crate::lyrics::{
+    migrate_babel_lyrics, BabelLyrics, Lyrics, LyricsLine, LyricsMetadata, LyricsSegment,
+    SegmentStyle, TextDirection, BABEL_LYRICS_VERSION,
+};
+use crate::timestamp::parse_timestamp;
+
+/// Parses lyrics in the format given by the extension of `path`: TTML for `.ttml`, LRC for
+/// `.lrc`, Babel lyrics JSON otherwise.
+pub fn parse_lyrics_file(path: &Path, contents: &[u8]) -> anyhow::Result<BabelLyrics> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("ttml") => ttml_to_babel(std::str::from_utf8(contents)?),
+        Some("lrc") => lrc_to_babel(std::str::from_utf8(contents)?),
+        _ => json_to_babel(std::str::from_utf8(contents)?),
+    }
+}
+
+/// Converts AMLL TTML lyrics to Babel lyrics, with each word as a segment.
+pub fn ttml_to_babel(ttml: &str) -> anyhow::Result<BabelLyrics> {
+    let ttml_lyrics = ttml::parse_ttml(ttml.as_bytes()).map_err(|e| anyhow::anyhow!("{}", e))?;
+    Ok(from_ttml_lyric(ttml_lyrics))
+}
+
+/// Parses, migrates, repairs and validates Babel lyrics JSON, describing where the data went
+/// wrong on failure.
+pub fn json_to_babel(json: &str) -> anyhow::Result<BabelLyrics> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| anyhow::anyhow!("invalid JSON: {}", e))?;
+    migrate_babel_lyrics(&mut value)
+        .map_err(|e| anyhow::anyhow!("not a valid Babel lyrics file: {}", e))?;
+    let mut babel_lyrics: BabelLyrics = serde_json::from_value(value)
+        .map_err(|e| anyhow::anyhow!("not a valid Babel lyrics file: {}", e))?;
+    babel_lyrics.sanitize();
+    babel_lyrics
+        .validate()
+        .map_err(|e| anyhow::anyhow!("inconsistent lyrics data: {}", e))?;
+    Ok(babel_lyrics)
+}
+
+fn from_ttml_lyric(ttml_lyrics: TTMLLyric<'_>) -> BabelLyrics {
+    let lines = ttml_lyrics.lines;
+    let mut babel_lines = Vec::<LyricsLine>::new();
+    for line in lines {
+        let mut babel_segments = Vec::<LyricsSegment>::new();
+        for segment in line.words {
+            let babel_segment = LyricsSegment {
+                begin: Duration::milliseconds(segment.start_time as i64),
+                end: Duration::milliseconds(segment.end_time as i64),
+                text: segment.word.to_string(),
+                translations: Vec::new(),
+                needs_review: false,
+                romanization: String::new(),
+                ruby: String::new(),
+                style: SegmentStyle::default(),
+            };
+            babel_segments.push(babel_segment);
+        }
+        let babel_line = LyricsLine {
+            begin: Duration::milliseconds(line.start_time as i64),
+            end: Duration::milliseconds(line.end_time as i64),
+            agent_id: String::new(),
+            original: babel_segments,
+            translations: Vec::new(),
+            uuid: Uuid::new_v4(),
+            note: String::new(),
+            disabled: false,
+        };
+        babel_lines.push(babel_line);
+    }
+    babel_from_lines(babel_lines)
+}
+
+/// Babel lyrics with the given lines and nothing else.
+fn babel_from_lines(lines: Vec<LyricsLine>) -> BabelLyrics {
+    BabelLyrics {
+        version: BABEL_LYRICS_VERSION,
+        metadata: LyricsMetadata {
+            agents: Vec::new(),
+            translations: Vec::new(),
+        },
+        lyrics: Lyrics { lines },
+        markers: Vec::new(),
+        direction: TextDirection::default(),
+    }
+}
+
+/// How long the last line lasts in formats that only give the start of each line, such as LRC,
+/// in milliseconds.
+const LAST_LINE_LENGTH: i64 = 5000;
+
+/// Parses line-timed LRC lyrics, with each line as a single segment that lasts until the next
+/// line starts. Lines with several time tags are repeated at each of them, and lines without
+/// text only end the line before them.
+pub fn lrc_to_babel(lrc: &str) -> anyhow::Result<BabelLyrics> {
+    let mut timed_texts = Vec::<(Duration, &str)>::new();
+    for line in lrc.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some(tag_end) = rest.strip_prefix('[').and_then(|tag| tag.find(']')) {
+            // Tags that are not timestamps, such as `[ar:Artist]`, are metadata and skipped.
+            if let Ok(time) = parse_timestamp(&rest[1..tag_end + 1]) {
+                times.push(time);
+            }
+            rest = &rest[tag_end + 2..];
+        }
+        let text = rest.trim();
+        timed_texts.extend(times.into_iter().map(|time| (time, text)));
+    }
+    if timed_texts.is_empty() {
+        anyhow::bail!("no timed lines found");
+    }
+    timed_texts.sort_by_key(|(time, _)| *time);
+
+    let mut babel_lines = Vec::<LyricsLine>::new();
+    for (index, (begin, text)) in timed_texts.iter().enumerate() {
+        if text.is_empty() {
+            continue;
+        }
+        let end = timed_texts
+            .get(index + 1)
+            .map(|(next, _)| *next)
+            .unwrap_or(*begin + Duration::milliseconds(LAST_LINE_LENGTH));
+        babel_lines.push(LyricsLine {
+            begin: *begin,
+            end,
+            agent_id: String::new(),
+            original: vec![LyricsSegment {
+                begin: *begin,
+                end,
+                text: text.to_string(),
+                translations: Vec::new(),
+                needs_review: false,
+                romanization: String::new(),
+                ruby: String::new(),
+                style: SegmentStyle::default(),
+            }],
+            translations: Vec::new(),
+            uuid: Uuid::new_v4(),
+            note: String::new(),
+            disabled: false,
+        });
+    }
+    Ok(babel_from_lines(babel_lines))
+}
+
+/// Converts the entries of synchronized lyrics embedded in audio tags (an ID3v2 `SYLT` frame),
+/// each a text with its start, to Babel lyrics. Each entry lasts until the next one starts.
+///
+/// Entries are taken as lines, unless some of them start with a line break, in which case
+/// they are taken as words and the line breaks separate the lines.
+pub fn sylt_to_babel(entries: &[(Duration, String)]) -> anyhow::Result<BabelLyrics> {
+    let mut entries = entries.to_vec();
+    entries.sort_by_key(|(time, _)| *time);
+    let line_break = ['\n', '\r'];
+    let word_timed = entries.iter().any(|(_, text)| text.starts_with(line_break));
+
+    let mut lines = Vec::<Vec<LyricsSegment>>::new();
+    for (index, (begin, text)) in entries.iter().enumerate() {
+        if lines.is_empty() || !word_timed || text.starts_with(line_break) {
+            lines.push(Vec::new());
+        }
+        let text = text.trim_start_matches(line_break);
+        if text.trim().is_empty() {
+            continue;
+        }
+        let end = entries
+            .get(index + 1)
+            .map(|(next, _)| *next)
+            .unwrap_or(*begin + Duration::milliseconds(LAST_LINE_LENGTH));
+        lines.last_mut().unwrap().push(LyricsSegment {
+            begin: *begin,
+            end,
+            text: text.to_string(),
+            translations: Vec::new(),
+            needs_review: false,
+            romanization: String::new(),
+            ruby: String::new(),
+            style: SegmentStyle::default(),
+        });
+    }
+
+    let babel_lines: Vec<LyricsLine> = lines
+        .into_iter()
+        .filter(|segments| !segments.is_empty())
+        .map(|segments| LyricsLine {
+            begin: segments[0].begin,
+            end: segments[segments.len() - 1].end,
+            agent_id: String::new(),
+            original: segments,
+            translations: Vec::new(),
+            uuid: Uuid::new_v4(),
+            note: String::new(),
+            disabled: false,
+        })
+        .collect();
+    if babel_lines.is_empty() {
+        anyhow::bail!("no timed lines found");
+    }
+    Ok(babel_from_lines(babel_lines))
+}
+
+/// Serializes Babel lyrics to JSON, as saved by the editor.
+pub fn babel_to_json(lyrics: &BabelLyrics) -> anyhow::Result<String> {
+    Ok(serde_json::to_string(lyrics)?)
+}
+
+/// Converts Babel lyrics to AMLL TTML.
+pub fn babel_to_ttml(lyrics: &BabelLyrics) -> String {
+    export::ttml_to_string(&export::babel_to_ttml(lyrics))
+}
+
+/// Converts Babel lyrics to SubRip subtitles, one cue per line.
+pub fn babel_to_srt(lyrics: &BabelLyrics, include_translations: bool) -> String {
+    export::to_srt(lyrics, include_translations)
+}
+
+/// Converts Babel lyrics to a standalone HTML page that highlights the lines in time with an
+/// audio player.
+pub fn babel_to_html(lyrics: &BabelLyrics, title: &str, include_translations: bool) -> String {
+    export::to_html(lyrics, title, include_translations)
+}
+
+/// Converts Babel lyrics to WebVTT subtitles, one cue per line, optionally with a timestamp
+/// before each segment.
+pub fn babel_to_vtt(
+    lyrics: &BabelLyrics,
+    include_translations: bool,
+    word_timings: bool,
+) -> String {
+    export::to_vtt(lyrics, include_translations, word_timings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timings(lyrics: &BabelLyrics) -> Vec<Vec<(i64, i64, &str)>> {
+        lyrics
+            .lyrics
+            .lines
+            .iter()
+            .map(|line| {
+                line.original
+                    .iter()
+                    .map(|seg| {
+                        (
+                            seg.begin.num_milliseconds(),
+                            seg.end.num_milliseconds(),
+                            seg.text.as_str(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn entries(entries: &[(i64, &str)]) -> Vec<(Duration, String)> {
+        entries
+            .iter()
+            .map(|(time, text)| (Duration::milliseconds(*time), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn sylt_entries_as_lines() {
+        let lyrics = sylt_to_babel(&entries(&[(2_000, "Two"), (0, "One"), (1_000, " ")])).unwrap();
+        assert_eq!(
+            timings(&lyrics),
+            vec![
+                vec![(0, 1_000, "One")],
+                vec![(2_000, 2_000 + LAST_LINE_LENGTH, "Two")]
+            ]
+        );
+    }
+
+    #[test]
+    fn sylt_entries_as_words() {
+        let lyrics = sylt_to_babel(&entries(&[
+            (0, "Hello"),
+            (500, " world"),
+            (1_000, "\nNext"),
+            (1_500, " line"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            timings(&lyrics),
+            vec![
+                vec![(0, 500, "Hello"), (500, 1_000, " world")],
+                vec![
+                    (1_000, 1_500, "Next"),
+                    (1_500, 1_500 + LAST_LINE_LENGTH, " line")
+                ],
+            ]
+        );
+        assert_eq!(lyrics.lyrics.lines[1].begin, Duration::milliseconds(1_000));
+    }
+
+    #[test]
+    fn sylt_without_text_is_rejected() {
+        assert!(sylt_to_babel(&entries(&[(0, ""), (100, "\n")])).is_err());
+    }
+
+    #[test]
+    fn lrc_lines_with_metadata_and_repeated_time_tags() {
+        let lrc = "[ar:Artist]\n[ti:Title]\n[00:01.5]One\n[00:03.25][00:07.125] Two \n\
+                   [00:05.00]\n\nUntimed\n[00:09]Three";
+        let lyrics = lrc_to_babel(lrc).unwrap();
+        assert_eq!(
+            timings(&lyrics),
+            vec![
+                vec![(1_500, 3_250, "One")],
+                vec![(3_250, 5_000, "Two")],
+                vec![(7_125, 9_000, "Two")],
+                vec![(9_000, 9_000 + LAST_LINE_LENGTH, "Three")],
+            ]
+        );
+        for line in &lyrics.lyrics.lines {
+            assert_eq!(
+                (line.begin, line.end),
+                (line.original[0].begin, line.original[0].end)
+            );
+        }
+    }
+
+    #[test]
+    fn lrc_without_time_tags_is_rejected() {
+        assert!(lrc_to_babel("[ar:Artist]\nJust text").is_err());
+        assert!(lrc_to_babel("").is_err());
+    }
+
+    #[test]
+    fn json_is_migrated_sanitized_and_validated() {
+        let language = Uuid::new_v4();
+        // A version 0 file, whose segment refers to a translation that is not declared and to
+        // a word past the end of it.
+        let json = format!(
+            r#"{{"metadata":{{"agents":[],"translations":[]}},"lyrics":{{"lines":[
+                {{"begin":0,"end":500,"agent_id":"","uuid":"{}","translations":[],
+                  "original":[{{"begin":0,"end":500,"text":"Hi","translations":[["{}",[3]]]}}]}}
+            ]}}}}"#,
+            Uuid::new_v4(),
+            language
+        );
+        let lyrics = json_to_babel(&json).unwrap();
+        assert_eq!(lyrics.version, BABEL_LYRICS_VERSION);
+        assert_eq!(lyrics.metadata.translations.len(), 1);
+        assert_eq!(lyrics.metadata.translations[0].id, language);
+        let line = &lyrics.lyrics.lines[0];
+        assert_eq!(line.translations, vec![(language, Vec::new())]);
+        assert_eq!(line.original[0].translations, vec![(language, Vec::new())]);
+        assert_eq!(
+            json_to_babel(&babel_to_json(&lyrics).unwrap()).unwrap(),
+            lyrics
+        );
+    }
+
+    #[test]
+    fn json_errors_are_described() {
+        let error = |json: &str| json_to_babel(json).unwrap_err().to_string();
+        assert!(error("{").starts_with("invalid JSON"));
+        assert!(error("[]").starts_with("not a valid Babel lyrics file"));
+        assert!(error(r#"{"version":99}"#).contains("format version 99"));
+        assert!(error(r#"{"version":1,"metadata":{}}"#).starts_with("not a valid Babel"));
+    }
+}
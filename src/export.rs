@@ -0,0 +1,408 @@
+use std::borrow::Cow;
+
+use amll_lyric::ttml::TTMLLyric;
+use amll_lyric::{LyricLine, LyricWord};
+use chrono::Duration;
+
+use crate::lyrics::{BabelLyrics, LyricsLine, LyricsSegment, TextDirection};
+
+/// Converts the lyrics to SubRip (SRT) subtitles, one cue per line.
+///
+/// If `include_translations` is set, each translation of a line is emitted on its own row
+/// below the original text.
+pub fn to_srt(lyrics: &BabelLyrics, include_translations: bool) -> String {
+    let mut srt = String::new();
+    let mut index = 1;
+    for line in enabled_lines(lyrics) {
+        let text = line_text(line);
+        if text.trim().is_empty() {
+            continue;
+        }
+        srt.push_str(&format!(
+            "{}\n{} --> {}\n{}\n",
+            index,
+            format_srt_timestamp(line.begin),
+            format_srt_timestamp(line.end),
+            text
+        ));
+        if include_translations {
+            for translation in line_translation_texts(line) {
+                srt.push_str(&translation);
+                srt.push('\n');
+            }
+        }
+        srt.push('\n');
+        index += 1;
+    }
+    srt
+}
+
+/// Converts the lyrics to WebVTT subtitles, one cue per line.
+///
+/// If `include_translations` is set, each translation of a line is emitted on its own row
+/// below the original text. If `word_timings` is set, the beginning of every segment after the
+/// first is marked with an inline `<HH:MM:SS.mmm>` timestamp tag.
+pub fn to_vtt(lyrics: &BabelLyrics, include_translations: bool, word_timings: bool) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for line in enabled_lines(lyrics) {
+        if line_text(line).trim().is_empty() {
+            continue;
+        }
+        vtt.push_str(&format!(
+            "{} --> {}\n",
+            format_vtt_timestamp(line.begin),
+            format_vtt_timestamp(line.end)
+        ));
+        for (index, segment) in line.original.iter().enumerate() {
+            if word_timings && index != 0 {
+                vtt.push_str(&format!("<{}>", format_vtt_timestamp(segment.begin)));
+            }
+            vtt.push_str(&escape_vtt_text(&segment.text));
+        }
+        vtt.push('\n');
+        if include_translations {
+            for translation in line_translation_texts(line) {
+                vtt.push_str(&escape_vtt_text(&translation));
+                vtt.push('\n');
+            }
+        }
+        vtt.push('\n');
+    }
+    vtt
+}
+
+/// Styles of the exported HTML page. The line being sung and the segment being sung within it
+/// are marked with the `active` class by [`HTML_SCRIPT`].
+const HTML_STYLE: &str = r#"body {
+  font-family: system-ui, sans-serif;
+  background: #111;
+  color: #888;
+  max-width: 40em;
+  margin: 2em auto;
+  line-height: 1.6;
+}
+.line { margin: 0.8em 0; cursor: pointer; }
+.line.active { color: #eee; }
+.segment.active { color: #f97316; }
+.translation { display: block; font-size: 0.8em; color: #666; }
+.lyrics.vertical { writing-mode: vertical-rl; }
+"#;
+
+/// Highlights the line and segment being sung while an `<audio>` element plays, scrolls the
+/// line into view, and seeks to a line when it is clicked. Other players can be synced by
+/// calling `babelLyrics.sync(audio)` with an `HTMLMediaElement`.
+const HTML_SCRIPT: &str = r#"(function () {
+  const lines = Array.from(document.querySelectorAll(".line"));
+  const timed = Array.from(document.querySelectorAll("[data-begin]"));
+  function isActive(element, ms) {
+    return Number(element.dataset.begin) <= ms && ms < Number(element.dataset.end);
+  }
+  function sync(audio) {
+    function update() {
+      const ms = audio.currentTime * 1000;
+      for (const element of timed) {
+        const active = isActive(element, ms);
+        if (active && !element.classList.contains("active") && lines.includes(element)) {
+          element.scrollIntoView({ block: "center", behavior: "smooth" });
+        }
+        element.classList.toggle("active", active);
+      }
+      if (!audio.paused) {
+        requestAnimationFrame(update);
+      }
+    }
+    audio.addEventListener("play", update);
+    audio.addEventListener("seeked", update);
+    for (const line of lines) {
+      line.addEventListener("click", function () {
+        audio.currentTime = Number(line.dataset.begin) / 1000;
+      });
+    }
+    update();
+  }
+  window.babelLyrics = { sync: sync };
+  const audio = document.querySelector("audio");
+  if (audio) {
+    sync(audio);
+  }
+})();
+"#;
+
+/// Converts the lyrics to a standalone HTML page titled `title`, one paragraph per line.
+///
+/// Lines and segments carry their timing in milliseconds as `data-begin` and `data-end`
+/// attributes, and a small included script highlights them in time with an `<audio>` element
+/// added to the page. Segments keep their ruby readings and styles. If `include_translations`
+/// is set, the translations of a line follow its original text.
+pub fn to_html(lyrics: &BabelLyrics, title: &str, include_translations: bool) -> String {
+    let (class, dir) = match lyrics.direction {
+        TextDirection::Auto => ("lyrics", "auto"),
+        TextDirection::LeftToRight => ("lyrics", "ltr"),
+        TextDirection::RightToLeft => ("lyrics", "rtl"),
+        TextDirection::Vertical => ("lyrics vertical", "ltr"),
+    };
+    let mut body = String::new();
+    for line in enabled_lines(lyrics) {
+        if line_text(line).trim().is_empty() {
+            continue;
+        }
+        body.push_str(&format!(
+            r#"<p class="line" data-begin="{}" data-end="{}" dir="{}">"#,
+            line.begin.num_milliseconds(),
+            line.end.num_milliseconds(),
+            dir
+        ));
+        for segment in &line.original {
+            body.push_str(&html_segment(segment));
+        }
+        if include_translations {
+            for (id, words) in &line.translations {
+                let text = words.concat();
+                if text.trim().is_empty() {
+                    continue;
+                }
+                let language = lyrics
+                    .metadata
+                    .translations
+                    .iter()
+                    .find(|entry| entry.id == *id)
+                    .map_or("", |entry| entry.language.as_str());
+                body.push_str(&format!(
+                    r#"<span class="translation" lang="{}">{}</span>"#,
+                    escape_xml_text(language),
+                    escape_xml_text(&text)
+                ));
+            }
+        }
+        body.push_str("</p>\n");
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>\n{}</style>\n</head>\n<body>\n\
+         <!-- Add <audio controls src=\"...\"></audio> here to play along. -->\n\
+         <div class=\"{}\">\n{}</div>\n<script>\n{}</script>\n</body>\n</html>\n",
+        escape_xml_text(title),
+        HTML_STYLE,
+        class,
+        body,
+        HTML_SCRIPT
+    )
+}
+
+/// A segment as an HTML span with its timing and style, and its ruby reading if it has one.
+fn html_segment(segment: &LyricsSegment) -> String {
+    let mut style = Vec::new();
+    if segment.style.bold {
+        style.push("font-weight: bold".to_string());
+    }
+    if segment.style.italic {
+        style.push("font-style: italic".to_string());
+    }
+    if segment.style.size != 1.0 {
+        style.push(format!("font-size: {}em", segment.style.size));
+    }
+    if let Some([r, g, b]) = segment.style.color {
+        style.push(format!("color: #{:02x}{:02x}{:02x}", r, g, b));
+    }
+    let style = if style.is_empty() {
+        String::new()
+    } else {
+        format!(r#" style="{}""#, style.join("; "))
+    };
+    let text = if segment.ruby.is_empty() {
+        escape_xml_text(&segment.text)
+    } else {
+        format!(
+            "<ruby>{}<rt>{}</rt></ruby>",
+            escape_xml_text(&segment.text),
+            escape_xml_text(&segment.ruby)
+        )
+    };
+    format!(
+        r#"<span class="segment" data-begin="{}" data-end="{}"{}>{}</span>"#,
+        segment.begin.num_milliseconds(),
+        segment.end.num_milliseconds(),
+        style,
+        text
+    )
+}
+
+/// Converts the lyrics to the AMLL TTML model.
+///
+/// Each segment becomes a word. The first translation of a line becomes its translated lyric,
+/// and the romanizations of its segments are joined into its roman lyric. AMLL only knows two
+/// singers, so lines sung by an agent other than the first line's are marked as duet lines.
+pub fn babel_to_ttml(lyrics: &BabelLyrics) -> TTMLLyric<'_> {
+    let main_agent = enabled_lines(lyrics)
+        .next()
+        .map(|line| line.agent_id.as_str());
+    let lines = enabled_lines(lyrics)
+        .map(|line| LyricLine {
+            words: line
+                .original
+                .iter()
+                .map(|segment| LyricWord {
+                    start_time: segment.begin.num_milliseconds().max(0) as u64,
+                    end_time: segment.end.num_milliseconds().max(0) as u64,
+                    word: Cow::Borrowed(segment.text.as_str()),
+                })
+                .collect(),
+            translated_lyric: Cow::Owned(
+                line_translation_texts(line)
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+            ),
+            roman_lyric: Cow::Owned(
+                line.original
+                    .iter()
+                    .map(|segment| segment.romanization.as_str())
+                    .filter(|romanization| !romanization.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            is_duet: main_agent.is_some_and(|agent| agent != line.agent_id),
+            start_time: line.begin.num_milliseconds().max(0) as u64,
+            end_time: line.end.num_milliseconds().max(0) as u64,
+            ..Default::default()
+        })
+        .collect();
+    TTMLLyric {
+        lines,
+        ..Default::default()
+    }
+}
+
+/// Serializes lyrics in the AMLL TTML model to a TTML document.
+pub fn ttml_to_string(ttml: &TTMLLyric) -> String {
+    let mut body = String::new();
+    for (index, line) in ttml.lines.iter().enumerate() {
+        body.push_str(&format!(
+            r#"<p begin="{}" end="{}" ttm:agent="{}" itunes:key="L{}">"#,
+            format_ttml_timestamp(line.start_time),
+            format_ttml_timestamp(line.end_time),
+            if line.is_duet { "v2" } else { "v1" },
+            index + 1
+        ));
+        for word in &line.words {
+            if word.word.trim().is_empty() {
+                // Spaces between words are plain text in AMLL TTML.
+                body.push_str(&escape_xml_text(&word.word));
+            } else {
+                body.push_str(&format!(
+                    r#"<span begin="{}" end="{}">{}</span>"#,
+                    format_ttml_timestamp(word.start_time),
+                    format_ttml_timestamp(word.end_time),
+                    escape_xml_text(&word.word)
+                ));
+            }
+        }
+        if !line.translated_lyric.is_empty() {
+            body.push_str(&format!(
+                r#"<span ttm:role="x-translation">{}</span>"#,
+                escape_xml_text(&line.translated_lyric)
+            ));
+        }
+        if !line.roman_lyric.is_empty() {
+            body.push_str(&format!(
+                r#"<span ttm:role="x-roman">{}</span>"#,
+                escape_xml_text(&line.roman_lyric)
+            ));
+        }
+        body.push_str("</p>");
+    }
+
+    let end = ttml
+        .lines
+        .iter()
+        .map(|line| line.end_time)
+        .max()
+        .unwrap_or(0);
+    let mut agents = r#"<ttm:agent type="person" xml:id="v1"/>"#.to_string();
+    if ttml.lines.iter().any(|line| line.is_duet) {
+        agents.push_str(r#"<ttm:agent type="other" xml:id="v2"/>"#);
+    }
+    format!(
+        concat!(
+            r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:ttm="http://www.w3.org/ns/ttml#metadata" "#,
+            r#"xmlns:amll="http://www.example.com/ns/amll" "#,
+            r#"xmlns:itunes="http://music.apple.com/lyric-ttml-internal">"#,
+            r#"<head><metadata>{}</metadata></head>"#,
+            r#"<body dur="{}"><div begin="{}" end="{}">{}</div></body></tt>"#
+        ),
+        agents,
+        format_ttml_timestamp(end),
+        format_ttml_timestamp(ttml.lines.first().map_or(0, |line| line.start_time)),
+        format_ttml_timestamp(end),
+        body
+    )
+}
+
+/// The lines to export, leaving out the disabled ones.
+fn enabled_lines(lyrics: &BabelLyrics) -> impl Iterator<Item = &LyricsLine> {
+    lyrics.lyrics.lines.iter().filter(|line| !line.disabled)
+}
+
+/// The original text of a line, as the concatenation of its segments.
+fn line_text(line: &LyricsLine) -> String {
+    line.original
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .collect()
+}
+
+/// The non-empty translations of a line, each joined into a single string.
+fn line_translation_texts(line: &LyricsLine) -> Vec<String> {
+    line.translations
+        .iter()
+        .map(|(_, words)| words.concat())
+        .filter(|text| !text.trim().is_empty())
+        .collect()
+}
+
+/// Formats a timestamp as `HH:MM:SS,mmm`.
+fn format_srt_timestamp(timestamp: Duration) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        timestamp.num_hours(),
+        timestamp.num_minutes() % 60,
+        timestamp.num_seconds() % 60,
+        timestamp.num_milliseconds() % 1000
+    )
+}
+
+/// Formats a timestamp as `HH:MM:SS.mmm`.
+fn format_vtt_timestamp(timestamp: Duration) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        timestamp.num_hours(),
+        timestamp.num_minutes() % 60,
+        timestamp.num_seconds() % 60,
+        timestamp.num_milliseconds() % 1000
+    )
+}
+
+/// Escapes the characters that have a special meaning in WebVTT cue text.
+fn escape_vtt_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Formats a timestamp in milliseconds as `MM:SS.mmm`.
+fn format_ttml_timestamp(milliseconds: u64) -> String {
+    format!(
+        "{:02}:{:02}.{:03}",
+        milliseconds / 60_000,
+        (milliseconds / 1000) % 60,
+        milliseconds % 1000
+    )
+}
+
+/// Escapes the characters that have a special meaning in XML text and attributes.
+fn escape_xml_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,65 @@
+use eframe::egui::Key;
+use serde::{Deserialize, Serialize};
+
+/// Keys bound to the transport, line stepping, loop and tap timing shortcuts of the player.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Keymap {
+    pub play_pause: Key,
+    pub seek_backward: Key,
+    pub seek_forward: Key,
+    pub set_loop_start: Key,
+    pub set_loop_end: Key,
+    pub clear_loop: Key,
+    pub tap: Key,
+    pub previous_line: Key,
+    pub next_line: Key,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap {
+            play_pause: Key::Space,
+            seek_backward: Key::ArrowLeft,
+            seek_forward: Key::ArrowRight,
+            set_loop_start: Key::A,
+            set_loop_end: Key::B,
+            clear_loop: Key::C,
+            tap: Key::T,
+            previous_line: Key::PageUp,
+            next_line: Key::PageDown,
+        }
+    }
+}
+
+impl Keymap {
+    /// Every binding with a description of its action, in the order they are listed in the
+    /// settings.
+    pub fn bindings_mut(&mut self) -> [(&'static str, &mut Key); 9] {
+        [
+            ("Play / pause", &mut self.play_pause),
+            ("Seek backward", &mut self.seek_backward),
+            ("Seek forward", &mut self.seek_forward),
+            ("Previous line", &mut self.previous_line),
+            ("Next line", &mut self.next_line),
+            ("Set loop start", &mut self.set_loop_start),
+            ("Set loop end", &mut self.set_loop_end),
+            ("Clear loop", &mut self.clear_loop),
+            ("Tap", &mut self.tap),
+        ]
+    }
+
+    /// Binds `key` to the action at `index` of [`Self::bindings_mut`]. An action that was
+    /// bound to `key` before takes over the previous key of the remapped action, so that no
+    /// key triggers two actions.
+    pub fn rebind(&mut self, index: usize, key: Key) {
+        let mut bindings = self.bindings_mut();
+        let previous = *bindings[index].1;
+        for (other_index, (_, other_key)) in bindings.iter_mut().enumerate() {
+            if other_index != index && **other_key == key {
+                **other_key = previous;
+            }
+        }
+        *bindings[index].1 = key;
+    }
+}
@@ -1,7 +1,17 @@
+pub mod analysis;
 mod app;
+pub mod audio;
 pub use app::BabelPlayerApp;
 pub mod component;
+pub mod convert;
+pub mod export;
 pub mod icons;
 mod init;
+mod keymap;
 pub mod lyrics;
 pub mod lyrics_editor;
+pub mod metadata;
+mod player;
+pub mod project;
+mod tap_tempo;
+pub mod timestamp;
@@ -1,31 +1,33 @@
+use std::collections::{HashMap, HashSet};
+
 use chrono::Duration;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct TranslationEntry {
     pub language: String,
     pub id: Uuid,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Agent {
     pub id: String,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct LyricsMetadata {
     pub agents: Vec<Agent>,
     pub translations: Vec<TranslationEntry>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Lyrics {
     pub lines: Vec<LyricsLine>,
 }
 
 #[serde_with::serde_as]
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct LyricsSegment {
     #[serde_as(as = "serde_with::DurationMilliSeconds<i64>")]
     pub begin: Duration,
@@ -38,10 +40,62 @@ pub struct LyricsSegment {
     ///
     /// The word index list contains indices of the translated words in, which will be associated with the segment.
     pub translations: Vec<(Uuid, Vec<usize>)>,
+
+    /// Whether this segment has been flagged as uncertain and needs to be reviewed.
+    #[serde(default)]
+    pub needs_review: bool,
+
+    /// A phonetic reading of the segment, shown beneath it, e.g. romaji or pinyin.
+    #[serde(default)]
+    pub romanization: String,
+
+    /// A ruby reading of the segment, shown in small text above it, e.g. furigana.
+    #[serde(default)]
+    pub ruby: String,
+
+    /// Emphasis that sets the segment apart from the rest of its line.
+    #[serde(default)]
+    pub style: SegmentStyle,
+}
+
+/// How a segment is drawn in the lyrics and captions windows, e.g. bold for a shouted word or
+/// small for a whispered aside.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct SegmentStyle {
+    pub bold: bool,
+    pub italic: bool,
+
+    /// Size of the text relative to the rest of the line, within [`SEGMENT_SIZE_RANGE`].
+    pub size: f32,
+
+    /// Color of the text as `[r, g, b]` in place of the usual text color.
+    pub color: Option<[u8; 3]>,
+}
+
+impl Default for SegmentStyle {
+    fn default() -> Self {
+        SegmentStyle {
+            bold: false,
+            italic: false,
+            size: 1.0,
+            color: None,
+        }
+    }
+}
+
+/// Sizes a segment can be drawn at, relative to the rest of its line.
+pub const SEGMENT_SIZE_RANGE: std::ops::RangeInclusive<f32> = 0.5..=2.0;
+
+impl SegmentStyle {
+    /// Whether the segment is drawn like the rest of its line.
+    pub fn is_plain(&self) -> bool {
+        *self == SegmentStyle::default()
+    }
 }
 
 #[serde_with::serde_as]
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct LyricsLine {
     #[serde_as(as = "serde_with::DurationMilliSeconds<i64>")]
     pub begin: Duration,
@@ -54,10 +108,1276 @@ pub struct LyricsLine {
     /// A list of translations for this line of each language.
     /// The translation of each language is a pair `(language_id, word_list)`.
     pub translations: Vec<(Uuid, Vec<String>)>,
+
+    /// A free-form note for this line, only shown in the editor.
+    #[serde(default)]
+    pub note: String,
+
+    /// Whether this line is kept in the project but left out of exports, e.g. an ad-lib or
+    /// an instrumental marker.
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+impl LyricsSegment {
+    /// Whether the segment is being sung at `timestamp`, from its begin up to but not including
+    /// its end. Every window uses this to find the active segment, so that they agree.
+    pub fn is_active_at(&self, timestamp: Duration) -> bool {
+        self.begin <= timestamp && timestamp < self.end
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+impl LyricsLine {
+    /// Whether the line is active at `timestamp`, from its begin up to but not including its
+    /// end. Every window uses this to find the active line, so that they agree.
+    pub fn is_active_at(&self, timestamp: Duration) -> bool {
+        self.begin <= timestamp && timestamp < self.end
+    }
+
+    /// How far the words of the line are timed. Space segments are left out, as they are
+    /// zero-length between words.
+    pub fn timing(&self) -> LineTiming {
+        let words = self
+            .original
+            .iter()
+            .filter(|seg| !seg.text.trim().is_empty())
+            .collect::<Vec<_>>();
+        let timed = words.iter().filter(|seg| seg.end > seg.begin).count();
+        let ordered = words.windows(2).all(|pair| pair[0].begin <= pair[1].begin);
+        if timed == 0 {
+            LineTiming::Untimed
+        } else if timed == words.len() && ordered {
+            LineTiming::Timed
+        } else {
+            LineTiming::Partial
+        }
+    }
+
+    /// Sets the line to run from the earliest begin to the latest end of its segments. A line
+    /// without segments keeps its timing.
+    pub fn fit_to_segments(&mut self) {
+        let begin = self.original.iter().map(|segment| segment.begin).min();
+        let end = self.original.iter().map(|segment| segment.end).max();
+        if let (Some(begin), Some(end)) = (begin, end) {
+            self.begin = begin;
+            self.end = end;
+        }
+    }
+
+    /// Replaces the segments with the words of `text`, spread over the line from its begin to
+    /// its end with each word timed in proportion to its length, as a first pass to fine-tune.
+    /// Words are separated by zero-length space segments, and every segment gets an empty
+    /// entry for each translation of the line. Returns the replaced segments.
+    pub fn auto_time(&mut self, text: &str) -> Vec<LyricsSegment> {
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        let total_chars = words
+            .iter()
+            .map(|word| word.chars().count() as i64)
+            .sum::<i64>()
+            .max(1);
+        let span = (self.end - self.begin).num_milliseconds().max(0);
+        // Timestamps are taken from the running character count, so that rounding does not
+        // add up and the last word ends exactly at the end of the line.
+        let at = |chars: i64| self.begin + Duration::milliseconds(span * chars / total_chars);
+        let translations = self
+            .translations
+            .iter()
+            .map(|(id, _)| (*id, Vec::new()))
+            .collect::<Vec<_>>();
+        let segment = |begin, end, text: &str| LyricsSegment {
+            begin,
+            end,
+            text: text.to_string(),
+            translations: translations.clone(),
+            needs_review: false,
+            romanization: String::new(),
+            ruby: String::new(),
+            style: SegmentStyle::default(),
+        };
+
+        let mut segments = Vec::with_capacity(words.len() * 2);
+        let mut chars = 0;
+        for (index, word) in words.iter().enumerate() {
+            let begin = at(chars);
+            chars += word.chars().count() as i64;
+            let end = at(chars);
+            if index > 0 {
+                segments.push(segment(begin, begin, " "));
+            }
+            segments.push(segment(begin, end, word));
+        }
+        std::mem::replace(&mut self.original, segments)
+    }
+}
+
+/// A labelled span without lyrics, such as an intro, an interlude or a solo.
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Marker {
+    pub label: String,
+    #[serde_as(as = "serde_with::DurationMilliSeconds<i64>")]
+    pub begin: Duration,
+    #[serde_as(as = "serde_with::DurationMilliSeconds<i64>")]
+    pub end: Duration,
+}
+
+/// How many lines were added, removed or changed between two versions of some lyrics.
+#[derive(Default)]
+pub struct LinesDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl LinesDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.changed == 0
+    }
+}
+
+/// How far the words of a line are timed, from [`LyricsLine::timing`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineTiming {
+    /// Every word has a duration, and the words begin in order.
+    Timed,
+
+    /// Some words have a duration, or the words are out of order.
+    Partial,
+
+    /// No word has a duration, or the line has no words.
+    Untimed,
+}
+
+/// How a line of some lyrics compares to another version of them, from
+/// [`BabelLyrics::compare_lines`].
+pub enum LineComparison<'a> {
+    /// A line with the same text in both versions.
+    Same {
+        old: &'a LyricsLine,
+        new: &'a LyricsLine,
+        timing_changed: bool,
+    },
+
+    /// A line whose text was replaced in place.
+    Edited {
+        old: &'a LyricsLine,
+        new: &'a LyricsLine,
+    },
+    Removed(&'a LyricsLine),
+    Added(&'a LyricsLine),
+}
+
+/// Counts that show how far along the transcription and timing of some lyrics are.
+pub struct LyricsStats {
+    pub lines: usize,
+    pub segments: usize,
+
+    /// Segments that still last no time at all.
+    pub untimed_segments: usize,
+
+    /// How many lines are translated into each language, as `(language, lines)`.
+    pub translated_lines: Vec<(String, usize)>,
+
+    /// Total time covered by at least one timed segment.
+    pub timed: Duration,
+}
+
+/// The direction the segments of a line are read in.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum TextDirection {
+    /// Right to left for lines that start in a right-to-left script such as Arabic or Hebrew,
+    /// and left to right otherwise.
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+
+    /// One segment under the other, for vertical CJK text.
+    Vertical,
+}
+
+impl TextDirection {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextDirection::Auto => "Auto",
+            TextDirection::LeftToRight => "Left to right",
+            TextDirection::RightToLeft => "Right to left",
+            TextDirection::Vertical => "Vertical",
+        }
+    }
+}
+
+/// Version of the Babel lyrics format written by this build. Bump it along with a step in
+/// [`migrate_babel_lyrics`] whenever the format changes shape.
+pub const BABEL_LYRICS_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct BabelLyrics {
+    /// Version of the format, from [`BABEL_LYRICS_VERSION`]. Files from before versioning
+    /// have none and count as version 0.
+    #[serde(default)]
+    pub version: u32,
     pub metadata: LyricsMetadata,
     pub lyrics: Lyrics,
+
+    /// Instrumental spans shown alongside the lyrics.
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+
+    /// How lines are laid out in the lyrics and captions windows.
+    #[serde(default)]
+    pub direction: TextDirection,
+}
+
+impl BabelLyrics {
+    /// Returns the start of the first flagged segment after `timestamp`, wrapping around to the
+    /// first flagged segment of the lyrics if there is none after it.
+    pub fn next_flagged_segment(&self, timestamp: Duration) -> Option<Duration> {
+        let flagged = self
+            .lyrics
+            .lines
+            .iter()
+            .flat_map(|line| &line.original)
+            .filter(|segment| segment.needs_review)
+            .map(|segment| segment.begin);
+        flagged
+            .clone()
+            .filter(|begin| *begin > timestamp)
+            .min()
+            .or_else(|| flagged.min())
+    }
+
+    /// Reorders the lines by their begin, keeping lines that begin together in their current
+    /// order. Returns whether any line moved.
+    pub fn sort_lines(&mut self) -> bool {
+        let lines = &mut self.lyrics.lines;
+        if lines.windows(2).all(|pair| pair[0].begin <= pair[1].begin) {
+            return false;
+        }
+        lines.sort_by_key(|line| line.begin);
+        true
+    }
+
+    /// Inserts `line` after the last line that begins no later than it, keeping lines that are
+    /// in timestamp order in order, and returns its index.
+    pub fn insert_line_in_order(&mut self, line: LyricsLine) -> usize {
+        let index = self
+            .lyrics
+            .lines
+            .partition_point(|other| other.begin <= line.begin);
+        self.lyrics.lines.insert(index, line);
+        index
+    }
+
+    /// Returns the begin of the first line starting after `timestamp`.
+    pub fn next_line_begin(&self, timestamp: Duration) -> Option<Duration> {
+        self.lyrics
+            .lines
+            .iter()
+            .map(|line| line.begin)
+            .filter(|begin| *begin > timestamp)
+            .min()
+    }
+
+    /// Returns the begin of the last line starting before `timestamp` less `grace`, so that
+    /// stepping back just after a line has started goes to the line before it instead.
+    pub fn previous_line_begin(&self, timestamp: Duration, grace: Duration) -> Option<Duration> {
+        self.lyrics
+            .lines
+            .iter()
+            .map(|line| line.begin)
+            .filter(|begin| *begin < timestamp - grace)
+            .max()
+    }
+
+    /// Summarizes how the lines of `other` differ from these lyrics.
+    ///
+    /// Lines are matched by their text. A matched line whose timing differs counts as changed,
+    /// as does a line whose text was replaced in place.
+    pub fn diff_lines(&self, other: &BabelLyrics) -> LinesDiff {
+        let mut diff = LinesDiff::default();
+        for comparison in self.compare_lines(other) {
+            match comparison {
+                LineComparison::Same { timing_changed, .. } => {
+                    diff.changed += usize::from(timing_changed);
+                }
+                LineComparison::Edited { .. } => diff.changed += 1,
+                LineComparison::Removed(_) => diff.removed += 1,
+                LineComparison::Added(_) => diff.added += 1,
+            }
+        }
+        diff
+    }
+
+    /// Compares the lines of these lyrics with those of `other` one by one, in order.
+    ///
+    /// Lines are matched by their text. The unmatched lines between two matches are paired up
+    /// as edited in place, and the rest count as removed or added.
+    pub fn compare_lines<'a>(&'a self, other: &'a BabelLyrics) -> Vec<LineComparison<'a>> {
+        let old = &self.lyrics.lines;
+        let new = &other.lyrics.lines;
+        let mut comparisons = Vec::new();
+        let mut previous = (0, 0);
+        for (old_index, new_index) in align_lines(old, new)
+            .into_iter()
+            .chain([(old.len(), new.len())])
+        {
+            let removed = &old[previous.0..old_index];
+            let added = &new[previous.1..new_index];
+            for (old, new) in removed.iter().zip(added) {
+                comparisons.push(LineComparison::Edited { old, new });
+            }
+            comparisons.extend(
+                removed
+                    .iter()
+                    .skip(added.len())
+                    .map(LineComparison::Removed),
+            );
+            comparisons.extend(added.iter().skip(removed.len()).map(LineComparison::Added));
+            if old_index < old.len() {
+                let (old, new) = (&old[old_index], &new[new_index]);
+                comparisons.push(LineComparison::Same {
+                    old,
+                    new,
+                    timing_changed: !same_timing(old, new),
+                });
+            }
+            previous = (old_index + 1, new_index + 1);
+        }
+        comparisons
+    }
+
+    /// Merges the lines of `other` into these lyrics, keeping the current version of every line
+    /// whose text is unchanged so that its timing, translations and notes survive.
+    pub fn merge_lines(&mut self, mut other: BabelLyrics) {
+        let kept = align_lines(&self.lyrics.lines, &other.lyrics.lines);
+        for (old_index, new_index) in kept {
+            other.lyrics.lines[new_index] = self.lyrics.lines[old_index].clone();
+        }
+        for agent in other.metadata.agents {
+            if !self.metadata.agents.iter().any(|x| x.id == agent.id) {
+                self.metadata.agents.push(agent);
+            }
+        }
+        for entry in other.metadata.translations {
+            if !self.metadata.translations.iter().any(|x| x.id == entry.id) {
+                self.metadata.translations.push(entry);
+            }
+        }
+        self.lyrics.lines = other.lyrics.lines;
+        self.sanitize();
+    }
+
+    /// The direction `line` is laid out in, with [`TextDirection::Auto`] resolved from the first
+    /// letter of the line.
+    pub fn line_direction(&self, line: &LyricsLine) -> TextDirection {
+        if self.direction != TextDirection::Auto {
+            return self.direction;
+        }
+        let first_letter = line
+            .original
+            .iter()
+            .flat_map(|segment| segment.text.chars())
+            .find(|c| c.is_alphabetic());
+        if first_letter.is_some_and(is_right_to_left) {
+            TextDirection::RightToLeft
+        } else {
+            TextDirection::LeftToRight
+        }
+    }
+
+    /// The marker whose span contains `timestamp`, if any.
+    pub fn marker_at(&self, timestamp: Duration) -> Option<&Marker> {
+        self.markers
+            .iter()
+            .find(|marker| marker.begin <= timestamp && timestamp < marker.end)
+    }
+
+    pub fn stats(&self) -> LyricsStats {
+        let lines = &self.lyrics.lines;
+        let segments = || lines.iter().flat_map(|line| &line.original);
+        let translated_lines = self
+            .metadata
+            .translations
+            .iter()
+            .map(|entry| {
+                let count = lines
+                    .iter()
+                    .filter(|line| {
+                        line.translations
+                            .iter()
+                            .any(|(id, words)| *id == entry.id && !words.is_empty())
+                    })
+                    .count();
+                (entry.language.clone(), count)
+            })
+            .collect();
+
+        // Overlapping segments only count once.
+        let mut spans: Vec<(Duration, Duration)> = segments()
+            .filter(|segment| segment.end > segment.begin)
+            .map(|segment| (segment.begin, segment.end))
+            .collect();
+        spans.sort_by_key(|(begin, _)| *begin);
+        let mut timed = Duration::zero();
+        let mut covered_until = Duration::min_value();
+        for (begin, end) in spans {
+            if end > covered_until {
+                timed += end - begin.max(covered_until);
+                covered_until = end;
+            }
+        }
+
+        LyricsStats {
+            lines: lines.len(),
+            segments: segments().count(),
+            untimed_segments: segments()
+                .filter(|segment| segment.end <= segment.begin)
+                .count(),
+            translated_lines,
+            timed,
+        }
+    }
+
+    /// The time from the start of the first line to the end of the last, as `(begin, end)`.
+    pub fn span(&self) -> Option<(Duration, Duration)> {
+        let begin = self.lyrics.lines.iter().map(|line| line.begin).min()?;
+        let end = self.lyrics.lines.iter().map(|line| line.end).max()?;
+        Some((begin, end))
+    }
+
+    /// Stretches every timestamp away from `anchor` by `factor`, so that the lyrics match audio
+    /// played at a slightly different speed. Timestamps that would fall before zero are clamped.
+    pub fn scale_timings(&mut self, factor: f64, anchor: Duration) {
+        let scale = |time: &mut Duration| *time = scale_timestamp(*time, factor, anchor);
+        for line in &mut self.lyrics.lines {
+            scale(&mut line.begin);
+            scale(&mut line.end);
+            for segment in &mut line.original {
+                scale(&mut segment.begin);
+                scale(&mut segment.end);
+            }
+        }
+        for marker in &mut self.markers {
+            scale(&mut marker.begin);
+            scale(&mut marker.end);
+        }
+    }
+
+    /// Ends every segment where the next one of its line begins, and the last one at the end of
+    /// its line, for lyrics where only the start of each word was timed. Blank segments are left
+    /// alone and skipped over.
+    ///
+    /// Returns the previous ends of the segments by the uuid of their line, to undo this with
+    /// [`Self::restore_segment_ends`].
+    pub fn end_segments_at_next_start(&mut self) -> HashMap<Uuid, Vec<Duration>> {
+        let mut previous_ends = HashMap::new();
+        for line in &mut self.lyrics.lines {
+            previous_ends.insert(
+                line.uuid,
+                line.original.iter().map(|segment| segment.end).collect(),
+            );
+            let mut next_begin = line.end;
+            for segment in line.original.iter_mut().rev() {
+                if segment.text.trim().is_empty() {
+                    continue;
+                }
+                segment.end = next_begin.max(segment.begin);
+                next_begin = segment.begin;
+            }
+        }
+        previous_ends
+    }
+
+    /// Puts back the segment ends saved by [`Self::end_segments_at_next_start`], skipping lines
+    /// whose segments have been added or removed since.
+    pub fn restore_segment_ends(&mut self, previous_ends: &HashMap<Uuid, Vec<Duration>>) {
+        for line in &mut self.lyrics.lines {
+            let Some(ends) = previous_ends.get(&line.uuid) else {
+                continue;
+            };
+            if ends.len() != line.original.len() {
+                continue;
+            }
+            for (segment, end) in line.original.iter_mut().zip(ends) {
+                segment.end = *end;
+            }
+        }
+    }
+
+    /// Returns a copy of these lyrics with only the lines whose uuid is in `uuids`, and only the
+    /// agents and translation languages those lines use.
+    pub fn subset(&self, uuids: &HashSet<Uuid>) -> BabelLyrics {
+        let mut lines: Vec<LyricsLine> = self
+            .lyrics
+            .lines
+            .iter()
+            .filter(|line| uuids.contains(&line.uuid))
+            .cloned()
+            .collect();
+
+        let used_translations: HashSet<Uuid> = lines
+            .iter()
+            .flat_map(|line| &line.translations)
+            .filter(|(_, words)| !words.is_empty())
+            .map(|(id, _)| *id)
+            .collect();
+        for line in &mut lines {
+            line.translations
+                .retain(|(id, _)| used_translations.contains(id));
+            for segment in &mut line.original {
+                segment
+                    .translations
+                    .retain(|(id, _)| used_translations.contains(id));
+            }
+        }
+        let used_agents: HashSet<&str> = lines.iter().map(|line| line.agent_id.as_str()).collect();
+
+        BabelLyrics {
+            version: BABEL_LYRICS_VERSION,
+            metadata: LyricsMetadata {
+                agents: self
+                    .metadata
+                    .agents
+                    .iter()
+                    .filter(|agent| used_agents.contains(agent.id.as_str()))
+                    .cloned()
+                    .collect(),
+                translations: self
+                    .metadata
+                    .translations
+                    .iter()
+                    .filter(|entry| used_translations.contains(&entry.id))
+                    .cloned()
+                    .collect(),
+            },
+            lyrics: Lyrics { lines },
+            markers: self.markers.clone(),
+            direction: self.direction,
+        }
+    }
+
+    /// Repairs translation references so that the lyrics are safe to edit.
+    ///
+    /// Translation ids referenced by lines or segments but missing from `metadata.translations`
+    /// get a placeholder entry, every line and segment gets an entry for each declared
+    /// translation, and word indices pointing past the end of a translation are dropped.
+    /// Segment sizes are clamped to [`SEGMENT_SIZE_RANGE`].
+    pub fn sanitize(&mut self) {
+        let mut declared = HashSet::new();
+        self.metadata
+            .translations
+            .retain(|entry| declared.insert(entry.id));
+
+        for line in &self.lyrics.lines {
+            let referenced = line.translations.iter().map(|(id, _)| *id).chain(
+                line.original
+                    .iter()
+                    .flat_map(|segment| segment.translations.iter().map(|(id, _)| *id)),
+            );
+            for id in referenced {
+                if declared.insert(id) {
+                    self.metadata.translations.push(TranslationEntry {
+                        language: format!("Unknown ({})", id),
+                        id,
+                    });
+                }
+            }
+        }
+
+        for line in &mut self.lyrics.lines {
+            for entry in &self.metadata.translations {
+                if !line.translations.iter().any(|(id, _)| *id == entry.id) {
+                    line.translations.push((entry.id, Vec::new()));
+                }
+            }
+            for segment in &mut line.original {
+                segment.style.size = if segment.style.size.is_finite() {
+                    segment
+                        .style
+                        .size
+                        .clamp(*SEGMENT_SIZE_RANGE.start(), *SEGMENT_SIZE_RANGE.end())
+                } else {
+                    SegmentStyle::default().size
+                };
+                for (id, words) in &line.translations {
+                    match segment.translations.iter_mut().find(|(x, _)| x == id) {
+                        Some((_, word_indices)) => word_indices.retain(|x| *x < words.len()),
+                        None => segment.translations.push((*id, Vec::new())),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the lyrics for inconsistencies that deserialization alone cannot catch,
+    /// such as translation ids that are not declared in the metadata.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut declared = HashSet::new();
+        for entry in &self.metadata.translations {
+            if !declared.insert(entry.id) {
+                anyhow::bail!(
+                    "metadata.translations: translation id {} is declared more than once",
+                    entry.id
+                );
+            }
+        }
+
+        for (line_index, line) in self.lyrics.lines.iter().enumerate() {
+            let line_text: String = line.original.iter().map(|seg| seg.text.as_str()).collect();
+            let location = format!("lyrics.lines[{}] (\"{}\")", line_index, line_text);
+
+            for (id, _) in &line.translations {
+                if !declared.contains(id) {
+                    anyhow::bail!(
+                        "{}: translation id {} is not declared in metadata.translations",
+                        location,
+                        id
+                    );
+                }
+            }
+
+            for (segment_index, segment) in line.original.iter().enumerate() {
+                if !SEGMENT_SIZE_RANGE.contains(&segment.style.size) {
+                    anyhow::bail!(
+                        "{}.original[{}] (\"{}\"): size {} is outside {}..={}",
+                        location,
+                        segment_index,
+                        segment.text,
+                        segment.style.size,
+                        SEGMENT_SIZE_RANGE.start(),
+                        SEGMENT_SIZE_RANGE.end()
+                    );
+                }
+                for (id, word_indices) in &segment.translations {
+                    if !declared.contains(id) {
+                        anyhow::bail!(
+                            "{}.original[{}] (\"{}\"): translation id {} is not declared in metadata.translations",
+                            location,
+                            segment_index,
+                            segment.text,
+                            id
+                        );
+                    }
+                    let word_count = line
+                        .translations
+                        .iter()
+                        .find(|(line_id, _)| line_id == id)
+                        .map(|(_, words)| words.len())
+                        .unwrap_or(0);
+                    if let Some(word_index) = word_indices.iter().find(|x| **x >= word_count) {
+                        anyhow::bail!(
+                            "{}.original[{}] (\"{}\"): translation word index {} is out of range for translation {} with {} words",
+                            location,
+                            segment_index,
+                            segment.text,
+                            word_index,
+                            id,
+                            word_count
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn line_text(line: &LyricsLine) -> String {
+    line.original.iter().map(|seg| seg.text.as_str()).collect()
+}
+
+/// Whether `c` belongs to a script written from right to left: Hebrew, Arabic, Syriac, Thaana,
+/// N'Ko and their presentation forms.
+fn is_right_to_left(c: char) -> bool {
+    matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}')
+}
+
+fn same_timing(a: &LyricsLine, b: &LyricsLine) -> bool {
+    a.begin == b.begin
+        && a.end == b.end
+        && a.original.len() == b.original.len()
+        && a.original
+            .iter()
+            .zip(&b.original)
+            .all(|(x, y)| x.begin == y.begin && x.end == y.end)
+}
+
+/// Pairs up the lines of `old` and `new` that have the same text, as `(old_index, new_index)`
+/// in order, keeping as many pairs as possible (a longest common subsequence).
+fn align_lines(old: &[LyricsLine], new: &[LyricsLine]) -> Vec<(usize, usize)> {
+    let old_text: Vec<String> = old.iter().map(line_text).collect();
+    let new_text: Vec<String> = new.iter().map(line_text).collect();
+    align(&old_text, &new_text)
+}
+
+/// Pairs up the segments of `old` and `new` that have the same text, like lines are paired up
+/// by [`BabelLyrics::compare_lines`], as `(old_index, new_index)` in order.
+pub fn align_segments(old: &LyricsLine, new: &LyricsLine) -> Vec<(usize, usize)> {
+    let old_text: Vec<&str> = old.original.iter().map(|seg| seg.text.as_str()).collect();
+    let new_text: Vec<&str> = new.original.iter().map(|seg| seg.text.as_str()).collect();
+    align(&old_text, &new_text)
+}
+
+/// Pairs up the equal items of `old` and `new` as `(old_index, new_index)` in order, keeping
+/// as many pairs as possible (a longest common subsequence).
+fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    // lengths[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Upgrades Babel lyrics JSON of any older version to the shape of [`BABEL_LYRICS_VERSION`],
+/// one version at a time.
+pub fn migrate_babel_lyrics(value: &mut serde_json::Value) -> anyhow::Result<()> {
+    let Some(object) = value.as_object_mut() else {
+        anyhow::bail!("expected an object at the top level");
+    };
+    let mut version = match object.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow::anyhow!("version: expected a number, found {}", version))?,
+    };
+    if version > BABEL_LYRICS_VERSION {
+        anyhow::bail!(
+            "the file is format version {}, but this version of Babel Player only reads up to {}",
+            version,
+            BABEL_LYRICS_VERSION
+        );
+    }
+
+    while version < BABEL_LYRICS_VERSION {
+        match version {
+            0 => migrate_from_version_0(object),
+            _ => unreachable!("no migration from version {}", version),
+        }
+        version += 1;
+    }
+    object.insert("version".to_string(), version.into());
+    Ok(())
+}
+
+/// Version 0 lines have no `note`, and their segments no `needs_review` or `romanization`.
+fn migrate_from_version_0(object: &mut serde_json::Map<String, serde_json::Value>) {
+    let lines = object
+        .get_mut("lyrics")
+        .and_then(|lyrics| lyrics.get_mut("lines"))
+        .and_then(|lines| lines.as_array_mut());
+    for line in lines.into_iter().flatten() {
+        let Some(line) = line.as_object_mut() else {
+            continue;
+        };
+        line.entry("note").or_insert_with(|| "".into());
+        let segments = line
+            .get_mut("original")
+            .and_then(|segments| segments.as_array_mut());
+        for segment in segments.into_iter().flatten() {
+            if let Some(segment) = segment.as_object_mut() {
+                segment.entry("needs_review").or_insert(false.into());
+                segment.entry("romanization").or_insert_with(|| "".into());
+            }
+        }
+    }
+}
+
+/// Moves `time` away from `anchor` by `factor`, as [`BabelLyrics::scale_timings`] does.
+/// Timestamps that would fall before zero or beyond the largest [`Duration`] are clamped.
+pub fn scale_timestamp(time: Duration, factor: f64, anchor: Duration) -> Duration {
+    let offset = ((time - anchor).num_milliseconds() as f64 * factor).round();
+    let offset = Duration::try_milliseconds(offset as i64).unwrap_or(if offset < 0.0 {
+        Duration::min_value()
+    } else {
+        Duration::max_value()
+    });
+    anchor
+        .checked_add(&offset)
+        .unwrap_or(Duration::max_value())
+        .max(Duration::zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(begin: i64, end: i64, text: &str) -> LyricsSegment {
+        LyricsSegment {
+            begin: Duration::milliseconds(begin),
+            end: Duration::milliseconds(end),
+            text: text.to_string(),
+            translations: Vec::new(),
+            needs_review: false,
+            romanization: String::new(),
+            ruby: String::new(),
+            style: SegmentStyle::default(),
+        }
+    }
+
+    fn line(begin: i64, end: i64, original: Vec<LyricsSegment>) -> LyricsLine {
+        LyricsLine {
+            begin: Duration::milliseconds(begin),
+            end: Duration::milliseconds(end),
+            agent_id: String::new(),
+            original,
+            uuid: Uuid::new_v4(),
+            translations: Vec::new(),
+            note: String::new(),
+            disabled: false,
+        }
+    }
+
+    fn lyrics(lines: Vec<LyricsLine>) -> BabelLyrics {
+        BabelLyrics {
+            version: BABEL_LYRICS_VERSION,
+            metadata: LyricsMetadata {
+                agents: Vec::new(),
+                translations: Vec::new(),
+            },
+            lyrics: Lyrics { lines },
+            markers: Vec::new(),
+            direction: TextDirection::default(),
+        }
+    }
+
+    fn round_trip<T>(value: &T) -> T
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn segment_round_trip() {
+        let language = Uuid::new_v4();
+        let mut segment = segment(12_345, 13_000, "Hello");
+        segment.translations = vec![(language, vec![0, 2]), (Uuid::new_v4(), Vec::new())];
+        segment.needs_review = true;
+        segment.romanization = "ha-ro-o".to_string();
+        segment.ruby = "はろう".to_string();
+        segment.style = SegmentStyle {
+            bold: true,
+            italic: true,
+            size: 1.5,
+            color: Some([255, 64, 0]),
+        };
+        assert_eq!(round_trip(&segment), segment);
+    }
+
+    #[test]
+    fn segment_durations_are_whole_milliseconds() {
+        let segment = segment(7, 999, "a");
+        let json: serde_json::Value = serde_json::to_value(&segment).unwrap();
+        assert_eq!(json["begin"], 7);
+        assert_eq!(json["end"], 999);
+        assert_eq!(round_trip(&segment), segment);
+    }
+
+    #[test]
+    fn space_segment_is_kept() {
+        let line = line(
+            1_000,
+            2_500,
+            vec![
+                segment(1_000, 1_400, "Hello"),
+                segment(1_400, 1_400, " "),
+                segment(1_400, 2_500, "world"),
+            ],
+        );
+        let parsed = round_trip(&line);
+        assert_eq!(parsed.original[1].text, " ");
+        assert_eq!(parsed, line);
+    }
+
+    #[test]
+    fn line_round_trip_with_translations() {
+        let language = Uuid::new_v4();
+        let mut line = line(250, 900, vec![segment(250, 900, "Hi")]);
+        line.translations = vec![
+            (language, vec!["Salut".to_string()]),
+            (Uuid::new_v4(), Vec::new()),
+        ];
+        line.note = "check the ad-lib".to_string();
+        line.disabled = true;
+        assert_eq!(round_trip(&line), line);
+    }
+
+    #[test]
+    fn line_round_trip_without_translations() {
+        let line = line(0, 0, Vec::new());
+        assert_eq!(round_trip(&line), line);
+    }
+
+    #[test]
+    fn line_defaults_missing_optional_fields() {
+        let uuid = Uuid::new_v4();
+        let json = format!(
+            r#"{{"begin":100,"end":600,"agent_id":"v1","uuid":"{}","translations":[],
+                "original":[{{"begin":100,"end":600,"text":" ","translations":[]}}]}}"#,
+            uuid
+        );
+        let parsed: LyricsLine = serde_json::from_str(&json).unwrap();
+        let mut expected = line(100, 600, vec![segment(100, 600, " ")]);
+        expected.agent_id = "v1".to_string();
+        expected.uuid = uuid;
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn babel_lyrics_round_trip() {
+        let language = Uuid::new_v4();
+        let lyrics = BabelLyrics {
+            version: BABEL_LYRICS_VERSION,
+            metadata: LyricsMetadata {
+                agents: vec![Agent {
+                    id: "v1".to_string(),
+                }],
+                translations: vec![TranslationEntry {
+                    language: "fr".to_string(),
+                    id: language,
+                }],
+            },
+            lyrics: Lyrics {
+                lines: vec![line(0, 500, vec![segment(0, 500, "Oh")])],
+            },
+            markers: vec![Marker {
+                label: "Intro".to_string(),
+                begin: Duration::zero(),
+                end: Duration::milliseconds(450),
+            }],
+            direction: TextDirection::RightToLeft,
+        };
+        assert_eq!(round_trip(&lyrics), lyrics);
+    }
+
+    #[test]
+    fn auto_time_spreads_words_by_length() {
+        let language = Uuid::new_v4();
+        let mut line = line(1_000, 2_000, vec![segment(1_000, 2_000, "old")]);
+        line.translations = vec![(language, vec!["vieux".to_string()])];
+        let previous = line.auto_time("a  bbb\tcc");
+
+        assert_eq!(previous, vec![segment(1_000, 2_000, "old")]);
+        let timings = line
+            .original
+            .iter()
+            .map(|seg| {
+                (
+                    seg.begin.num_milliseconds(),
+                    seg.end.num_milliseconds(),
+                    seg.text.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            timings,
+            vec![
+                (1_000, 1_166, "a"),
+                (1_166, 1_166, " "),
+                (1_166, 1_666, "bbb"),
+                (1_666, 1_666, " "),
+                (1_666, 2_000, "cc"),
+            ]
+        );
+        assert_eq!(line.original.last().unwrap().end, line.end);
+        assert!(line
+            .original
+            .iter()
+            .all(|seg| seg.translations == vec![(language, Vec::new())]));
+    }
+
+    #[test]
+    fn segment_size_is_clamped_and_validated() {
+        let sizes = [0.0, -1.0, 1.5, 1000.0, f32::NAN];
+        let mut lyrics = lyrics(
+            sizes
+                .iter()
+                .map(|size| {
+                    let mut segment = segment(0, 100, "word");
+                    segment.style.size = *size;
+                    line(0, 100, vec![segment])
+                })
+                .collect(),
+        );
+        assert!(lyrics.validate().is_err());
+
+        lyrics.sanitize();
+        let sanitized = lyrics
+            .lyrics
+            .lines
+            .iter()
+            .map(|line| line.original[0].style.size)
+            .collect::<Vec<_>>();
+        assert_eq!(sanitized, vec![0.5, 0.5, 1.5, 2.0, 1.0]);
+        assert!(lyrics.validate().is_ok());
+    }
+
+    #[test]
+    fn migrates_version_0() {
+        let mut value = serde_json::json!({
+            "metadata": {"agents": [], "translations": []},
+            "lyrics": {"lines": [{
+                "begin": 0, "end": 500, "agent_id": "", "uuid": Uuid::new_v4(),
+                "translations": [],
+                "original": [{"begin": 0, "end": 500, "text": "Oh", "translations": []}]
+            }]}
+        });
+        migrate_babel_lyrics(&mut value).unwrap();
+        assert_eq!(value["version"], BABEL_LYRICS_VERSION);
+        let line = &value["lyrics"]["lines"][0];
+        assert_eq!(line["note"], "");
+        assert_eq!(line["original"][0]["needs_review"], false);
+        assert_eq!(line["original"][0]["romanization"], "");
+        let lyrics: BabelLyrics = serde_json::from_value(value).unwrap();
+        assert_eq!(lyrics.lyrics.lines[0].original[0].text, "Oh");
+    }
+
+    #[test]
+    fn migration_keeps_existing_fields() {
+        let mut value = serde_json::json!({
+            "lyrics": {"lines": [{
+                "note": "kept",
+                "original": [{"needs_review": true, "romanization": "o"}]
+            }]}
+        });
+        migrate_babel_lyrics(&mut value).unwrap();
+        let line = &value["lyrics"]["lines"][0];
+        assert_eq!(line["note"], "kept");
+        assert_eq!(line["original"][0]["needs_review"], true);
+        assert_eq!(line["original"][0]["romanization"], "o");
+
+        let mut current = serde_json::json!({"version": BABEL_LYRICS_VERSION});
+        migrate_babel_lyrics(&mut current).unwrap();
+        assert_eq!(
+            current,
+            serde_json::json!({"version": BABEL_LYRICS_VERSION})
+        );
+    }
+
+    #[test]
+    fn migration_rejects_unknown_versions() {
+        for value in [
+            serde_json::json!({"version": BABEL_LYRICS_VERSION + 1}),
+            serde_json::json!({"version": "1"}),
+            serde_json::json!({"version": -1}),
+            serde_json::json!([]),
+        ] {
+            let mut migrated = value.clone();
+            assert!(migrate_babel_lyrics(&mut migrated).is_err(), "{}", value);
+        }
+    }
+
+    fn text_lines(lines: &[(i64, &str)]) -> BabelLyrics {
+        lyrics(
+            lines
+                .iter()
+                .map(|(begin, text)| {
+                    line(
+                        *begin,
+                        begin + 1_000,
+                        vec![segment(*begin, begin + 1_000, text)],
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn align_keeps_longest_common_subsequence() {
+        let cases = [
+            ("abc", "abc", vec![(0, 0), (1, 1), (2, 2)]),
+            ("ac", "abc", vec![(0, 0), (1, 2)]),
+            ("abc", "ac", vec![(0, 0), (2, 1)]),
+            ("abc", "axc", vec![(0, 0), (2, 2)]),
+            ("abcd", "bdac", vec![(1, 0), (3, 1)]),
+            ("", "abc", vec![]),
+        ];
+        for (old, new, pairs) in cases {
+            let old = old.chars().collect::<Vec<_>>();
+            let new = new.chars().collect::<Vec<_>>();
+            assert_eq!(align(&old, &new), pairs, "{:?} -> {:?}", old, new);
+        }
+    }
+
+    #[test]
+    fn diff_lines_counts_changes() {
+        let old = text_lines(&[(0, "one"), (1_000, "two"), (2_000, "three")]);
+        // (new lines, added, removed, changed)
+        let cases = [
+            (vec![(0, "one"), (1_000, "two"), (2_000, "three")], 0, 0, 0),
+            (
+                vec![(0, "one"), (500, "new"), (1_000, "two"), (2_000, "three")],
+                1,
+                0,
+                0,
+            ),
+            (vec![(0, "one"), (2_000, "three")], 0, 1, 0),
+            (vec![(0, "one"), (1_000, "TWO"), (2_000, "three")], 0, 0, 1),
+            (vec![(0, "one"), (1_200, "two"), (2_000, "three")], 0, 0, 1),
+            (vec![(0, "uno"), (1_000, "dos")], 0, 1, 2),
+        ];
+        for (new, added, removed, changed) in cases {
+            let diff = old.diff_lines(&text_lines(&new));
+            assert_eq!(
+                (diff.added, diff.removed, diff.changed),
+                (added, removed, changed),
+                "{:?}",
+                new
+            );
+            assert_eq!(diff.is_empty(), added + removed + changed == 0);
+        }
+    }
+
+    #[test]
+    fn compare_lines_pairs_edits_in_place() {
+        let old = text_lines(&[(0, "one"), (1_000, "two"), (2_000, "three")]);
+        let new = text_lines(&[
+            (0, "one"),
+            (1_000, "too"),
+            (2_500, "three"),
+            (3_000, "four"),
+        ]);
+        let kinds = old
+            .compare_lines(&new)
+            .iter()
+            .map(|comparison| match comparison {
+                LineComparison::Same { timing_changed, .. } => {
+                    if *timing_changed {
+                        "retimed"
+                    } else {
+                        "same"
+                    }
+                }
+                LineComparison::Edited { .. } => "edited",
+                LineComparison::Removed(_) => "removed",
+                LineComparison::Added(_) => "added",
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(kinds, vec!["same", "edited", "retimed", "added"]);
+    }
+
+    #[test]
+    fn merge_lines_keeps_unchanged_lines() {
+        let mut old = text_lines(&[(0, "one"), (1_000, "two"), (2_000, "three")]);
+        old.lyrics.lines[0].note = "kept".to_string();
+        let kept_uuid = old.lyrics.lines[2].uuid;
+        let new = text_lines(&[(100, "one"), (1_500, "new"), (2_000, "three")]);
+        let added_uuid = new.lyrics.lines[1].uuid;
+
+        old.merge_lines(new);
+        let lines = &old.lyrics.lines;
+        assert_eq!(
+            lines.iter().map(line_text).collect::<Vec<_>>(),
+            vec!["one", "new", "three"]
+        );
+        // Lines with the same text keep their timing and everything else from before.
+        assert_eq!(lines[0].begin, Duration::zero());
+        assert_eq!(lines[0].note, "kept");
+        assert_eq!(lines[1].uuid, added_uuid);
+        assert_eq!(lines[2].uuid, kept_uuid);
+    }
+
+    fn segment_ends(line: &LyricsLine) -> Vec<i64> {
+        line.original
+            .iter()
+            .map(|seg| seg.end.num_milliseconds())
+            .collect()
+    }
+
+    #[test]
+    fn end_segments_at_next_start_skips_blank_segments() {
+        let mut lyrics = lyrics(vec![line(
+            0,
+            2_000,
+            vec![
+                segment(0, 0, "Hel"),
+                segment(400, 400, "lo"),
+                segment(400, 400, " "),
+                segment(1_000, 1_000, "world"),
+            ],
+        )]);
+        lyrics.end_segments_at_next_start();
+        // The space keeps its end, and "lo" ends where "world" begins.
+        assert_eq!(
+            segment_ends(&lyrics.lyrics.lines[0]),
+            vec![400, 1_000, 400, 2_000]
+        );
+    }
+
+    #[test]
+    fn end_segments_at_next_start_clamps_to_begin() {
+        // Out of order, and with the last segment starting after the end of its line.
+        let mut lyrics = lyrics(vec![line(
+            0,
+            1_000,
+            vec![
+                segment(600, 700, "late"),
+                segment(200, 300, "early"),
+                segment(1_200, 1_300, "after"),
+            ],
+        )]);
+        lyrics.end_segments_at_next_start();
+        assert_eq!(
+            segment_ends(&lyrics.lyrics.lines[0]),
+            vec![600, 1_200, 1_200]
+        );
+    }
+
+    #[test]
+    fn restore_segment_ends_undoes_auto_end() {
+        let original = lyrics(vec![
+            line(0, 1_000, vec![segment(0, 100, "a"), segment(500, 600, "b")]),
+            line(1_000, 2_000, vec![segment(1_000, 1_100, "c")]),
+        ]);
+        let mut lyrics = original.clone();
+        let previous_ends = lyrics.end_segments_at_next_start();
+        assert_eq!(segment_ends(&lyrics.lyrics.lines[0]), vec![500, 1_000]);
+        assert_eq!(segment_ends(&lyrics.lyrics.lines[1]), vec![2_000]);
+
+        lyrics.restore_segment_ends(&previous_ends);
+        assert_eq!(lyrics, original);
+
+        // A line whose segments changed since is left as it is.
+        lyrics.end_segments_at_next_start();
+        lyrics.lyrics.lines[1]
+            .original
+            .push(segment(1_500, 1_500, "d"));
+        lyrics.restore_segment_ends(&previous_ends);
+        assert_eq!(segment_ends(&lyrics.lyrics.lines[0]), vec![100, 600]);
+        assert_eq!(segment_ends(&lyrics.lyrics.lines[1]), vec![2_000, 1_500]);
+    }
+
+    #[test]
+    fn scale_timestamp_clamps() {
+        let ms = Duration::milliseconds;
+        assert_eq!(scale_timestamp(ms(3000), 1.5, ms(1000)), ms(4000));
+        assert_eq!(scale_timestamp(ms(3000), 0.5, ms(1000)), ms(2000));
+        assert_eq!(scale_timestamp(ms(0), 2.0, ms(1000)), Duration::zero());
+        let far = Duration::max_value() - ms(1);
+        assert_eq!(scale_timestamp(far, 2.0, ms(0)), Duration::max_value());
+        assert_eq!(scale_timestamp(ms(0), 2.0, far), Duration::zero());
+    }
 }
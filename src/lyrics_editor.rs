@@ -1,18 +1,47 @@
+use crate::analysis::DecodedAudio;
 use crate::component::colors::MfColors;
+use crate::component::timeline::{show_timeline, TimelineState};
+use crate::convert;
 use crate::icons;
 use crate::icons::material_design_icons::MDI_CHECK;
 use crate::lyrics::{
-    BabelLyrics, Lyrics, LyricsLine, LyricsMetadata, LyricsSegment, TranslationEntry,
+    scale_timestamp, BabelLyrics, LineTiming, Lyrics, LyricsLine, LyricsMetadata, LyricsSegment,
+    Marker, SegmentStyle, TextDirection, TranslationEntry, BABEL_LYRICS_VERSION,
+    SEGMENT_SIZE_RANGE,
 };
-use amll_lyric::ttml;
-use amll_lyric::ttml::TTMLLyric;
+use crate::timestamp::{format_timestamp, parse_timestamp};
 use chrono::Duration;
 use eframe::egui;
 use eframe::egui::RichText;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+/// Length of a newly added marker, in seconds.
+const DEFAULT_MARKER_LENGTH: i64 = 10;
+
+/// Largest timestamp the minute, second and millisecond fields can hold, 59:59.999, in
+/// milliseconds.
+const MAX_TIMESTAMP_MS: i64 = 59 * 60 * 1000 + 59 * 1000 + 999;
+
+/// Segments of a line selected for bulk actions.
+#[derive(Default)]
+struct SegmentSelection {
+    indices: BTreeSet<usize>,
+
+    /// How far "Shift selected" moves the selected segments, in milliseconds.
+    shift_ms: i64,
+}
+
+/// What to do with imported lyrics when the editor already holds lyrics.
+enum ImportDecision {
+    Replace,
+    Merge,
+    Keep,
+}
+
 pub struct LyricsEditor {
     pub show_lyrics_editor: bool,
 
@@ -22,17 +51,117 @@ pub struct LyricsEditor {
     lyrics_data_tx: mpsc::Sender<BabelLyrics>,
     lyrics_data_rx: mpsc::Receiver<BabelLyrics>,
 
+    lyrics_error_tx: mpsc::Sender<String>,
+    lyrics_error_rx: mpsc::Receiver<String>,
+
+    /// The error from the last failed lyrics load, if any.
+    lyrics_error: Option<String>,
+
     pub arc_loading_file: Arc<Mutex<bool>>,
     pub lyrics: Option<BabelLyrics>,
 
+    /// The lyrics as they were last loaded from or exported to a file, to tell whether the
+    /// working lyrics have unsaved changes.
+    saved_lyrics: Option<BabelLyrics>,
+    saved_tx: mpsc::Sender<BabelLyrics>,
+    saved_rx: mpsc::Receiver<BabelLyrics>,
+
     selected_file: Option<String>,
     file_name: Option<String>,
+
+    /// The path and name of the file being imported, until its lyrics arrive.
+    incoming_file: (Option<String>, Option<String>),
+
+    /// Imported lyrics waiting for the user to confirm that they replace the current edits,
+    /// with the path and name of their file.
+    pending_import: Option<(BabelLyrics, Option<String>, Option<String>)>,
+
+    /// Whether subtitle exports include the translations below the original text.
+    export_translations: bool,
+
+    /// Whether WebVTT exports include inline per-segment timestamps.
+    export_word_timings: bool,
+
+    /// A `(begin, end)` span the player should play once and then pause, requested by the
+    /// "Test timing" button of a line.
+    pub audition_request: Option<(Duration, Duration)>,
+
+    /// Whether the timeline view is shown above the list of lines.
+    show_timeline: bool,
+
+    /// While tap timing, the position of the segment the next tap times among
+    /// [`tap_targets`], which is past the end once every segment has been tapped.
+    tap_position: Option<usize>,
+    timeline: TimelineState,
+
+    /// Lyrics autosaved by a previous session that the user has not restored or discarded yet.
+    pub recovered_lyrics: Option<BabelLyrics>,
+
+    /// Segment selections, by the uuid of their line.
+    segment_selections: HashMap<Uuid, SegmentSelection>,
+
+    /// Index of the line selected for keyboard navigation.
+    selected_line: Option<usize>,
+
+    /// Lines picked for "Export selected lines", by uuid.
+    export_selection: HashSet<Uuid>,
+
+    /// Loudness below which the audio counts as silent, in dBFS.
+    silence_threshold_db: f32,
+
+    /// Shortest silence that separates two lines, in milliseconds.
+    silence_min_length_ms: i64,
+
+    detecting_silences: bool,
+    silence_tx: mpsc::Sender<Result<Vec<(Duration, Duration)>, String>>,
+    silence_rx: mpsc::Receiver<Result<Vec<(Duration, Duration)>, String>>,
+
+    /// Sounding spans between the detected silences, proposed as lines.
+    proposed_lines: Vec<(Duration, Duration)>,
+
+    /// Factor of the "Scale timings" operation.
+    scale_factor: f64,
+
+    /// The timestamp "Scale timings" stretches away from, in milliseconds.
+    scale_anchor_ms: i64,
+
+    /// Whether edited segment boundaries snap to the nearest zero-crossing of the audio.
+    snap_to_zero_crossings: bool,
+
+    /// Whether segment timestamps are edited as one `mm:ss.mmm` field instead of three.
+    typed_timestamps: bool,
+
+    /// Whether segments and translation words of only spaces are edited like any other text,
+    /// instead of being shown as "(space)".
+    edit_spaces: bool,
+
+    /// Whether runs of spaces typed into a segment or translation word collapse into one.
+    collapse_spaces: bool,
+
+    /// Whether lines are put back in order of their begin whenever it is edited.
+    auto_sort_lines: bool,
+
+    /// Whether exports end each segment at the start of the next, leaving the edited lyrics
+    /// as they are.
+    export_auto_end: bool,
+
+    /// Segment ends from before "End at next start" was applied, for undoing it.
+    auto_end_backup: Option<HashMap<Uuid, Vec<Duration>>>,
+
+    /// Text typed for "Auto-time" on each line, by uuid.
+    auto_time_texts: HashMap<Uuid, String>,
+
+    /// Segments of each line from before "Auto-time" was applied, for undoing it.
+    auto_time_backups: HashMap<Uuid, Vec<LyricsSegment>>,
 }
 
 impl Default for LyricsEditor {
     fn default() -> Self {
         let (lyrics_details_tx, lyrics_details_rx) = mpsc::channel(32);
         let (lyrics_data_tx, lyrics_data_rx) = mpsc::channel(32);
+        let (lyrics_error_tx, lyrics_error_rx) = mpsc::channel(32);
+        let (silence_tx, silence_rx) = mpsc::channel(32);
+        let (saved_tx, saved_rx) = mpsc::channel(32);
 
         LyricsEditor {
             show_lyrics_editor: false,
@@ -40,16 +169,387 @@ impl Default for LyricsEditor {
             lyrics_details_rx,
             lyrics_data_tx,
             lyrics_data_rx,
+            lyrics_error_tx,
+            lyrics_error_rx,
+            lyrics_error: None,
             arc_loading_file: Arc::new(Mutex::new(false)),
             lyrics: None,
+            saved_lyrics: None,
+            saved_tx,
+            saved_rx,
             selected_file: None,
             file_name: None,
+            incoming_file: (None, None),
+            pending_import: None,
+            export_translations: true,
+            export_word_timings: false,
+            audition_request: None,
+            show_timeline: false,
+            tap_position: None,
+            timeline: TimelineState::default(),
+            recovered_lyrics: None,
+            segment_selections: HashMap::new(),
+            selected_line: None,
+            export_selection: HashSet::new(),
+            silence_threshold_db: -40.0,
+            silence_min_length_ms: 300,
+            detecting_silences: false,
+            silence_tx,
+            silence_rx,
+            proposed_lines: Vec::new(),
+            scale_factor: 1.0,
+            scale_anchor_ms: 0,
+            snap_to_zero_crossings: false,
+            typed_timestamps: false,
+            edit_spaces: false,
+            collapse_spaces: false,
+            auto_sort_lines: false,
+            export_auto_end: false,
+            auto_end_backup: None,
+            auto_time_texts: HashMap::new(),
+            auto_time_backups: HashMap::new(),
         }
     }
 }
 
 impl LyricsEditor {
-    pub fn show_lyrics_editor_window(&mut self, ctx: &egui::Context) -> anyhow::Result<()> {
+    /// The lyrics to autosave for recovery: the working lyrics, or the ones recovered from the
+    /// last session if they are still waiting for a decision.
+    pub fn recovery_lyrics(&self) -> Option<&BabelLyrics> {
+        self.lyrics.as_ref().or(self.recovered_lyrics.as_ref())
+    }
+
+    /// Whether the lyrics being edited differ from the ones last loaded from or exported to a
+    /// file.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.lyrics.is_some() && self.lyrics != self.saved_lyrics
+    }
+
+    /// Offers to restore the lyrics recovered from the last session.
+    pub fn show_recovery_prompt(&mut self, ctx: &egui::Context) {
+        if self.recovered_lyrics.is_none() {
+            return;
+        }
+        egui::Window::new("Recover lyrics")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("The lyrics being edited in the last session were recovered.");
+                ui.horizontal(|ui| {
+                    if ui.button("Restore").clicked() {
+                        self.lyrics = self.recovered_lyrics.take();
+                        self.file_name = Some("Recovered".to_string());
+                        self.show_lyrics_editor = true;
+                    }
+                    if ui.button("Discard").clicked() {
+                        self.recovered_lyrics = None;
+                    }
+                });
+            });
+    }
+
+    /// Asks whether imported lyrics should replace the lyrics being edited, summarizing what
+    /// would change.
+    fn show_import_confirmation(&mut self, ctx: &egui::Context) {
+        let (Some((imported, _, file_name)), Some(current)) = (&self.pending_import, &self.lyrics)
+        else {
+            return;
+        };
+        let diff = current.diff_lines(imported);
+        let file_name = file_name.clone().unwrap_or_default();
+
+        let mut decision = None;
+        egui::Window::new("Replace lyrics?")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Importing {} will replace the lyrics being edited.",
+                    file_name
+                ));
+                if diff.is_empty() {
+                    ui.label("The lines and their timing are the same.");
+                } else {
+                    egui::Grid::new("import_diff_grid").show(ui, |ui| {
+                        ui.label("Lines added");
+                        ui.colored_label(MfColors::GREEN_400, diff.added.to_string());
+                        ui.end_row();
+                        ui.label("Lines removed");
+                        ui.colored_label(MfColors::RED_400, diff.removed.to_string());
+                        ui.end_row();
+                        ui.label("Lines changed");
+                        ui.colored_label(MfColors::YELLOW_400, diff.changed.to_string());
+                        ui.end_row();
+                    });
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("Replace").clicked() {
+                        decision = Some(ImportDecision::Replace);
+                    }
+                    if ui
+                        .button("Merge")
+                        .on_hover_text(
+                            "Take the imported lines, but keep the current timing, translations \
+                             and notes of lines whose text is unchanged",
+                        )
+                        .clicked()
+                    {
+                        decision = Some(ImportDecision::Merge);
+                    }
+                    if ui.button("Keep current").clicked() {
+                        decision = Some(ImportDecision::Keep);
+                    }
+                });
+            });
+
+        let Some(decision) = decision else {
+            return;
+        };
+        let (imported, selected_file, file_name) = self.pending_import.take().unwrap();
+        match decision {
+            ImportDecision::Replace => {
+                self.saved_lyrics = Some(imported.clone());
+                self.lyrics = Some(imported);
+            }
+            ImportDecision::Merge => self.lyrics.as_mut().unwrap().merge_lines(imported),
+            ImportDecision::Keep => return,
+        }
+        self.selected_file = selected_file;
+        self.file_name = file_name;
+        self.segment_selections.clear();
+        self.selected_line = None;
+        self.export_selection.clear();
+        self.auto_end_backup = None;
+        self.auto_time_texts.clear();
+        self.auto_time_backups.clear();
+    }
+
+    /// Opens the editor on fresh lyrics for audio lasting `duration`: a single blank line
+    /// spanning the audio, to split and fill in. Lyrics already being edited are only replaced
+    /// once the user confirms.
+    pub fn start_new_lyrics(&mut self, duration: Option<Duration>) {
+        let end = duration.unwrap_or_else(Duration::zero);
+        let lyrics = BabelLyrics {
+            version: BABEL_LYRICS_VERSION,
+            metadata: LyricsMetadata {
+                agents: Vec::new(),
+                translations: Vec::new(),
+            },
+            lyrics: Lyrics {
+                lines: vec![LyricsLine {
+                    begin: Duration::zero(),
+                    end,
+                    agent_id: String::new(),
+                    original: vec![LyricsSegment {
+                        begin: Duration::zero(),
+                        end,
+                        text: String::new(),
+                        translations: Vec::new(),
+                        needs_review: false,
+                        romanization: String::new(),
+                        ruby: String::new(),
+                        style: SegmentStyle::default(),
+                    }],
+                    translations: Vec::new(),
+                    uuid: Uuid::new_v4(),
+                    note: String::new(),
+                    disabled: false,
+                }],
+            },
+            markers: Vec::new(),
+            direction: TextDirection::default(),
+        };
+        let file_name = Some("New lyrics".to_string());
+        if self.lyrics.is_some() {
+            self.pending_import = Some((lyrics, None, file_name));
+        } else {
+            self.lyrics = Some(lyrics);
+            self.selected_file = None;
+            self.file_name = file_name;
+        }
+        self.show_lyrics_editor = true;
+    }
+
+    /// Reorders the lines by their begin, keeping the same line selected.
+    fn sort_lines(&mut self) {
+        let Some(ref mut lyrics) = self.lyrics else {
+            return;
+        };
+        let selected = self
+            .selected_line
+            .and_then(|index| lyrics.lyrics.lines.get(index))
+            .map(|line| line.uuid);
+        if lyrics.sort_lines() {
+            self.selected_line = selected.and_then(|uuid| {
+                lyrics
+                    .lyrics
+                    .lines
+                    .iter()
+                    .position(|line| line.uuid == uuid)
+            });
+        }
+    }
+
+    /// The segment the next tap times, as the uuid of its line and its index in the line.
+    pub fn armed_segment(&self) -> Option<(Uuid, usize)> {
+        let lyrics = self.lyrics.as_ref()?;
+        let (line_index, segment_index) = *tap_targets(lyrics).get(self.tap_position?)?;
+        Some((lyrics.lyrics.lines[line_index].uuid, segment_index))
+    }
+
+    /// The progress of tap timing as `(position, count)`, with `position` counting from 1 and
+    /// past `count` once every segment has been tapped.
+    pub fn tap_progress(&self) -> Option<(usize, usize)> {
+        let count = tap_targets(self.lyrics.as_ref()?).len();
+        Some((self.tap_position? + 1, count))
+    }
+
+    /// Starts the armed segment at `timestamp`, ending the segment tapped before it if it is in
+    /// the same line, and arms the next one.
+    pub fn tap(&mut self, timestamp: Duration) {
+        let (Some(position), Some(lyrics)) = (self.tap_position, self.lyrics.as_mut()) else {
+            return;
+        };
+        let targets = tap_targets(lyrics);
+        let Some(&(line_index, segment_index)) = targets.get(position) else {
+            return;
+        };
+        let line = &mut lyrics.lyrics.lines[line_index];
+        if let Some(&(previous_line, previous_segment)) =
+            position.checked_sub(1).and_then(|index| targets.get(index))
+        {
+            if previous_line == line_index {
+                // Blank segments between the two words get no time of their own.
+                for segment in &mut line.original[previous_segment + 1..segment_index] {
+                    segment.begin = timestamp;
+                    segment.end = timestamp;
+                }
+                line.original[previous_segment].end = timestamp;
+            }
+        }
+        let segment = &mut line.original[segment_index];
+        segment.begin = timestamp;
+        segment.end = segment.end.max(timestamp);
+        if let Some(first) = line.original.first() {
+            line.begin = first.begin;
+        }
+        line.end = line.end.max(line.original[segment_index].end);
+        self.tap_position = Some(position + 1);
+    }
+
+    fn show_tap_timing(&mut self, ui: &mut egui::Ui) {
+        if self.lyrics.is_none() {
+            return;
+        }
+        ui.horizontal(|ui| {
+            let mut tapping = self.tap_position.is_some();
+            if ui
+                .toggle_value(&mut tapping, "Tap timing")
+                .on_hover_text("Time the segments one by one by pressing the Tap shortcut")
+                .changed()
+            {
+                self.tap_position = tapping.then_some(0);
+            }
+            let Some((position, count)) = self.tap_progress() else {
+                return;
+            };
+            if position > count {
+                ui.colored_label(
+                    MfColors::GREEN_400,
+                    format!("{} All {} segments timed", MDI_CHECK, count),
+                );
+            } else {
+                ui.colored_label(
+                    MfColors::ORANGE_500,
+                    format!("Segment {} of {}", position, count),
+                );
+            }
+            if ui
+                .add_enabled(position > 1, egui::Button::new("Back"))
+                .on_hover_text("Arm the previous segment again")
+                .clicked()
+            {
+                self.tap_position = Some(position - 2);
+            }
+            if ui.button("Restart").clicked() {
+                self.tap_position = Some(0);
+            }
+        });
+    }
+
+    /// Shows the buttons to end every segment at the start of the next one, and to undo it.
+    fn show_auto_end(&mut self, ui: &mut egui::Ui) {
+        let Some(ref mut lyrics) = self.lyrics else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            if ui
+                .button("End at next start")
+                .on_hover_text(
+                    "End each segment where the next one begins, and the last one at the end \
+                     of its line",
+                )
+                .clicked()
+            {
+                let previous_ends = lyrics.end_segments_at_next_start();
+                // Undoing goes back to before the first of repeated applications.
+                self.auto_end_backup.get_or_insert(previous_ends);
+            }
+            if let Some(ref previous_ends) = self.auto_end_backup {
+                if ui
+                    .button("Undo")
+                    .on_hover_text("Put back the segment ends from before")
+                    .clicked()
+                {
+                    lyrics.restore_segment_ends(previous_ends);
+                    self.auto_end_backup = None;
+                }
+            }
+        });
+    }
+
+    /// Shows the button to fit the timing of every line to its segments.
+    fn show_fit_lines(&mut self, ui: &mut egui::Ui) {
+        let Some(ref mut lyrics) = self.lyrics else {
+            return;
+        };
+        if ui
+            .button("Fit lines to segments")
+            .on_hover_text(
+                "Set every line to run from the earliest begin to the latest end of its \
+                 segments, so that the captions and lyrics windows follow the segments",
+            )
+            .clicked()
+        {
+            for line in &mut lyrics.lyrics.lines {
+                line.fit_to_segments();
+            }
+        }
+    }
+
+    /// The lyrics as exported to other formats, with the export options applied.
+    fn export_lyrics(&self) -> Cow<'_, BabelLyrics> {
+        let lyrics = self.lyrics.as_ref().unwrap();
+        if self.export_auto_end {
+            let mut lyrics = lyrics.clone();
+            lyrics.end_segments_at_next_start();
+            Cow::Owned(lyrics)
+        } else {
+            Cow::Borrowed(lyrics)
+        }
+    }
+
+    /// Shows the editor window. `player_timestamp` is the current position of the player, used
+    /// by actions that work at the playhead, and `audio_data` is the loaded audio file, used by
+    /// the analysis tools.
+    pub fn show_lyrics_editor_window(
+        &mut self,
+        ctx: &egui::Context,
+        player_timestamp: Duration,
+        audio_data: Option<&[u8]>,
+        decoded_audio: Option<&DecodedAudio>,
+    ) -> anyhow::Result<()> {
+        self.show_import_confirmation(ctx);
         egui::Window::new("Lyrics Editor").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 let loading_lyrics_file = *self.arc_loading_file.lock().unwrap();
@@ -71,19 +571,40 @@ impl LyricsEditor {
                     {
                         let details_tx = self.lyrics_details_tx.clone();
                         let data_tx = self.lyrics_data_tx.clone();
+                        let error_tx = self.lyrics_error_tx.clone();
                         let arc_loading_file = self.arc_loading_file.clone();
                         tokio::spawn(async move {
-                            ttml_lyrics_file_loader(arc_loading_file, details_tx, data_tx).await;
+                            ttml_lyrics_file_loader(
+                                arc_loading_file,
+                                details_tx,
+                                data_tx,
+                                error_tx,
+                            )
+                            .await;
                         });
                     }
                     if ui.button("Select lyrics file").clicked() {
                         let details_tx = self.lyrics_details_tx.clone();
                         let data_tx = self.lyrics_data_tx.clone();
+                        let error_tx = self.lyrics_error_tx.clone();
                         let arc_loading_file = self.arc_loading_file.clone();
                         tokio::spawn(async move {
-                            json_lyrics_file_loader(arc_loading_file, details_tx, data_tx).await;
+                            json_lyrics_file_loader(
+                                arc_loading_file,
+                                details_tx,
+                                data_tx,
+                                error_tx,
+                            )
+                            .await;
                         });
                     }
+                    if ui
+                        .button("New lyrics")
+                        .on_hover_text("Start from a blank line spanning the loaded audio")
+                        .clicked()
+                    {
+                        self.start_new_lyrics(decoded_audio.map(DecodedAudio::duration));
+                    }
                 });
                 if loading_lyrics_file {
                     ui.spinner();
@@ -92,33 +613,184 @@ impl LyricsEditor {
                 }
             });
             ui.add_enabled_ui(self.lyrics.is_some(), |ui| {
-                if ui.button("Export Babel Lyrics").clicked() {
-                    let lyrics = self.lyrics.clone();
-                    tokio::spawn(async move {
-                        let file = rfd::FileDialog::new()
-                            .add_filter("JSON", &["json"])
-                            .save_file();
-                        if let Some(path) = file {
-                            let f = std::fs::File::create(&path).unwrap();
-                            let mut writer = std::io::BufWriter::new(f);
-                            let _ = serde_json::to_writer(&mut writer, lyrics.as_ref().unwrap());
-                        }
-                    });
-                }
+                ui.horizontal(|ui| {
+                    if ui.button("Export Babel Lyrics").clicked() {
+                        let lyrics = self.lyrics.clone();
+                        let saved_tx = self.saved_tx.clone();
+                        tokio::spawn(async move {
+                            let file = rfd::FileDialog::new()
+                                .add_filter("JSON", &["json"])
+                                .save_file();
+                            if let Some(path) = file {
+                                let f = std::fs::File::create(&path).unwrap();
+                                let mut writer = std::io::BufWriter::new(f);
+                                let lyrics = lyrics.unwrap();
+                                if serde_json::to_writer(&mut writer, &lyrics).is_ok() {
+                                    let _ = saved_tx.send(lyrics).await;
+                                }
+                            }
+                        });
+                    }
+                    if ui.button("Export TTML").clicked() {
+                        let ttml = convert::babel_to_ttml(&self.export_lyrics());
+                        tokio::spawn(async move {
+                            save_text_file("AMLL TTML Lyrics", "ttml", ttml).await;
+                        });
+                    }
+                    if ui.button("Export SRT").clicked() {
+                        let srt =
+                            convert::babel_to_srt(&self.export_lyrics(), self.export_translations);
+                        tokio::spawn(async move {
+                            save_text_file("SubRip Subtitles", "srt", srt).await;
+                        });
+                    }
+                    if ui.button("Export VTT").clicked() {
+                        let vtt = convert::babel_to_vtt(
+                            &self.export_lyrics(),
+                            self.export_translations,
+                            self.export_word_timings,
+                        );
+                        tokio::spawn(async move {
+                            save_text_file("WebVTT Subtitles", "vtt", vtt).await;
+                        });
+                    }
+                    if ui
+                        .button("Export HTML")
+                        .on_hover_text("A web page that highlights the lyrics along with the audio")
+                        .clicked()
+                    {
+                        let title = self.file_name.as_deref().unwrap_or("Lyrics");
+                        let html = convert::babel_to_html(
+                            &self.export_lyrics(),
+                            title,
+                            self.export_translations,
+                        );
+                        tokio::spawn(async move {
+                            save_text_file("HTML", "html", html).await;
+                        });
+                    }
+                    let selected_lines = self.export_selection.len();
+                    if ui
+                        .add_enabled(
+                            selected_lines > 0,
+                            egui::Button::new(format!(
+                                "Export selected lines ({})",
+                                selected_lines
+                            )),
+                        )
+                        .on_disabled_hover_text("Tick \"Export\" on the lines to export")
+                        .clicked()
+                    {
+                        let subset = self.lyrics.as_ref().unwrap().subset(&self.export_selection);
+                        tokio::spawn(async move {
+                            match convert::babel_to_json(&subset) {
+                                Ok(json) => save_text_file("JSON", "json", json).await,
+                                Err(e) => eprintln!("Failed to serialize lyrics: {}", e),
+                            }
+                        });
+                    }
+                    ui.checkbox(&mut self.export_translations, "Include translations");
+                    ui.checkbox(&mut self.export_word_timings, "Word timings (VTT)");
+                    ui.checkbox(&mut self.export_auto_end, "End segments at next start")
+                        .on_hover_text(
+                            "Export each segment as lasting until the next one starts, without \
+                             changing the lyrics being edited",
+                        );
+                });
             });
-            if let Ok((selected_file, file_name)) = self.lyrics_details_rx.try_recv() {
+            if let Ok(details) = self.lyrics_details_rx.try_recv() {
                 *self.arc_loading_file.lock().unwrap() = false;
-                self.selected_file = selected_file;
-                self.file_name = file_name;
+                self.incoming_file = details;
             }
 
             if let Ok(lyrics_data) = self.lyrics_data_rx.try_recv() {
-                self.lyrics = Some(lyrics_data);
+                let (selected_file, file_name) = std::mem::take(&mut self.incoming_file);
+                self.lyrics_error = None;
+                if self.lyrics.is_some() {
+                    // Importing over existing lyrics would lose their edits, so ask first.
+                    self.pending_import = Some((lyrics_data, selected_file, file_name));
+                } else {
+                    self.saved_lyrics = Some(lyrics_data.clone());
+                    self.lyrics = Some(lyrics_data);
+                    self.selected_file = selected_file;
+                    self.file_name = file_name;
+                    self.export_selection.clear();
+                    self.auto_end_backup = None;
+                    self.auto_time_texts.clear();
+                    self.auto_time_backups.clear();
+                }
+            }
+
+            if let Ok(saved_lyrics) = self.saved_rx.try_recv() {
+                self.saved_lyrics = Some(saved_lyrics);
+            }
+
+            if let Ok(lyrics_error) = self.lyrics_error_rx.try_recv() {
+                *self.arc_loading_file.lock().unwrap() = false;
+                self.lyrics_error = Some(lyrics_error);
+            }
+
+            if let Some(ref lyrics_error) = self.lyrics_error {
+                ui.colored_label(MfColors::RED_400, lyrics_error);
             }
 
+            self.show_silence_detection(ui, audio_data);
+            let audio_duration = decoded_audio.map(DecodedAudio::duration);
+            self.show_scale_timings(ui, player_timestamp, audio_duration);
+            self.show_markers(ui, player_timestamp, audio_duration);
+
             ui.separator();
             self.show_lyrics_file_details_grid(ui);
+            self.show_stats(ui, audio_duration);
             ui.separator();
+            self.show_tap_timing(ui);
+            self.show_auto_end(ui);
+            self.show_fit_lines(ui);
+            ui.add_enabled(
+                decoded_audio.is_some(),
+                egui::Checkbox::new(&mut self.snap_to_zero_crossings, "Snap to zero-crossings"),
+            )
+            .on_hover_text(
+                "Move edited segment boundaries to the nearest point where the audio crosses \
+                 zero, so that auditioned segments do not click. Timestamps are kept to the \
+                 millisecond, so a boundary can land up to half a millisecond from the crossing",
+            )
+            .on_disabled_hover_text("Waiting for the audio to be decoded");
+            ui.checkbox(&mut self.typed_timestamps, "Type timestamps")
+                .on_hover_text("Edit segment timestamps as a single mm:ss.mmm field");
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.edit_spaces, "Edit spaces")
+                    .on_hover_text(
+                        "Edit segments and translation words made of spaces instead of showing \
+                         them as (space)",
+                    );
+                ui.checkbox(&mut self.collapse_spaces, "Collapse spaces")
+                    .on_hover_text("Turn several spaces typed in a row into one");
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.auto_sort_lines, "Keep lines sorted")
+                    .on_hover_text("Reorder the lines by their start whenever one is changed");
+                if ui
+                    .add_enabled(self.lyrics.is_some(), egui::Button::new("Sort lines"))
+                    .on_hover_text("Reorder the lines by their start")
+                    .clicked()
+                {
+                    self.sort_lines();
+                }
+            });
+            // Timings before this frame's edits, to tell which ones to snap.
+            let timings_before = self
+                .lyrics
+                .as_ref()
+                .filter(|_| self.snap_to_zero_crossings)
+                .map(segment_timings);
+            if let Some(ref mut lyrics) = self.lyrics {
+                ui.toggle_value(&mut self.show_timeline, "Timeline view");
+                if self.show_timeline {
+                    show_timeline(ui, lyrics, &mut self.timeline, player_timestamp);
+                    ui.separator();
+                }
+            }
             if self.lyrics.is_some() {
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     // A map of translation language id to its name in metadata.
@@ -156,20 +828,58 @@ impl LyricsEditor {
                         .map(|entry| (entry.id, Vec::new()))
                         .collect();
 
-                    self.show_lyrics_lines(ui, translation_language_map, empty_translations_usize);
+                    self.show_lyrics_lines(
+                        ui,
+                        translation_language_map,
+                        empty_translations_usize,
+                        player_timestamp,
+                    );
 
-                    if ui.button("+ Add Line").clicked() {
-                        self.lyrics.as_mut().unwrap().lyrics.lines.push(LyricsLine {
-                            begin: Duration::zero(),
-                            end: Duration::zero(),
-                            agent_id: String::new(),
-                            original: Vec::new(),
-                            translations: empty_translations_string.clone(),
-                            uuid: Uuid::new_v4(),
-                        });
-                    }
+                    ui.horizontal(|ui| {
+                        if ui.button("+ Add Line").clicked() {
+                            self.lyrics.as_mut().unwrap().lyrics.lines.push(LyricsLine {
+                                begin: Duration::zero(),
+                                end: Duration::zero(),
+                                agent_id: String::new(),
+                                original: Vec::new(),
+                                translations: empty_translations_string.clone(),
+                                uuid: Uuid::new_v4(),
+                                note: String::new(),
+                                disabled: false,
+                            });
+                        }
+                        if ui
+                            .button("Insert line at playhead")
+                            .on_hover_text("Add an empty line starting at the playhead, in order")
+                            .clicked()
+                        {
+                            let index =
+                                self.lyrics
+                                    .as_mut()
+                                    .unwrap()
+                                    .insert_line_in_order(LyricsLine {
+                                        begin: player_timestamp,
+                                        end: player_timestamp,
+                                        agent_id: String::new(),
+                                        original: Vec::new(),
+                                        translations: empty_translations_string.clone(),
+                                        uuid: Uuid::new_v4(),
+                                        note: String::new(),
+                                        disabled: false,
+                                    });
+                            self.selected_line = Some(index);
+                        }
+                    });
                 });
             }
+            if let (Some(lyrics), Some(before), Some(audio)) =
+                (&mut self.lyrics, timings_before, decoded_audio)
+            {
+                snap_edited_timings(lyrics, &before, audio);
+            }
+            if self.auto_sort_lines {
+                self.sort_lines();
+            }
         });
         Ok(())
     }
@@ -179,25 +889,250 @@ impl LyricsEditor {
         ui: &mut egui::Ui,
         translation_language_map: std::collections::HashMap<Uuid, String>,
         empty_translations_usize: Vec<(Uuid, Vec<usize>)>,
+        player_timestamp: Duration,
     ) {
-        for line in self.lyrics.as_mut().unwrap().lyrics.lines.iter_mut() {
-            egui::CollapsingHeader::new(
+        let mut to_duplicate = Vec::<usize>::new();
+        let mut audition_request = None;
+
+        // Up and down move the selection between lines while no text field is being edited.
+        let line_count = self.lyrics.as_ref().unwrap().lyrics.lines.len();
+        let previous_selection = self.selected_line.filter(|index| *index < line_count);
+        let mut navigated = false;
+        if let Some(selected) = previous_selection {
+            if !ui.ctx().wants_keyboard_input() {
+                let (up, down, escape) = ui.input_mut(|i| {
+                    (
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                        i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                    )
+                });
+                if escape {
+                    self.selected_line = None;
+                } else if up && selected > 0 {
+                    self.selected_line = Some(selected - 1);
+                    navigated = true;
+                } else if down && selected + 1 < line_count {
+                    self.selected_line = Some(selected + 1);
+                    navigated = true;
+                }
+            }
+        }
+
+        let armed_segment = self.armed_segment();
+        let mut clicked_line = None;
+        for (index, line) in self
+            .lyrics
+            .as_mut()
+            .unwrap()
+            .lyrics
+            .lines
+            .iter_mut()
+            .enumerate()
+        {
+            let selected = self.selected_line == Some(index);
+            let mut header_text = RichText::new(
                 line.original
                     .iter()
                     .map(|seg| seg.text.as_str())
                     .collect::<String>(),
-            )
-            .id_source(line.uuid)
-            .default_open(false)
-            .show(ui, |ui| {
-                ui.horizontal(|ui| {
-                    ui.label("Agent");
-                    ui.text_edit_singleline(&mut line.agent_id);
+            );
+            if selected {
+                header_text = header_text.color(MfColors::ORANGE_500);
+            } else if line.disabled {
+                header_text = header_text.color(MfColors::GRAY_500);
+            }
+            if line.disabled {
+                header_text = header_text.strikethrough();
+            }
+            // The header starts with a dot showing how far the line is timed.
+            let (timing_icon, timing_color) = match line.timing() {
+                LineTiming::Timed => (
+                    icons::material_design_icons::MDI_CIRCLE,
+                    MfColors::GREEN_500,
+                ),
+                LineTiming::Partial => (
+                    icons::material_design_icons::MDI_CIRCLE_HALF_FULL,
+                    MfColors::YELLOW_500,
+                ),
+                LineTiming::Untimed => (
+                    icons::material_design_icons::MDI_CIRCLE_OUTLINE,
+                    MfColors::RED_400,
+                ),
+            };
+            let mut header = egui::text::LayoutJob::default();
+            let style = ui.style();
+            RichText::new(format!("{} ", timing_icon))
+                .color(timing_color)
+                .append_to(
+                    &mut header,
+                    style,
+                    egui::FontSelection::Default,
+                    egui::Align::Center,
+                );
+            header_text.append_to(
+                &mut header,
+                style,
+                egui::FontSelection::Default,
+                egui::Align::Center,
+            );
+            let open = if navigated && selected {
+                Some(true)
+            } else if navigated && previous_selection == Some(index) {
+                Some(false)
+            } else {
+                None
+            };
+            let response = egui::CollapsingHeader::new(header)
+                .id_source(line.uuid)
+                .default_open(false)
+                .open(open)
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Agent");
+                        ui.text_edit_singleline(&mut line.agent_id);
+                        let mut export = self.export_selection.contains(&line.uuid);
+                        if ui
+                            .checkbox(&mut export, "Export")
+                            .on_hover_text("Include this line in \"Export selected lines\"")
+                            .changed()
+                        {
+                            if export {
+                                self.export_selection.insert(line.uuid);
+                            } else {
+                                self.export_selection.remove(&line.uuid);
+                            }
+                        }
+                        ui.checkbox(&mut line.disabled, "Disabled").on_hover_text(
+                            "Keep this line in the project but leave it out of SRT, VTT and \
+                             TTML exports",
+                        );
+                        if ui
+                            .button(icons::material_design_icons::MDI_CONTENT_COPY)
+                            .on_hover_text("Duplicate line")
+                            .clicked()
+                        {
+                            to_duplicate.push(index);
+                        }
+                        if ui
+                            .button(icons::material_design_icons::MDI_PLAY)
+                            .on_hover_text("Test timing: play just this line")
+                            .clicked()
+                        {
+                            audition_request = Some((line.begin, line.end));
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Line")
+                            .on_hover_text("When the line is shown in the captions window");
+                        timestamp_edit(ui, &mut line.begin, self.typed_timestamps);
+                        ui.label("to");
+                        timestamp_edit(ui, &mut line.end, self.typed_timestamps);
+                        if ui
+                            .add_enabled(
+                                !line.original.is_empty(),
+                                egui::Button::new("Auto from segments"),
+                            )
+                            .on_hover_text(
+                                "Begin at the earliest segment begin and end at the latest \
+                                 segment end",
+                            )
+                            .clicked()
+                        {
+                            line.fit_to_segments();
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Note");
+                        ui.add(
+                            egui::TextEdit::multiline(&mut line.note)
+                                .desired_rows(2)
+                                .hint_text("e.g. background vocal unclear"),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        let line_text = line
+                            .original
+                            .iter()
+                            .map(|seg| seg.text.as_str())
+                            .collect::<String>();
+                        let text = self.auto_time_texts.entry(line.uuid).or_default();
+                        ui.label("Auto-time");
+                        ui.add(egui::TextEdit::singleline(text).hint_text(line_text.as_str()))
+                            .on_hover_text(
+                                "Words to spread over the line, or leave empty to re-time the \
+                                 current segments",
+                            );
+                        let words = if text.trim().is_empty() {
+                            line_text
+                        } else {
+                            text.clone()
+                        };
+                        if ui
+                            .add_enabled(
+                                !words.trim().is_empty() && line.begin < line.end,
+                                egui::Button::new("Auto-time"),
+                            )
+                            .on_hover_text(
+                                "Replace the segments with these words, timed from the line's \
+                                 begin to its end in proportion to their length",
+                            )
+                            .clicked()
+                        {
+                            let previous = line.auto_time(&words);
+                            // Undoing goes back to before the first of repeated applications.
+                            self.auto_time_backups.entry(line.uuid).or_insert(previous);
+                            self.segment_selections.remove(&line.uuid);
+                            text.clear();
+                        }
+                        if self.auto_time_backups.contains_key(&line.uuid)
+                            && ui
+                                .button("Undo")
+                                .on_hover_text("Put back the segments from before auto-timing")
+                                .clicked()
+                        {
+                            line.original = self.auto_time_backups.remove(&line.uuid).unwrap();
+                            self.segment_selections.remove(&line.uuid);
+                        }
+                    });
+                    let options = SegmentEditOptions {
+                        typed_timestamps: self.typed_timestamps,
+                        edit_spaces: self.edit_spaces,
+                        collapse_spaces: self.collapse_spaces,
+                    };
+                    show_line_translations(ui, line, &translation_language_map, options);
+                    ui.separator();
+                    show_segment_edit_grid(
+                        line,
+                        ui,
+                        empty_translations_usize.clone(),
+                        player_timestamp,
+                        self.segment_selections.entry(line.uuid).or_default(),
+                        armed_segment
+                            .filter(|(uuid, _)| *uuid == line.uuid)
+                            .map(|(_, segment_index)| segment_index),
+                        options,
+                    );
                 });
-                show_line_translations(ui, line, &translation_language_map);
-                ui.separator();
-                show_segment_edit_grid(line, ui, empty_translations_usize.clone());
-            });
+            if response.header_response.clicked() {
+                clicked_line = Some(index);
+            }
+            if navigated && selected {
+                response
+                    .header_response
+                    .scroll_to_me(Some(egui::Align::Center));
+            }
+        }
+        if clicked_line.is_some() {
+            self.selected_line = clicked_line;
+        }
+        if audition_request.is_some() {
+            self.audition_request = audition_request;
+        }
+        let lines = &mut self.lyrics.as_mut().unwrap().lyrics.lines;
+        for index in to_duplicate.iter().rev() {
+            let line = duplicate_line(&lines[*index]);
+            lines.insert(*index + 1, line);
         }
     }
 
@@ -253,7 +1188,267 @@ impl LyricsEditor {
         });
     }
 
-    fn show_lyrics_file_details_grid(&self, ui: &mut egui::Ui) {
+    /// Shows the "Scale timings" operation, which stretches all timestamps around an anchor,
+    /// with a preview of the resulting span of the lyrics. The anchor is kept within the
+    /// lyrics and the audio, if loaded.
+    fn show_scale_timings(
+        &mut self,
+        ui: &mut egui::Ui,
+        player_timestamp: Duration,
+        audio_duration: Option<Duration>,
+    ) {
+        let Some(ref mut lyrics) = self.lyrics else {
+            return;
+        };
+        let anchor_limit = lyrics
+            .span()
+            .map(|(_, end)| end)
+            .max(audio_duration)
+            .unwrap_or_else(Duration::zero)
+            .num_milliseconds();
+        self.scale_anchor_ms = self.scale_anchor_ms.min(anchor_limit);
+        ui.collapsing("Scale timings", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Factor");
+                ui.add(
+                    egui::DragValue::new(&mut self.scale_factor)
+                        .speed(0.0001)
+                        .range(0.5..=2.0)
+                        .fixed_decimals(4),
+                );
+                ui.label("Anchor");
+                ui.add(
+                    egui::DragValue::new(&mut self.scale_anchor_ms)
+                        .speed(10)
+                        .range(0..=anchor_limit)
+                        .suffix(" ms"),
+                );
+                if ui
+                    .button("Playhead")
+                    .on_hover_text("Anchor at the current position of the player")
+                    .clicked()
+                {
+                    self.scale_anchor_ms = player_timestamp.num_milliseconds().min(anchor_limit);
+                }
+            });
+
+            let anchor = Duration::milliseconds(self.scale_anchor_ms);
+            if let Some((begin, end)) = lyrics.span() {
+                let scaled_begin = scale_timestamp(begin, self.scale_factor, anchor);
+                let scaled_end = scale_timestamp(end, self.scale_factor, anchor);
+                egui::Grid::new("scale_timings_preview_grid").show(ui, |ui| {
+                    ui.label("Current span");
+                    ui.label(format!(
+                        "{} → {}",
+                        format_timestamp(begin),
+                        format_timestamp(end)
+                    ));
+                    ui.end_row();
+                    ui.label("Scaled span");
+                    ui.label(format!(
+                        "{} → {}",
+                        format_timestamp(scaled_begin),
+                        format_timestamp(scaled_end)
+                    ));
+                    ui.end_row();
+                });
+            }
+
+            if ui
+                .add_enabled(self.scale_factor != 1.0, egui::Button::new("Apply"))
+                .clicked()
+            {
+                lyrics.scale_timings(self.scale_factor, anchor);
+                self.scale_factor = 1.0;
+            }
+        });
+    }
+
+    /// Lists the instrumental markers, with their label and span editable. Markers end no later
+    /// than the audio, if loaded.
+    fn show_markers(
+        &mut self,
+        ui: &mut egui::Ui,
+        player_timestamp: Duration,
+        audio_duration: Option<Duration>,
+    ) {
+        let Some(ref mut lyrics) = self.lyrics else {
+            return;
+        };
+        let end_limit = audio_duration.map_or(MAX_TIMESTAMP_MS, |d| d.num_milliseconds());
+        ui.collapsing(format!("Markers ({})", lyrics.markers.len()), |ui| {
+            let mut to_remove = None;
+            egui::Grid::new("markers_grid").show(ui, |ui| {
+                for (index, marker) in lyrics.markers.iter_mut().enumerate() {
+                    ui.text_edit_singleline(&mut marker.label);
+                    let mut begin_ms = marker.begin.num_milliseconds();
+                    let mut end_ms = marker.end.num_milliseconds();
+                    ui.add(
+                        egui::DragValue::new(&mut begin_ms)
+                            .speed(10)
+                            .range(0..=end_ms)
+                            .suffix(" ms"),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut end_ms)
+                            .speed(10)
+                            .range(begin_ms..=end_limit.max(begin_ms))
+                            .suffix(" ms"),
+                    );
+                    marker.begin = Duration::milliseconds(begin_ms);
+                    marker.end = Duration::milliseconds(end_ms);
+                    if ui
+                        .button(icons::material_design_icons::MDI_DELETE)
+                        .on_hover_text("Remove marker")
+                        .clicked()
+                    {
+                        to_remove = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(index) = to_remove {
+                lyrics.markers.remove(index);
+            }
+            if ui
+                .button("Add marker at playhead")
+                .on_hover_text("Mark an intro, interlude or solo starting at the playhead")
+                .clicked()
+            {
+                lyrics.markers.push(Marker {
+                    label: "Instrumental".to_string(),
+                    begin: player_timestamp,
+                    end: player_timestamp + Duration::seconds(DEFAULT_MARKER_LENGTH),
+                });
+                lyrics.markers.sort_by_key(|marker| marker.begin);
+            }
+        });
+    }
+
+    /// Shows the tools to find the silences in the audio and create a line for each phrase
+    /// between them.
+    fn show_silence_detection(&mut self, ui: &mut egui::Ui, audio_data: Option<&[u8]>) {
+        if let Ok(result) = self.silence_rx.try_recv() {
+            self.detecting_silences = false;
+            match result {
+                Ok(regions) => self.proposed_lines = regions,
+                Err(e) => self.lyrics_error = Some(e),
+            }
+        }
+
+        ui.collapsing("Detect silences", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Threshold");
+                ui.add(
+                    egui::DragValue::new(&mut self.silence_threshold_db)
+                        .speed(0.5)
+                        .range(-90.0..=0.0)
+                        .suffix(" dB"),
+                );
+                ui.label("Minimum length");
+                ui.add(
+                    egui::DragValue::new(&mut self.silence_min_length_ms)
+                        .speed(10)
+                        .range(10..=10_000)
+                        .suffix(" ms"),
+                );
+                let can_detect = audio_data.is_some() && !self.detecting_silences;
+                if ui
+                    .add_enabled(can_detect, egui::Button::new("Detect"))
+                    .on_disabled_hover_text("Load an audio file in the player first")
+                    .clicked()
+                {
+                    self.detecting_silences = true;
+                    let data = audio_data.unwrap().to_vec();
+                    let threshold_db = self.silence_threshold_db;
+                    let min_length = Duration::milliseconds(self.silence_min_length_ms);
+                    let silence_tx = self.silence_tx.clone();
+                    tokio::spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || {
+                            let audio = DecodedAudio::decode(data)?;
+                            let silences = audio.detect_silences(threshold_db, min_length);
+                            Ok::<_, anyhow::Error>(audio.voiced_regions(&silences))
+                        })
+                        .await;
+                        let result = match result {
+                            Ok(Ok(regions)) => Ok(regions),
+                            Ok(Err(e)) => Err(format!("Failed to analyze audio: {}", e)),
+                            Err(e) => Err(format!("Failed to analyze audio: {}", e)),
+                        };
+                        let _ = silence_tx.send(result).await;
+                    });
+                }
+                if self.detecting_silences {
+                    ui.spinner();
+                }
+            });
+
+            if self.proposed_lines.is_empty() {
+                return;
+            }
+            ui.label(format!("{} phrases found", self.proposed_lines.len()));
+            egui::ScrollArea::vertical()
+                .id_source("proposed_lines")
+                .max_height(120.0)
+                .show(ui, |ui| {
+                    for (begin, end) in &self.proposed_lines {
+                        ui.label(format!(
+                            "{} - {}",
+                            format_timestamp(*begin),
+                            format_timestamp(*end)
+                        ));
+                    }
+                });
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Create lines")
+                    .on_hover_text("Add an empty line for each phrase")
+                    .clicked()
+                {
+                    self.create_lines(&self.proposed_lines.clone());
+                    self.proposed_lines.clear();
+                }
+                if ui.button("Discard").clicked() {
+                    self.proposed_lines.clear();
+                }
+            });
+        });
+    }
+
+    /// Adds an empty line for each `(begin, end)` span, keeping the lines in time order.
+    fn create_lines(&mut self, spans: &[(Duration, Duration)]) {
+        let lyrics = self.lyrics.get_or_insert_with(|| BabelLyrics {
+            version: BABEL_LYRICS_VERSION,
+            metadata: LyricsMetadata {
+                agents: Vec::new(),
+                translations: Vec::new(),
+            },
+            lyrics: Lyrics { lines: Vec::new() },
+            markers: Vec::new(),
+            direction: TextDirection::default(),
+        });
+        let translations: Vec<(Uuid, Vec<String>)> = lyrics
+            .metadata
+            .translations
+            .iter()
+            .map(|entry| (entry.id, Vec::new()))
+            .collect();
+        for (begin, end) in spans {
+            lyrics.lyrics.lines.push(LyricsLine {
+                begin: *begin,
+                end: *end,
+                agent_id: String::new(),
+                original: Vec::new(),
+                translations: translations.clone(),
+                uuid: Uuid::new_v4(),
+                note: String::new(),
+                disabled: false,
+            });
+        }
+        lyrics.lyrics.lines.sort_by_key(|line| line.begin);
+    }
+
+    fn show_lyrics_file_details_grid(&mut self, ui: &mut egui::Ui) {
         egui::Grid::new("lyrics_editor_file_details_grid").show(ui, |ui| {
             ui.label("File name");
             ui.label(self.file_name.as_deref().unwrap_or("-"));
@@ -266,14 +1461,142 @@ impl LyricsEditor {
                 "-".to_string()
             });
             ui.end_row();
+
+            if let Some(ref mut lyrics) = self.lyrics {
+                ui.label("Text direction").on_hover_text(
+                    "How lines are laid out in the lyrics and captions windows. Auto reads lines \
+                     that start in Arabic, Hebrew or another right-to-left script from right to \
+                     left",
+                );
+                egui::ComboBox::from_id_source("lyrics_editor_text_direction")
+                    .selected_text(lyrics.direction.label())
+                    .show_ui(ui, |ui| {
+                        for direction in [
+                            TextDirection::Auto,
+                            TextDirection::LeftToRight,
+                            TextDirection::RightToLeft,
+                            TextDirection::Vertical,
+                        ] {
+                            ui.selectable_value(
+                                &mut lyrics.direction,
+                                direction,
+                                direction.label(),
+                            );
+                        }
+                    });
+                ui.end_row();
+            }
         });
     }
+
+    /// Shows counts of the lines, segments and translations being edited, and how much of the
+    /// audio of length `duration` the timed segments cover.
+    fn show_stats(&self, ui: &mut egui::Ui, duration: Option<Duration>) {
+        let Some(ref lyrics) = self.lyrics else {
+            return;
+        };
+        ui.collapsing("Statistics", |ui| {
+            let stats = lyrics.stats();
+            egui::Grid::new("lyrics_editor_stats_grid").show(ui, |ui| {
+                ui.label("Lines");
+                ui.label(stats.lines.to_string());
+                ui.end_row();
+
+                ui.label("Segments");
+                ui.label(stats.segments.to_string());
+                ui.end_row();
+
+                ui.label("Untimed segments");
+                if stats.untimed_segments > 0 {
+                    ui.colored_label(MfColors::YELLOW_500, stats.untimed_segments.to_string())
+                        .on_hover_text("Segments whose end is not after their beginning");
+                } else {
+                    ui.label("0");
+                }
+                ui.end_row();
+
+                for (language, lines) in &stats.translated_lines {
+                    ui.label(format!("Translated to {}", language));
+                    ui.label(format!("{} of {} lines", lines, stats.lines));
+                    ui.end_row();
+                }
+
+                ui.label("Timed coverage");
+                ui.label(match duration {
+                    Some(duration) if duration > Duration::zero() => format!(
+                        "{:.1}% ({} of {})",
+                        stats.timed.num_milliseconds() as f64 / duration.num_milliseconds() as f64
+                            * 100.0,
+                        format_timestamp(stats.timed),
+                        format_timestamp(duration)
+                    ),
+                    _ => format!("{} (audio not loaded)", format_timestamp(stats.timed)),
+                });
+                ui.end_row();
+            });
+        });
+    }
+}
+
+/// Settings of the editor that change how the segments and translations of a line are edited.
+#[derive(Clone, Copy)]
+struct SegmentEditOptions {
+    typed_timestamps: bool,
+    edit_spaces: bool,
+    collapse_spaces: bool,
+}
+
+/// Whether `text` is made only of spaces, as the segments and translation words between words
+/// are.
+fn is_space(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c == ' ')
+}
+
+/// Replaces every run of spaces in `text` with a single space.
+fn collapse_spaces(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !(c == ' ' && collapsed.ends_with(' ')) {
+            collapsed.push(c);
+        }
+    }
+    collapsed
+}
+
+/// A label with `text`, showing text made only of spaces as "(space)".
+fn space_aware_label(ui: &mut egui::Ui, text: &str) -> egui::Response {
+    if is_space(text) {
+        ui.label(RichText::new("(space)").color(MfColors::GRAY_500))
+    } else {
+        ui.label(text)
+    }
+}
+
+/// A single-line editor for a segment or translation word, collapsing spaces as it is edited
+/// if `options` say so. Text made only of spaces is shown as "(space)" instead, unless spaces
+/// are edited.
+fn space_aware_text_edit(
+    ui: &mut egui::Ui,
+    text: &mut String,
+    id: egui::Id,
+    options: SegmentEditOptions,
+) -> egui::Response {
+    if is_space(text) && !options.edit_spaces {
+        return space_aware_label(ui, text)
+            .on_hover_text("Turn on \"Edit spaces\" to change this space");
+    }
+    let response = ui.add(egui::TextEdit::singleline(text).id(id));
+    if response.changed() && options.collapse_spaces {
+        *text = collapse_spaces(text);
+    }
+    response
 }
 
 fn show_line_translations(
     ui: &mut egui::Ui,
     line: &mut LyricsLine,
     translation_language_map: &std::collections::HashMap<Uuid, String>,
+    options: SegmentEditOptions,
 ) {
     ui.collapsing("Translation", |ui| {
         for line_translation_pair in &mut line.translations {
@@ -285,6 +1608,20 @@ fn show_line_translations(
                 .id_source(format!("{}_{}", line.uuid, line_translation_pair.0))
                 .default_open(false)
                 .show(ui, |ui| {
+                    if ui
+                        .button("Map words 1:1")
+                        .on_hover_text(
+                            "Associate each translation word with the original segment at the \
+                             same position, replacing the current mapping",
+                        )
+                        .clicked()
+                    {
+                        map_translation_one_to_one(
+                            &mut line.original,
+                            line_translation_pair.0,
+                            &line_translation_pair.1,
+                        );
+                    }
                     egui::Grid::new(format!("translation_grid_{}", line_translation_pair.0))
                         .striped(true)
                         .show(ui, |ui| {
@@ -300,13 +1637,17 @@ fn show_line_translations(
                                     {
                                         to_delete.push(index);
                                     }
-                                    if translation_word == " " {
-                                        ui.label(
-                                            RichText::new("(space)").color(MfColors::GRAY_500),
-                                        );
-                                    } else {
-                                        ui.add(egui::TextEdit::singleline(translation_word));
-                                    }
+                                    space_aware_text_edit(
+                                        ui,
+                                        translation_word,
+                                        egui::Id::new((
+                                            "translation_word",
+                                            line.uuid,
+                                            line_translation_pair.0,
+                                            index,
+                                        )),
+                                        options,
+                                    );
                                 });
                             }
                             for index in to_delete.iter().rev() {
@@ -331,11 +1672,7 @@ fn show_line_translations(
                             }
                             ui.end_row();
                             for segment in line.original.iter_mut() {
-                                if segment.text == " " {
-                                    ui.label(RichText::new("(space)").color(MfColors::GRAY_500));
-                                } else {
-                                    ui.label(segment.text.as_str());
-                                }
+                                space_aware_label(ui, &segment.text);
                                 for (word_index, _) in
                                     line_translation_pair.1.iter_mut().enumerate()
                                 {
@@ -364,24 +1701,126 @@ fn show_line_translations(
     });
 }
 
+/// Maps the n-th non-space segment to the n-th non-space word of the translation `id`.
+/// Segments beyond the last word are left without a translation word.
+fn map_translation_one_to_one(segments: &mut [LyricsSegment], id: Uuid, words: &[String]) {
+    let mut word_indices = words
+        .iter()
+        .enumerate()
+        .filter(|(_, word)| !word.trim().is_empty())
+        .map(|(index, _)| index);
+    for segment in segments {
+        let mapped = if segment.text.trim().is_empty() {
+            Vec::new()
+        } else {
+            word_indices.next().into_iter().collect()
+        };
+        match segment.translations.iter_mut().find(|(x, _)| *x == id) {
+            Some((_, segment_word_indices)) => *segment_word_indices = mapped,
+            None => segment.translations.push((id, mapped)),
+        }
+    }
+}
+
 fn show_segment_edit_grid(
     line: &mut LyricsLine,
     ui: &mut egui::Ui,
     empty_translations_usize: Vec<(Uuid, Vec<usize>)>,
+    player_timestamp: Duration,
+    selection: &mut SegmentSelection,
+    armed_segment: Option<usize>,
+    options: SegmentEditOptions,
 ) {
+    selection
+        .indices
+        .retain(|index| *index < line.original.len());
+    ui.horizontal(|ui| {
+        if ui.button("Select all").clicked() {
+            selection.indices = (0..line.original.len()).collect();
+        }
+        if ui.button("Select none").clicked() {
+            selection.indices.clear();
+        }
+        ui.add_enabled_ui(!selection.indices.is_empty(), |ui| {
+            if ui
+                .button(format!(
+                    "{} Delete selected",
+                    icons::material_design_icons::MDI_DELETE
+                ))
+                .clicked()
+            {
+                for index in selection.indices.iter().rev() {
+                    line.original.remove(*index);
+                }
+                selection.indices.clear();
+            }
+            ui.add(
+                egui::DragValue::new(&mut selection.shift_ms)
+                    .speed(10)
+                    .suffix(" ms"),
+            );
+            if ui
+                .button("Shift selected")
+                .on_hover_text("Move the selected segments by this amount")
+                .clicked()
+            {
+                let shift = Duration::milliseconds(selection.shift_ms);
+                for index in &selection.indices {
+                    let segment = &mut line.original[*index];
+                    // Keep the segment intact when shifting it before the start.
+                    let shift = shift.max(-segment.begin);
+                    segment.begin += shift;
+                    segment.end += shift;
+                }
+            }
+        });
+    });
+
     let mut to_remove = Vec::<usize>::new();
     let mut to_insert = Vec::<usize>::new();
+    let mut to_duplicate = Vec::<usize>::new();
+    let mut to_split = Vec::<(usize, usize)>::new();
+    let mut to_merge = Vec::<usize>::new();
     let mut to_move = Vec::<(usize, usize)>::new();
-    egui::Grid::new(format!("grid_{}", line.uuid)).show(ui, |ui| {
+    let mut to_reorder = None::<(usize, usize)>;
+    let mut row_rects = Vec::<egui::Rect>::new();
+    let grid = egui::Grid::new(format!("grid_{}", line.uuid)).show(ui, |ui| {
         let mut size = ui.spacing().interact_size;
         size.x = 200.0;
+        ui.label("");
         ui.label("Options");
         ui.label("Start");
         ui.label("End");
         ui.label("Text");
+        ui.label("Romanization");
+        ui.label("Reading")
+            .on_hover_text("Ruby text shown above the segment, e.g. furigana");
+        ui.label("Style");
+        ui.label("Review");
         ui.end_row();
         let word_count = line.original.len();
         for (index, seg) in line.original.iter_mut().enumerate() {
+            let text_edit_id = egui::Id::new(("segment_text", line.uuid, index));
+            let mut selected = selection.indices.contains(&index);
+            let first_cell = ui.horizontal(|ui| {
+                ui.dnd_drag_source(
+                    egui::Id::new(("segment_drag", line.uuid, index)),
+                    SegmentDrag {
+                        line: line.uuid,
+                        index,
+                    },
+                    |ui| ui.label(icons::material_design_icons::MDI_DRAG_VERTICAL),
+                )
+                .response
+                .on_hover_text("Drag to reorder");
+                if ui.checkbox(&mut selected, "").changed() {
+                    if selected {
+                        selection.indices.insert(index);
+                    } else {
+                        selection.indices.remove(&index);
+                    }
+                }
+            });
             ui.horizontal(|ui| {
                 if ui
                     .button(icons::material_design_icons::MDI_DELETE)
@@ -392,6 +1831,35 @@ fn show_segment_edit_grid(
                 if ui.button(icons::material_design_icons::MDI_PLUS).clicked() {
                     to_insert.push(index + 1);
                 }
+                if ui
+                    .button(icons::material_design_icons::MDI_CONTENT_COPY)
+                    .on_hover_text("Duplicate segment")
+                    .clicked()
+                {
+                    to_duplicate.push(index);
+                }
+                let can_split = seg.begin < player_timestamp && player_timestamp < seg.end;
+                if ui
+                    .add_enabled(
+                        can_split,
+                        egui::Button::new(icons::material_design_icons::MDI_CALL_SPLIT),
+                    )
+                    .on_hover_text("Split at the playhead")
+                    .on_disabled_hover_text("Move the playhead inside this segment to split it")
+                    .clicked()
+                {
+                    to_split.push((index, split_char_index(ui.ctx(), &seg.text, text_edit_id)));
+                }
+                if ui
+                    .add_enabled(
+                        index != word_count - 1,
+                        egui::Button::new(icons::material_design_icons::MDI_CALL_MERGE),
+                    )
+                    .on_hover_text("Merge with next")
+                    .clicked()
+                {
+                    to_merge.push(index);
+                }
                 if index != 0
                     && ui
                         .button(icons::material_design_icons::MDI_ARROW_UP)
@@ -407,68 +1875,62 @@ fn show_segment_edit_grid(
                     to_move.push((index, index + 1));
                 }
             });
-            ui.horizontal(|ui| {
-                let mut minutes = seg.begin.num_minutes();
-                let mut seconds = (seg.begin.num_seconds() % 60) as u32;
-                let mut milliseconds = (seg.begin.num_milliseconds() % 1000) as u32;
-                ui.add(
-                    egui::DragValue::new(&mut minutes)
-                        .speed(1)
-                        .range(0..=59)
-                        .suffix("m"),
-                );
-                ui.add(
-                    egui::DragValue::new(&mut seconds)
-                        .speed(1)
-                        .range(0..=59)
-                        .suffix("s"),
-                );
-                ui.add(
-                    egui::DragValue::new(&mut milliseconds)
-                        .speed(1)
-                        .range(0..=999),
-                );
-
-                seg.begin = Duration::milliseconds(
-                    minutes * 60 * 1000 + seconds as i64 * 1000 + milliseconds as i64,
-                );
-            });
-            ui.horizontal(|ui| {
-                let mut minutes = seg.end.num_minutes();
-                let mut seconds = (seg.end.num_seconds() % 60) as u32;
-                let mut milliseconds = (seg.end.num_milliseconds() % 1000) as u32;
-                ui.add(
-                    egui::DragValue::new(&mut minutes)
-                        .speed(1)
-                        .range(0..=59)
-                        .suffix("m"),
-                );
-                ui.add(
-                    egui::DragValue::new(&mut seconds)
-                        .speed(1)
-                        .range(0..=59)
-                        .suffix("s"),
-                );
-                ui.add(
-                    egui::DragValue::new(&mut milliseconds)
-                        .speed(1)
-                        .range(0..=999),
-                );
-
-                seg.end = Duration::milliseconds(
-                    minutes * 60 * 1000 + seconds as i64 * 1000 + milliseconds as i64,
-                );
+            timestamp_edit(ui, &mut seg.begin, options.typed_timestamps);
+            timestamp_edit(ui, &mut seg.end, options.typed_timestamps);
+            let text_response = ui.add_sized(size, |ui: &mut egui::Ui| {
+                space_aware_text_edit(ui, &mut seg.text, text_edit_id, options)
             });
-            if seg.text == " " {
-                ui.label(RichText::new("(space)").color(MfColors::GRAY_500));
-            } else {
-                ui.add_sized(size, |ui: &mut egui::Ui| {
-                    ui.text_edit_singleline(&mut seg.text)
-                });
+            if armed_segment == Some(index) {
+                outline_armed_segment(ui, &text_response);
             }
+            ui.add_sized(size, |ui: &mut egui::Ui| {
+                ui.text_edit_singleline(&mut seg.romanization)
+            });
+            ui.add_sized(size, |ui: &mut egui::Ui| {
+                ui.text_edit_singleline(&mut seg.ruby)
+            });
+            segment_style_menu(ui, &mut seg.style);
+            let last_cell = ui
+                .checkbox(&mut seg.needs_review, "")
+                .on_hover_text("Flag this segment for review");
+            row_rects.push(first_cell.response.rect.union(last_cell.rect));
             ui.end_row();
         }
     });
+    if let Some(to) = segment_drop_target(ui, grid.response.rect, &row_rects) {
+        if let Some(drag) = egui::DragAndDrop::payload::<SegmentDrag>(ui.ctx()) {
+            if drag.line == line.uuid {
+                if ui.input(|i| i.pointer.any_released()) {
+                    egui::DragAndDrop::clear_payload(ui.ctx());
+                    // Removing the segment first shifts the rows after it up by one.
+                    let to = if to > drag.index { to - 1 } else { to };
+                    if to != drag.index {
+                        to_reorder = Some((drag.index, to));
+                    }
+                } else {
+                    let y = row_rects
+                        .get(to)
+                        .map_or_else(|| row_rects[to - 1].bottom(), |rect| rect.top());
+                    ui.painter().hline(
+                        grid.response.rect.x_range(),
+                        y,
+                        egui::Stroke::new(2.0, MfColors::BLUE_400),
+                    );
+                }
+            }
+        }
+    }
+    if !(to_remove.is_empty()
+        && to_insert.is_empty()
+        && to_duplicate.is_empty()
+        && to_split.is_empty()
+        && to_merge.is_empty()
+        && to_move.is_empty()
+        && to_reorder.is_none())
+    {
+        // The indices of the selection no longer match after changing the segments.
+        selection.indices.clear();
+    }
     for index in to_remove.iter().rev() {
         line.original.remove(*index);
     }
@@ -480,18 +1942,289 @@ fn show_segment_edit_grid(
                 end: Duration::zero(),
                 text: String::new(),
                 translations: empty_translations_usize.clone(),
+                needs_review: false,
+                romanization: String::new(),
+                ruby: String::new(),
+                style: SegmentStyle::default(),
             },
         );
     }
+    for (index, char_index) in to_split.iter().rev() {
+        let segment = &mut line.original[*index];
+        let byte_index = segment
+            .text
+            .char_indices()
+            .nth(*char_index)
+            .map_or(segment.text.len(), |(i, _)| i);
+        // Both halves keep the translation words of the original segment.
+        let second = LyricsSegment {
+            begin: player_timestamp,
+            text: segment.text.split_off(byte_index),
+            ..segment.clone()
+        };
+        segment.end = player_timestamp;
+        line.original.insert(*index + 1, second);
+    }
+    for index in to_merge.iter().rev() {
+        let next = line.original.remove(*index + 1);
+        let segment = &mut line.original[*index];
+        segment.text.push_str(&next.text);
+        segment.romanization.push_str(&next.romanization);
+        segment.ruby.push_str(&next.ruby);
+        segment.begin = segment.begin.min(next.begin);
+        segment.end = segment.end.max(next.end);
+        segment.needs_review |= next.needs_review;
+        for (id, word_indices) in next.translations {
+            match segment.translations.iter_mut().find(|(x, _)| *x == id) {
+                Some((_, existing)) => {
+                    for word_index in word_indices {
+                        if !existing.contains(&word_index) {
+                            existing.push(word_index);
+                        }
+                    }
+                }
+                None => segment.translations.push((id, word_indices)),
+            }
+        }
+    }
+    for index in to_duplicate.iter().rev() {
+        let segment = &line.original[*index];
+        let segment = shift_segment(segment, segment.end - segment.begin);
+        line.original.insert(*index + 1, segment);
+    }
     for (from, to) in to_move.iter() {
         line.original.swap(*from, *to);
     }
+    // Each segment carries the indices of its translation words, so they move along with it.
+    if let Some((from, to)) = to_reorder {
+        let segment = line.original.remove(from);
+        line.original.insert(to, segment);
+    }
+}
+
+/// A segment row being dragged to another position in the segment grid of its line.
+struct SegmentDrag {
+    line: Uuid,
+    index: usize,
+}
+
+/// Where a segment row dropped at the pointer would be inserted among the rows at `row_rects`,
+/// as the index of the row it would go before, if a segment is being dragged over the grid.
+fn segment_drop_target(
+    ui: &egui::Ui,
+    grid_rect: egui::Rect,
+    row_rects: &[egui::Rect],
+) -> Option<usize> {
+    if !egui::DragAndDrop::has_payload_of_type::<SegmentDrag>(ui.ctx()) {
+        return None;
+    }
+    let pointer = ui.ctx().pointer_interact_pos()?;
+    if !grid_rect.contains(pointer) {
+        return None;
+    }
+    let row = row_rects
+        .iter()
+        .position(|rect| pointer.y < rect.bottom())?;
+    Some(if pointer.y < row_rects[row].center().y {
+        row
+    } else {
+        row + 1
+    })
+}
+
+/// Returns where to split the text of a segment: at the caret of its text box if the caret is
+/// strictly inside the text, or in the middle otherwise.
+fn split_char_index(ctx: &egui::Context, text: &str, text_edit_id: egui::Id) -> usize {
+    let char_count = text.chars().count();
+    egui::TextEdit::load_state(ctx, text_edit_id)
+        .and_then(|state| state.cursor.char_range())
+        .map(|range| range.primary.index)
+        .filter(|index| *index > 0 && *index < char_count)
+        .unwrap_or(char_count / 2)
+}
+
+/// A button opening the style of a segment for editing, highlighted when the segment has one.
+fn segment_style_menu(ui: &mut egui::Ui, style: &mut SegmentStyle) {
+    let icon = RichText::new(icons::material_design_icons::MDI_FORMAT_FONT);
+    let icon = if style.is_plain() {
+        icon
+    } else {
+        icon.color(MfColors::ORANGE_500)
+    };
+    ui.menu_button(icon, |ui| {
+        ui.horizontal(|ui| {
+            ui.toggle_value(
+                &mut style.bold,
+                icons::material_design_icons::MDI_FORMAT_BOLD,
+            )
+            .on_hover_text("Bold");
+            ui.toggle_value(
+                &mut style.italic,
+                icons::material_design_icons::MDI_FORMAT_ITALIC,
+            )
+            .on_hover_text("Italic");
+        });
+        ui.add(
+            egui::Slider::new(&mut style.size, SEGMENT_SIZE_RANGE)
+                .text("Size")
+                .custom_formatter(|size, _| format!("{:.0}%", size * 100.0)),
+        );
+        ui.horizontal(|ui| {
+            let mut custom_color = style.color.is_some();
+            ui.checkbox(&mut custom_color, "Color");
+            if custom_color {
+                let mut color = style.color.unwrap_or([255, 255, 255]);
+                ui.color_edit_button_srgb(&mut color);
+                style.color = Some(color);
+            } else {
+                style.color = None;
+            }
+        });
+        if ui.button("Reset").clicked() {
+            *style = SegmentStyle::default();
+        }
+    })
+    .response
+    .on_hover_text("Style");
+}
+
+/// Clones a segment with its timings moved later by `offset`.
+fn shift_segment(segment: &LyricsSegment, offset: Duration) -> LyricsSegment {
+    LyricsSegment {
+        begin: segment.begin + offset,
+        end: segment.end + offset,
+        ..segment.clone()
+    }
+}
+
+/// The `(begin, end)` of every segment, by the uuid of its line.
+fn segment_timings(lyrics: &BabelLyrics) -> HashMap<Uuid, Vec<(Duration, Duration)>> {
+    lyrics
+        .lyrics
+        .lines
+        .iter()
+        .map(|line| {
+            let timings = line
+                .original
+                .iter()
+                .map(|segment| (segment.begin, segment.end))
+                .collect();
+            (line.uuid, timings)
+        })
+        .collect()
+}
+
+/// Snaps the segment boundaries that differ from `before` to a zero-crossing of `audio`,
+/// searching in the direction they moved so that dragging can get past a crossing.
+///
+/// Lines whose segments were added or removed are left alone, as their segments no longer
+/// line up with `before`.
+fn snap_edited_timings(
+    lyrics: &mut BabelLyrics,
+    before: &HashMap<Uuid, Vec<(Duration, Duration)>>,
+    audio: &DecodedAudio,
+) {
+    for line in &mut lyrics.lyrics.lines {
+        let Some(timings) = before.get(&line.uuid) else {
+            continue;
+        };
+        if timings.len() != line.original.len() {
+            continue;
+        }
+        for (segment, (begin, end)) in line.original.iter_mut().zip(timings) {
+            for (time, previous) in [(&mut segment.begin, begin), (&mut segment.end, end)] {
+                if *time != *previous {
+                    if let Some(crossing) = audio.zero_crossing(*time, *time > *previous) {
+                        *time = crossing;
+                    }
+                }
+            }
+            segment.end = segment.end.max(segment.begin);
+        }
+    }
+}
+
+/// Clones a line with a fresh uuid, placed right after the original by offsetting all its
+/// timings by the duration of the line.
+fn duplicate_line(line: &LyricsLine) -> LyricsLine {
+    let offset = line.end - line.begin;
+    LyricsLine {
+        begin: line.begin + offset,
+        end: line.end + offset,
+        original: line
+            .original
+            .iter()
+            .map(|segment| shift_segment(segment, offset))
+            .collect(),
+        uuid: Uuid::new_v4(),
+        ..line.clone()
+    }
+}
+
+/// Edits a segment timestamp of up to 59:59.999, either as a single `mm:ss.mmm` field that can
+/// be typed in, or as separate minutes, seconds and milliseconds.
+fn timestamp_edit(ui: &mut egui::Ui, time: &mut Duration, typed: bool) {
+    if typed {
+        let mut milliseconds = time.num_milliseconds();
+        ui.add(
+            egui::DragValue::new(&mut milliseconds)
+                .speed(1)
+                .range(0..=MAX_TIMESTAMP_MS)
+                .custom_formatter(|n, _| format_timestamp(Duration::milliseconds(n as i64)))
+                .custom_parser(|text| {
+                    parse_timestamp(text)
+                        .ok()
+                        .map(|time| time.num_milliseconds() as f64)
+                }),
+        )
+        .on_hover_text("Click to type a timestamp as mm:ss.mmm");
+        *time = Duration::milliseconds(milliseconds);
+        return;
+    }
+    ui.horizontal(|ui| {
+        let mut minutes = time.num_minutes();
+        let mut seconds = (time.num_seconds() % 60) as u32;
+        let mut milliseconds = (time.num_milliseconds() % 1000) as u32;
+        ui.add(
+            egui::DragValue::new(&mut minutes)
+                .speed(1)
+                .range(0..=59)
+                .suffix("m"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut seconds)
+                .speed(1)
+                .range(0..=59)
+                .suffix("s"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut milliseconds)
+                .speed(1)
+                .range(0..=999),
+        );
+
+        *time = Duration::milliseconds(
+            minutes * 60 * 1000 + seconds as i64 * 1000 + milliseconds as i64,
+        );
+    });
+}
+
+async fn save_text_file(filter_name: &str, extension: &str, contents: String) {
+    let file = rfd::FileDialog::new()
+        .add_filter(filter_name, &[extension])
+        .save_file();
+    if let Some(path) = file {
+        if let Err(e) = tokio::fs::write(&path, contents).await {
+            eprintln!("Failed to write file: {}", e);
+        }
+    }
 }
 
 async fn ttml_lyrics_file_loader(
     arc_loading_file: Arc<Mutex<bool>>,
     details_tx: mpsc::Sender<(Option<String>, Option<String>)>,
     data_tx: mpsc::Sender<BabelLyrics>,
+    error_tx: mpsc::Sender<String>,
 ) {
     let file = rfd::FileDialog::new()
         .add_filter("TTML Lyrics", &["ttml"])
@@ -501,22 +2234,22 @@ async fn ttml_lyrics_file_loader(
         *arc_loading_file.lock().unwrap() = true;
         let path_str = path.to_string_lossy().to_string();
         let file_name_str = path.file_name().unwrap().to_string_lossy().to_string();
-        match std::fs::File::open(&path) {
-            Ok(file) => {
-                let _ = details_tx.send((Some(path_str), Some(file_name_str))).await;
-                let f = std::io::BufReader::new(file);
-                match ttml::parse_ttml(f) {
-                    Ok(ttml_lyrics) => {
-                        let babel_lyrics = parse_ttml_lyrics(ttml_lyrics);
-                        let _ = data_tx.send(babel_lyrics).await;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to parse ttml: {}", e);
-                    }
+        match tokio::fs::read_to_string(&path).await {
+            Ok(ttml) => match convert::ttml_to_babel(&ttml) {
+                Ok(babel_lyrics) => {
+                    let _ = details_tx.send((Some(path_str), Some(file_name_str))).await;
+                    let _ = data_tx.send(babel_lyrics).await;
                 }
-            }
+                Err(e) => {
+                    let _ = error_tx
+                        .send(format!("Failed to parse {}: {}", file_name_str, e))
+                        .await;
+                }
+            },
             Err(e) => {
-                eprintln!("Failed to open file: {}", e);
+                let _ = error_tx
+                    .send(format!("Failed to open {}: {}", file_name_str, e))
+                    .await;
             }
         }
     }
@@ -526,64 +2259,110 @@ pub async fn json_lyrics_file_loader(
     arc_loading_file: Arc<Mutex<bool>>,
     details_tx: mpsc::Sender<(Option<String>, Option<String>)>,
     data_tx: mpsc::Sender<BabelLyrics>,
+    error_tx: mpsc::Sender<String>,
 ) {
     let file = rfd::FileDialog::new()
         .add_filter("JSON", &["json"])
         .pick_file();
 
     if let Some(path) = file {
-        *arc_loading_file.lock().unwrap() = true;
-        let path_str = path.to_string_lossy().to_string();
-        let file_name_str = path.file_name().unwrap().to_string_lossy().to_string();
-        match std::fs::File::open(&path) {
-            Ok(file) => {
+        load_json_lyrics_file(path, arc_loading_file, details_tx, data_tx, error_tx).await;
+    }
+}
+
+/// Loads a Babel lyrics JSON file from `path` without showing a file dialog.
+pub async fn load_json_lyrics_file(
+    path: std::path::PathBuf,
+    arc_loading_file: Arc<Mutex<bool>>,
+    details_tx: mpsc::Sender<(Option<String>, Option<String>)>,
+    data_tx: mpsc::Sender<BabelLyrics>,
+    error_tx: mpsc::Sender<String>,
+) {
+    *arc_loading_file.lock().unwrap() = true;
+    let path_str = path.to_string_lossy().to_string();
+    let file_name_str = path.file_name().unwrap().to_string_lossy().to_string();
+    match tokio::fs::read_to_string(&path).await {
+        Ok(json) => match convert::json_to_babel(&json) {
+            Ok(babel_lyrics) => {
                 let _ = details_tx.send((Some(path_str), Some(file_name_str))).await;
-                let f = std::io::BufReader::new(file);
-                match serde_json::from_reader(f) {
+                let _ = data_tx.send(babel_lyrics).await;
+            }
+            Err(e) => {
+                let _ = error_tx
+                    .send(format!("Failed to load {}: {}", file_name_str, e))
+                    .await;
+            }
+        },
+        Err(e) => {
+            let _ = error_tx
+                .send(format!("Failed to open {}: {}", file_name_str, e))
+                .await;
+        }
+    }
+}
+
+/// Lets the user pick several TTML or Babel lyrics JSON files and loads each of them as a
+/// separate lyric set, sent as `(path, lyrics)`. Files that fail to load are reported on
+/// `error_tx` without stopping the others.
+pub async fn lyrics_sets_loader(
+    arc_loading_file: Arc<Mutex<bool>>,
+    set_tx: mpsc::Sender<(String, BabelLyrics)>,
+    error_tx: mpsc::Sender<String>,
+) {
+    let files = rfd::FileDialog::new()
+        .add_filter("Lyrics", &["json", "ttml"])
+        .pick_files();
+
+    if let Some(paths) = files {
+        *arc_loading_file.lock().unwrap() = true;
+        for path in paths {
+            let path_str = path.to_string_lossy().to_string();
+            let file_name_str = path.file_name().unwrap().to_string_lossy().to_string();
+            match tokio::fs::read(&path).await {
+                Ok(contents) => match convert::parse_lyrics_file(&path, &contents) {
                     Ok(babel_lyrics) => {
-                        let _ = data_tx.send(babel_lyrics).await;
+                        let _ = set_tx.send((path_str, babel_lyrics)).await;
                     }
                     Err(e) => {
-                        eprintln!("Failed to parse json: {}", e);
+                        let _ = error_tx
+                            .send(format!("Failed to load {}: {}", file_name_str, e))
+                            .await;
                     }
+                },
+                Err(e) => {
+                    let _ = error_tx
+                        .send(format!("Failed to open {}: {}", file_name_str, e))
+                        .await;
                 }
             }
-            Err(e) => {
-                eprintln!("Failed to open file: {}", e);
-            }
         }
+        *arc_loading_file.lock().unwrap() = false;
     }
 }
 
-fn parse_ttml_lyrics(ttml_lyrics: TTMLLyric) -> BabelLyrics {
-    let lines = ttml_lyrics.lines;
-    let mut babel_lines = Vec::<LyricsLine>::new();
-    for line in lines {
-        let mut babel_segments = Vec::<LyricsSegment>::new();
-        for segment in line.words {
-            let babel_segment = LyricsSegment {
-                begin: Duration::milliseconds(segment.start_time as i64),
-                end: Duration::milliseconds(segment.end_time as i64),
-                text: segment.word.to_string(),
-                translations: Vec::new(),
-            };
-            babel_segments.push(babel_segment);
-        }
-        let babel_line = LyricsLine {
-            begin: Duration::milliseconds(line.start_time as i64),
-            end: Duration::milliseconds(line.end_time as i64),
-            agent_id: String::new(),
-            original: babel_segments,
-            translations: Vec::new(),
-            uuid: Uuid::new_v4(),
-        };
-        babel_lines.push(babel_line);
-    }
-    BabelLyrics {
-        metadata: LyricsMetadata {
-            agents: Vec::new(),
-            translations: Vec::new(),
-        },
-        lyrics: Lyrics { lines: babel_lines },
-    }
+/// The segments tap timing goes through, as `(line index, segment index)` in order, skipping
+/// blank segments.
+fn tap_targets(lyrics: &BabelLyrics) -> Vec<(usize, usize)> {
+    lyrics
+        .lyrics
+        .lines
+        .iter()
+        .enumerate()
+        .flat_map(|(line_index, line)| {
+            line.original
+                .iter()
+                .enumerate()
+                .filter(|(_, segment)| !segment.text.trim().is_empty())
+                .map(move |(segment_index, _)| (line_index, segment_index))
+        })
+        .collect()
+}
+
+/// Draws the outline marking the segment the next tap times around `response`.
+pub fn outline_armed_segment(ui: &egui::Ui, response: &egui::Response) {
+    ui.painter().rect_stroke(
+        response.rect.expand(2.0),
+        2.0,
+        egui::Stroke::new(2.0, MfColors::ORANGE_500),
+    );
 }
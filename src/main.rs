@@ -1,12 +1,19 @@
 #![warn(clippy::all, rust_2018_idioms)]
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::path::Path;
 use std::time::Duration;
 
+use babel_player::convert;
 use eframe::egui;
 use tokio::runtime::Runtime;
 
 fn main() -> eframe::Result {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("convert") {
+        std::process::exit(run_convert(&args[2..]));
+    }
+
     let rt = Runtime::new().expect("Unable to create Tokio Runtime");
 
     // Enter the runtime so that `tokio::spawn` is available immediately.
@@ -33,3 +40,27 @@ fn main() -> eframe::Result {
         Box::new(|cc| Ok(Box::new(babel_player::BabelPlayerApp::new(cc)))),
     )
 }
+
+/// Runs `babel_player convert <input> <output>` without opening a window, returning the exit
+/// status.
+fn run_convert(args: &[String]) -> i32 {
+    let [input, output] = args else {
+        eprintln!("Usage: babel_player convert <input.ttml|input.lrc|input.json> <output.json>");
+        return 2;
+    };
+    match convert_file(Path::new(input), Path::new(output)) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Failed to convert {}: {}", input, e);
+            1
+        }
+    }
+}
+
+/// Converts TTML, LRC or Babel lyrics at `input` to Babel lyrics JSON at `output`.
+fn convert_file(input: &Path, output: &Path) -> anyhow::Result<()> {
+    let contents = std::fs::read(input)?;
+    let lyrics = convert::parse_lyrics_file(input, &contents)?;
+    std::fs::write(output, convert::babel_to_json(&lyrics)?)?;
+    Ok(())
+}
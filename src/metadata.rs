@@ -0,0 +1,364 @@
+use std::io::Cursor;
+
+use chrono::Duration;
+use eframe::egui::ColorImage;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{
+    MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey, Visual,
+};
+use symphonia::core::probe::Hint;
+
+use crate::convert;
+use crate::lyrics::BabelLyrics;
+
+/// Longest side of the cover art thumbnail, in pixels.
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// Extracts the cover art embedded in the tags of an audio file, scaled down to a thumbnail.
+///
+/// The front cover is preferred when the file has several pictures. Returns `None` if there is
+/// no picture, or if it is in a format that cannot be decoded.
+pub fn cover_art(data: Vec<u8>) -> Option<ColorImage> {
+    let visuals: Vec<Visual> = tag_revisions(data)
+        .iter()
+        .flat_map(|revision| revision.visuals())
+        .cloned()
+        .collect();
+    let visual = visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or(visuals.first())?;
+
+    let image = image::load_from_memory(&visual.data)
+        .ok()?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgba8();
+    Some(ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.as_raw(),
+    ))
+}
+
+/// Extracts synchronized lyrics embedded in the tags of an audio file.
+///
+/// An ID3v2 `SYLT` frame timed in milliseconds is preferred. Failing that, unsynchronized
+/// lyrics (an ID3v2 `USLT` frame or the MP4 lyrics atom) are used if they hold LRC text, as
+/// some taggers store them that way. Returns `None` if the file has no timed lyrics.
+pub fn embedded_lyrics(data: Vec<u8>) -> Option<BabelLyrics> {
+    if let Some(entries) = id3_synced_lyrics(&data) {
+        if let Ok(lyrics) = convert::sylt_to_babel(&entries) {
+            return Some(lyrics);
+        }
+    }
+    tag_revisions(data)
+        .iter()
+        .flat_map(|revision| revision.tags())
+        .filter(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+        .find_map(|tag| convert::lrc_to_babel(&tag.value.to_string()).ok())
+}
+
+/// Reads the current revisions of the tags of an audio file.
+fn tag_revisions(data: Vec<u8>) -> Vec<MetadataRevision> {
+    let stream = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+    let Ok(mut probed) = symphonia::default::get_probe().format(
+        &Hint::new(),
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) else {
+        return Vec::new();
+    };
+
+    // ID3 tags are read while probing, while MP4 tags are part of the container.
+    let mut revisions = Vec::new();
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|log| log.current()) {
+        revisions.push(revision.clone());
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        revisions.push(revision.clone());
+    }
+    revisions
+}
+
+/// Reads the entries of the first `SYLT` frame timed in milliseconds of the ID3v2 tag at the
+/// start of `data`, as `(start, text)`.
+///
+/// Symphonia skips `SYLT` frames, so the tag is walked here. Tags of version 2.3 and 2.4 are
+/// read, except for unsynchronised ones and compressed or encrypted frames.
+fn id3_synced_lyrics(data: &[u8]) -> Option<Vec<(Duration, String)>> {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return None;
+    }
+    let version = data[3];
+    let flags = data[5];
+    if !(3..=4).contains(&version) || flags & 0x80 != 0 {
+        return None;
+    }
+    let tag_end = (10 + syncsafe(&data[6..10])).min(data.len());
+    let frame_size = |bytes: &[u8]| {
+        if version == 4 {
+            syncsafe(bytes)
+        } else {
+            u32::from_be_bytes(bytes.try_into().unwrap()) as usize
+        }
+    };
+
+    let mut position = 10;
+    if flags & 0x40 != 0 {
+        // The extended header counts its own size field in version 2.4 only.
+        let size = frame_size(data.get(10..14)?);
+        position += if version == 4 { size } else { size + 4 };
+    }
+    while position + 10 <= tag_end {
+        let id = &data[position..position + 4];
+        if id[0] == 0 {
+            // Padding.
+            break;
+        }
+        let size = frame_size(&data[position + 4..position + 8]);
+        let format_flags = data[position + 9];
+        // A frame running past the end of the tag means that the tag is truncated.
+        let mut body = data.get(position + 10..position + 10 + size)?;
+        if position + 10 + size > tag_end {
+            return None;
+        }
+        position += 10 + size;
+        if id != b"SYLT" {
+            continue;
+        }
+        let (unsupported, data_length_indicator) = if version == 4 {
+            (format_flags & 0x0e != 0, format_flags & 0x01 != 0)
+        } else {
+            (format_flags & 0xc0 != 0, false)
+        };
+        if unsupported {
+            continue;
+        }
+        if data_length_indicator {
+            body = body.get(4..)?;
+        }
+        if let Some(entries) = parse_sylt(body) {
+            return Some(entries);
+        }
+    }
+    None
+}
+
+/// Parses the body of a `SYLT` frame, if it is timed in milliseconds rather than MPEG frames.
+fn parse_sylt(body: &[u8]) -> Option<Vec<(Duration, String)>> {
+    let (&encoding, rest) = body.split_first()?;
+    // Language (3 bytes), then the timestamp format and content type.
+    let timestamp_format = *rest.get(3)?;
+    if timestamp_format != 2 {
+        return None;
+    }
+    let (_descriptor, mut rest) = split_terminated(rest.get(5..)?, encoding)?;
+    let mut entries = Vec::new();
+    while let Some((text, after)) = split_terminated(rest, encoding) {
+        let Some(time) = after.get(..4) else {
+            break;
+        };
+        let time = u32::from_be_bytes(time.try_into().unwrap());
+        entries.push((
+            Duration::milliseconds(time as i64),
+            decode_text(text, encoding),
+        ));
+        rest = &after[4..];
+    }
+    Some(entries)
+}
+
+/// Splits a string in the given ID3v2 text encoding off the start of `bytes` at its
+/// terminator, returning the string and what follows the terminator.
+fn split_terminated(bytes: &[u8], encoding: u8) -> Option<(&[u8], &[u8])> {
+    if encoding == 1 || encoding == 2 {
+        // UTF-16 strings end with two zero bytes on a code unit boundary.
+        let end = (0..bytes.len().saturating_sub(1))
+            .step_by(2)
+            .find(|&i| bytes[i] == 0 && bytes[i + 1] == 0)?;
+        Some((&bytes[..end], &bytes[end + 2..]))
+    } else {
+        let end = bytes.iter().position(|&byte| byte == 0)?;
+        Some((&bytes[..end], &bytes[end + 1..]))
+    }
+}
+
+/// Decodes a string in the given ID3v2 text encoding: ISO-8859-1, UTF-16 with a byte order
+/// mark, UTF-16BE or UTF-8.
+fn decode_text(bytes: &[u8], encoding: u8) -> String {
+    match encoding {
+        0 => bytes.iter().map(|&byte| byte as char).collect(),
+        1 | 2 => {
+            let (little_endian, bytes) = match bytes {
+                [0xff, 0xfe, rest @ ..] => (true, rest),
+                [0xfe, 0xff, rest @ ..] => (false, rest),
+                _ => (false, bytes),
+            };
+            let units = bytes.chunks_exact(2).map(|pair| {
+                if little_endian {
+                    u16::from_le_bytes([pair[0], pair[1]])
+                } else {
+                    u16::from_be_bytes([pair[0], pair[1]])
+                }
+            });
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+/// Reads a 28-bit ID3v2 "syncsafe" integer, stored in the low seven bits of four bytes.
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |value, &byte| (value << 7) | (byte & 0x7f) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes `value` as a 28-bit syncsafe integer.
+    fn to_syncsafe(value: usize) -> [u8; 4] {
+        [21, 14, 7, 0].map(|shift| ((value >> shift) & 0x7f) as u8)
+    }
+
+    /// An ID3v2 tag of the given version holding `frames`, with an empty extended header if
+    /// `extended` is set.
+    fn tag(version: u8, extended: bool, frames: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = Vec::new();
+        if extended {
+            if version == 4 {
+                body.extend(to_syncsafe(6));
+                body.extend([1, 0]);
+            } else {
+                body.extend(6u32.to_be_bytes());
+                body.extend([0; 6]);
+            }
+        }
+        for (id, frame) in frames {
+            body.extend(*id);
+            if version == 4 {
+                body.extend(to_syncsafe(frame.len()));
+            } else {
+                body.extend((frame.len() as u32).to_be_bytes());
+            }
+            body.extend([0, 0]);
+            body.extend(frame);
+        }
+        let mut data = b"ID3".to_vec();
+        data.extend([version, 0, if extended { 0x40 } else { 0 }]);
+        data.extend(to_syncsafe(body.len()));
+        data.extend(body);
+        data
+    }
+
+    /// The body of a `SYLT` frame with an empty descriptor, whose texts are already encoded
+    /// and terminated.
+    fn sylt(encoding: u8, timestamp_format: u8, entries: &[(Vec<u8>, u32)]) -> Vec<u8> {
+        let mut body = vec![encoding];
+        body.extend(b"eng");
+        body.extend([timestamp_format, 1]);
+        body.extend(if encoding == 1 { &[0, 0][..] } else { &[0][..] });
+        for (text, time) in entries {
+            body.extend(text);
+            body.extend(time.to_be_bytes());
+        }
+        body
+    }
+
+    fn latin1(text: &str) -> Vec<u8> {
+        let mut bytes = text.chars().map(|c| c as u8).collect::<Vec<_>>();
+        bytes.push(0);
+        bytes
+    }
+
+    fn utf16_with_bom(text: &str) -> Vec<u8> {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes.extend([0, 0]);
+        bytes
+    }
+
+    fn entries(entries: &[(i64, &str)]) -> Vec<(Duration, String)> {
+        entries
+            .iter()
+            .map(|(time, text)| (Duration::milliseconds(*time), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_latin1_sylt_from_v23_tag() {
+        // Longer than 127 bytes, so that its size differs between plain and syncsafe integers.
+        let long = "la ".repeat(60);
+        let data = tag(
+            3,
+            false,
+            &[
+                (b"TIT2", vec![0, b'T', b'i', b't', b'l', b'e']),
+                (
+                    b"SYLT",
+                    sylt(0, 2, &[(latin1("Café"), 1_000), (latin1(&long), 2_500)]),
+                ),
+            ],
+        );
+        assert_eq!(
+            id3_synced_lyrics(&data),
+            Some(entries(&[(1_000, "Café"), (2_500, &long)]))
+        );
+    }
+
+    #[test]
+    fn reads_utf16_sylt_after_extended_header() {
+        let long = "ら".repeat(70);
+        for version in [3, 4] {
+            let frame = sylt(
+                1,
+                2,
+                &[(utf16_with_bom("君の"), 0), (utf16_with_bom(&long), 300)],
+            );
+            let data = tag(version, true, &[(b"SYLT", frame)]);
+            assert_eq!(
+                id3_synced_lyrics(&data),
+                Some(entries(&[(0, "君の"), (300, &long)])),
+                "version 2.{}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn skips_sylt_timed_in_mpeg_frames() {
+        let mpeg = sylt(0, 1, &[(latin1("Frames"), 40)]);
+        assert_eq!(
+            id3_synced_lyrics(&tag(4, false, &[(b"SYLT", mpeg.clone())])),
+            None
+        );
+
+        let milliseconds = sylt(0, 2, &[(latin1("Milliseconds"), 40)]);
+        let data = tag(4, false, &[(b"SYLT", mpeg), (b"SYLT", milliseconds)]);
+        assert_eq!(
+            id3_synced_lyrics(&data),
+            Some(entries(&[(40, "Milliseconds")]))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_tags() {
+        let frame = sylt(
+            1,
+            2,
+            &[(utf16_with_bom("Hello"), 0), (utf16_with_bom("world"), 500)],
+        );
+        for version in [3, 4] {
+            let data = tag(version, true, &[(b"SYLT", frame.clone())]);
+            for len in 0..data.len() {
+                assert_eq!(id3_synced_lyrics(&data[..len]), None, "{} bytes", len);
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::Duration;
+use std::time::Instant;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PlayerState {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+/// The playback clock, which tracks the timestamp of the player independently of the sink.
+pub struct PlayerClock {
+    /// Timestamp of the player.
+    ///
+    /// This is equal to `offset` + (`current_instant` - `start_instant`).
+    pub timestamp: Duration,
+
+    /// The instant when the player started/resumed.
+    start_instant: Option<Instant>,
+
+    /// The offset of the timestamp of the player.
+    ///
+    /// This is used to calculate the timestamp after pausing and resuming.
+    offset: Duration,
+
+    pub state: PlayerState,
+
+    /// Playback speed, as a multiple of real time.
+    speed: f64,
+}
+
+impl Default for PlayerClock {
+    fn default() -> Self {
+        PlayerClock {
+            timestamp: Duration::zero(),
+            start_instant: None,
+            offset: Duration::zero(),
+            state: PlayerState::Stopped,
+            speed: 1.0,
+        }
+    }
+}
+
+impl PlayerClock {
+    /// Advances `timestamp` to `now` while playing.
+    pub fn tick(&mut self, now: Instant) {
+        if self.state == PlayerState::Playing {
+            self.timestamp = self.offset
+                + Duration::milliseconds(
+                    self.start_instant
+                        .map(|start_instant| {
+                            ((now - start_instant).as_millis() as f64 * self.speed) as i64
+                        })
+                        .unwrap_or(0),
+                );
+        }
+    }
+
+    /// Starts or resumes playback from `timestamp`.
+    pub fn play(&mut self, now: Instant) {
+        self.state = PlayerState::Playing;
+        self.offset = self.timestamp;
+        self.start_instant = Some(now);
+    }
+
+    pub fn pause(&mut self) {
+        self.state = PlayerState::Paused;
+        self.offset = self.timestamp;
+    }
+
+    /// Halts playback without rewinding.
+    pub fn stop(&mut self) {
+        self.state = PlayerState::Stopped;
+        self.offset = self.timestamp;
+        self.start_instant = None;
+    }
+
+    /// Halts playback and rewinds to the start.
+    pub fn reset(&mut self) {
+        self.state = PlayerState::Stopped;
+        self.timestamp = Duration::zero();
+        self.offset = Duration::zero();
+        self.start_instant = None;
+    }
+
+    /// Moves the clock to `timestamp`, keeping the current state.
+    ///
+    /// Negative timestamps are clamped to zero.
+    pub fn seek(&mut self, timestamp: Duration, now: Instant) {
+        let timestamp = timestamp.max(Duration::zero());
+        self.timestamp = timestamp;
+        self.offset = timestamp;
+        if self.state == PlayerState::Playing {
+            self.start_instant = Some(now);
+        }
+    }
+
+    /// Changes the playback speed, keeping the timestamp reached so far.
+    pub fn set_speed(&mut self, speed: f64, now: Instant) {
+        self.tick(now);
+        self.offset = self.timestamp;
+        if self.state == PlayerState::Playing {
+            self.start_instant = Some(now);
+        }
+        self.speed = speed;
+    }
+
+    /// The position the sink should be at for the current timestamp.
+    ///
+    /// A negative timestamp maps to the start rather than failing the conversion.
+    pub fn sink_position(&self) -> std::time::Duration {
+        self.timestamp
+            .max(Duration::zero())
+            .to_std()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrub_while_paused_then_resume() {
+        let start = Instant::now();
+        let mut clock = PlayerClock::default();
+        clock.play(start);
+        clock.tick(start + std::time::Duration::from_millis(2_000));
+        clock.pause();
+        assert_eq!(clock.timestamp, Duration::milliseconds(2_000));
+
+        clock.seek(Duration::milliseconds(30_000), start);
+        assert_eq!(clock.state, PlayerState::Paused);
+        assert_eq!(clock.timestamp, Duration::milliseconds(30_000));
+        assert_eq!(clock.sink_position(), std::time::Duration::from_secs(30));
+
+        // Time passing while paused must not move the clock.
+        clock.tick(start + std::time::Duration::from_millis(10_000));
+        assert_eq!(clock.timestamp, Duration::milliseconds(30_000));
+
+        let resume = start + std::time::Duration::from_millis(10_000);
+        clock.play(resume);
+        assert_eq!(clock.sink_position(), std::time::Duration::from_secs(30));
+        clock.tick(resume + std::time::Duration::from_millis(500));
+        assert_eq!(clock.timestamp, Duration::milliseconds(30_500));
+    }
+
+    #[test]
+    fn scrub_while_playing_restarts_from_new_position() {
+        let start = Instant::now();
+        let mut clock = PlayerClock::default();
+        clock.play(start);
+        clock.tick(start + std::time::Duration::from_millis(1_000));
+
+        let scrub = start + std::time::Duration::from_millis(1_000);
+        clock.seek(Duration::milliseconds(5_000), scrub);
+        clock.tick(scrub + std::time::Duration::from_millis(250));
+        assert_eq!(clock.timestamp, Duration::milliseconds(5_250));
+    }
+
+    #[test]
+    fn seek_before_start_clamps_to_zero() {
+        let start = Instant::now();
+        let mut clock = PlayerClock::default();
+        clock.play(start);
+        clock.tick(start + std::time::Duration::from_millis(500));
+
+        let scrub = start + std::time::Duration::from_millis(500);
+        clock.seek(Duration::milliseconds(-2_000), scrub);
+        assert_eq!(clock.timestamp, Duration::zero());
+        assert_eq!(clock.sink_position(), std::time::Duration::ZERO);
+
+        clock.tick(scrub + std::time::Duration::from_millis(100));
+        assert_eq!(clock.timestamp, Duration::milliseconds(100));
+    }
+
+    #[test]
+    fn speed_change_keeps_elapsed_time() {
+        let start = Instant::now();
+        let mut clock = PlayerClock::default();
+        clock.play(start);
+
+        let change = start + std::time::Duration::from_millis(1_000);
+        clock.set_speed(0.5, change);
+        assert_eq!(clock.timestamp, Duration::milliseconds(1_000));
+
+        clock.tick(change + std::time::Duration::from_millis(1_000));
+        assert_eq!(clock.timestamp, Duration::milliseconds(1_500));
+    }
+}
@@ -0,0 +1,184 @@
+//! Project archives, which bundle an audio file with its lyrics so that both can be passed on
+//! as a single file.
+//!
+//! A project is a zip archive holding a `manifest.json`, the audio file under `audio/` as it
+//! was loaded, and the lyrics as Babel lyrics JSON in `lyrics.json`.
+
+use std::io::{Cursor, Read, Write};
+
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::convert;
+use crate::lyrics::BabelLyrics;
+
+/// Extension of project archives.
+pub const PROJECT_EXTENSION: &str = "babel";
+
+/// Version of the project layout written by this build.
+const PROJECT_VERSION: u32 = 1;
+
+const MANIFEST_PATH: &str = "manifest.json";
+const LYRICS_PATH: &str = "lyrics.json";
+
+/// Describes the contents of a project archive.
+#[derive(Serialize, Deserialize)]
+struct ProjectManifest {
+    version: u32,
+
+    /// Path of the audio file within the archive.
+    audio: String,
+
+    /// Path of the lyrics within the archive.
+    lyrics: String,
+}
+
+/// An audio file and its lyrics, as stored in a project archive.
+pub struct Project {
+    /// Name of the audio file when it was added to the project.
+    pub audio_file_name: String,
+    pub audio: Vec<u8>,
+    pub lyrics: BabelLyrics,
+}
+
+/// Bundles the audio file named `audio_file_name` with `lyrics` into a project archive.
+pub fn write_project(
+    audio_file_name: &str,
+    audio: &[u8],
+    lyrics: &BabelLyrics,
+) -> anyhow::Result<Vec<u8>> {
+    let manifest = ProjectManifest {
+        version: PROJECT_VERSION,
+        audio: format!("audio/{}", audio_file_name),
+        lyrics: LYRICS_PATH.to_string(),
+    };
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // Audio is compressed already, so it is stored as it is.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file(MANIFEST_PATH, deflated)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.start_file(&manifest.audio, stored)?;
+    zip.write_all(audio)?;
+    zip.start_file(&manifest.lyrics, deflated)?;
+    zip.write_all(convert::babel_to_json(lyrics)?.as_bytes())?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Reads the audio and lyrics out of a project archive, describing what is wrong with it on
+/// failure.
+pub fn read_project(data: &[u8]) -> anyhow::Result<Project> {
+    let mut zip = ZipArchive::new(Cursor::new(data))
+        .map_err(|e| anyhow::anyhow!("not a project archive: {}", e))?;
+    let manifest: ProjectManifest =
+        serde_json::from_slice(&read_entry(&mut zip, MANIFEST_PATH)?)
+            .map_err(|e| anyhow::anyhow!("invalid {}: {}", MANIFEST_PATH, e))?;
+    if manifest.version > PROJECT_VERSION {
+        anyhow::bail!(
+            "the project is version {}, but this version of Babel Player only reads up to {}",
+            manifest.version,
+            PROJECT_VERSION
+        );
+    }
+
+    let audio = read_entry(&mut zip, &manifest.audio)?;
+    let lyrics = read_entry(&mut zip, &manifest.lyrics)?;
+    let lyrics = convert::json_to_babel(std::str::from_utf8(&lyrics)?)
+        .map_err(|e| anyhow::anyhow!("{}: {}", manifest.lyrics, e))?;
+    let audio_file_name = manifest
+        .audio
+        .rsplit('/')
+        .next()
+        .unwrap_or(&manifest.audio)
+        .to_string();
+    Ok(Project {
+        audio_file_name,
+        audio,
+        lyrics,
+    })
+}
+
+fn read_entry(zip: &mut ZipArchive<Cursor<&[u8]>>, path: &str) -> anyhow::Result<Vec<u8>> {
+    let mut entry = zip
+        .by_name(path)
+        .map_err(|e| anyhow::anyhow!("{}: {}", path, e))?;
+    let mut contents = Vec::new();
+    entry.read_to_end(&mut contents)?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lyrics() -> BabelLyrics {
+        convert::lrc_to_babel("[00:01.00]Hello\n[00:02.50]world").unwrap()
+    }
+
+    /// An archive with the given entries, as `(path, contents)`.
+    fn archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in entries {
+            zip.start_file(*path, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn error(data: &[u8]) -> String {
+        match read_project(data) {
+            Ok(_) => panic!("the project was read"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn project_round_trip() {
+        let lyrics = lyrics();
+        let data = write_project("song.mp3", b"audio bytes", &lyrics).unwrap();
+        let project = read_project(&data).unwrap();
+        assert_eq!(project.audio_file_name, "song.mp3");
+        assert_eq!(project.audio, b"audio bytes");
+        assert_eq!(project.lyrics, lyrics);
+    }
+
+    #[test]
+    fn rejects_archive_without_manifest() {
+        let json = convert::babel_to_json(&lyrics()).unwrap();
+        let data = archive(&[(LYRICS_PATH, json.as_bytes())]);
+        assert!(error(&data).contains(MANIFEST_PATH));
+        assert!(error(b"not a zip").starts_with("not a project archive"));
+    }
+
+    #[test]
+    fn rejects_newer_version() {
+        let manifest = format!(
+            r#"{{"version":{},"audio":"audio/song.mp3","lyrics":"{}"}}"#,
+            PROJECT_VERSION + 1,
+            LYRICS_PATH
+        );
+        let json = convert::babel_to_json(&lyrics()).unwrap();
+        let data = archive(&[
+            (MANIFEST_PATH, manifest.as_bytes()),
+            ("audio/song.mp3", b"audio bytes"),
+            (LYRICS_PATH, json.as_bytes()),
+        ]);
+        assert!(error(&data).contains(&format!("version {}", PROJECT_VERSION + 1)));
+    }
+
+    #[test]
+    fn rejects_missing_audio_entry() {
+        let manifest = format!(
+            r#"{{"version":{},"audio":"audio/missing.mp3","lyrics":"{}"}}"#,
+            PROJECT_VERSION, LYRICS_PATH
+        );
+        let json = convert::babel_to_json(&lyrics()).unwrap();
+        let data = archive(&[
+            (MANIFEST_PATH, manifest.as_bytes()),
+            (LYRICS_PATH, json.as_bytes()),
+        ]);
+        assert!(error(&data).starts_with("audio/missing.mp3"));
+    }
+}
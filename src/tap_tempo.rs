@@ -0,0 +1,151 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of intervals between taps the tempo is averaged over.
+const TAP_WINDOW: usize = 8;
+
+/// A pause between taps longer than this starts a new measurement.
+const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A tempo measured from the intervals between taps.
+#[derive(Clone, Copy)]
+pub struct TempoEstimate {
+    pub bpm: f32,
+
+    /// How steady the taps were, from 0 for erratic to 1 for perfectly even.
+    pub confidence: f32,
+
+    /// Number of taps the estimate is based on.
+    pub taps: usize,
+}
+
+/// Works out a tempo from taps in rhythm with the music.
+#[derive(Default)]
+pub struct TapTempo {
+    taps: VecDeque<Instant>,
+}
+
+impl TapTempo {
+    /// Records a tap at `now`, forgetting earlier taps after a long pause.
+    pub fn tap(&mut self, now: Instant) {
+        if let Some(last) = self.taps.back() {
+            if now.duration_since(*last) > TAP_TIMEOUT {
+                self.taps.clear();
+            }
+        }
+        self.taps.push_back(now);
+        while self.taps.len() > TAP_WINDOW + 1 {
+            self.taps.pop_front();
+        }
+    }
+
+    /// The tempo of the recent taps, once there are at least two.
+    pub fn estimate(&self) -> Option<TempoEstimate> {
+        let intervals: Vec<f32> = self
+            .taps
+            .iter()
+            .zip(self.taps.iter().skip(1))
+            .map(|(a, b)| b.duration_since(*a).as_secs_f32())
+            .collect();
+        if intervals.is_empty() {
+            return None;
+        }
+        let mean = intervals.iter().sum::<f32>() / intervals.len() as f32;
+        if mean <= 0.0 {
+            return None;
+        }
+        let variance = intervals
+            .iter()
+            .map(|interval| (interval - mean).powi(2))
+            .sum::<f32>()
+            / intervals.len() as f32;
+        // A spread of a tenth of a beat counts as no confidence, and a single interval as
+        // little, as there is nothing to compare it with.
+        let steadiness = (1.0 - variance.sqrt() / mean * 10.0).clamp(0.0, 1.0);
+        let coverage = intervals.len() as f32 / TAP_WINDOW as f32;
+        Some(TempoEstimate {
+            bpm: 60.0 / mean,
+            confidence: steadiness * coverage,
+            taps: self.taps.len(),
+        })
+    }
+
+    /// Whether the last tap was long enough ago that the next one starts over.
+    pub fn timed_out(&self, now: Instant) -> bool {
+        self.taps
+            .back()
+            .is_none_or(|last| now.duration_since(*last) > TAP_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Taps `count` times, `interval_ms` apart, from `start`, returning the time of the last.
+    fn tap_evenly(tempo: &mut TapTempo, start: Instant, count: usize, interval_ms: u64) -> Instant {
+        let mut now = start;
+        for index in 0..count {
+            now = start + Duration::from_millis(interval_ms * index as u64);
+            tempo.tap(now);
+        }
+        now
+    }
+
+    #[test]
+    fn no_estimate_before_two_taps() {
+        let mut tempo = TapTempo::default();
+        assert!(tempo.estimate().is_none());
+        tempo.tap(Instant::now());
+        assert!(tempo.estimate().is_none());
+    }
+
+    #[test]
+    fn even_taps_give_their_tempo() {
+        let mut tempo = TapTempo::default();
+        tap_evenly(&mut tempo, Instant::now(), 2, 500);
+        let estimate = tempo.estimate().unwrap();
+        assert!((estimate.bpm - 120.0).abs() < 0.01);
+        assert_eq!(estimate.taps, 2);
+        // A single interval has nothing to be compared with.
+        assert!(estimate.confidence < 0.2);
+
+        // Only the last TAP_WINDOW intervals count.
+        let mut tempo = TapTempo::default();
+        tap_evenly(&mut tempo, Instant::now(), 20, 600);
+        let estimate = tempo.estimate().unwrap();
+        assert!((estimate.bpm - 100.0).abs() < 0.01);
+        assert_eq!(estimate.taps, TAP_WINDOW + 1);
+        assert!(estimate.confidence > 0.99);
+    }
+
+    #[test]
+    fn uneven_taps_lower_the_confidence() {
+        let start = Instant::now();
+        let mut tempo = TapTempo::default();
+        for offset_ms in [0, 400, 1_000, 1_400, 2_000, 2_400, 3_000, 3_400, 4_000] {
+            tempo.tap(start + Duration::from_millis(offset_ms));
+        }
+        let estimate = tempo.estimate().unwrap();
+        assert!((estimate.bpm - 120.0).abs() < 0.01);
+        assert!(estimate.confidence < 0.5);
+    }
+
+    #[test]
+    fn long_pause_starts_over() {
+        let start = Instant::now();
+        let mut tempo = TapTempo::default();
+        let last = tap_evenly(&mut tempo, start, 5, 500);
+        assert!(!tempo.timed_out(last + TAP_TIMEOUT));
+        assert!(tempo.timed_out(last + TAP_TIMEOUT + Duration::from_millis(1)));
+
+        let resumed = last + Duration::from_secs(5);
+        tempo.tap(resumed);
+        assert!(tempo.estimate().is_none());
+        tempo.tap(resumed + Duration::from_millis(750));
+        let estimate = tempo.estimate().unwrap();
+        assert!((estimate.bpm - 80.0).abs() < 0.01);
+        assert_eq!(estimate.taps, 2);
+        assert!(TapTempo::default().timed_out(start));
+    }
+}
@@ -0,0 +1,122 @@
+//! Timestamps as shown and typed throughout the player and the editor.
+
+use chrono::Duration;
+
+/// Formats a timestamp as `m:ss.mmm`, or as `h:mm:ss.mmm` from an hour on, which
+/// [`parse_timestamp`] reads back.
+pub fn format_timestamp(timestamp: Duration) -> String {
+    if timestamp.num_hours() == 0 {
+        format!(
+            "{}:{:02}.{:03}",
+            timestamp.num_minutes(),
+            timestamp.num_seconds() % 60,
+            timestamp.num_milliseconds() % 1000
+        )
+    } else {
+        format!(
+            "{}:{:02}:{:02}.{:03}",
+            timestamp.num_hours(),
+            timestamp.num_minutes() % 60,
+            timestamp.num_seconds() % 60,
+            timestamp.num_milliseconds() % 1000
+        )
+    }
+}
+
+/// Parses a timestamp typed as `mm:ss`, `mm:ss.mmm` or `hh:mm:ss.mmm`.
+pub fn parse_timestamp(input: &str) -> Result<Duration, String> {
+    let malformed = || format!("\"{}\" is not of the form mm:ss.mmm or hh:mm:ss.mmm", input);
+    let parts: Vec<&str> = input.trim().split(':').collect();
+    let (hours, minutes, seconds) = match parts[..] {
+        [minutes, seconds] => ("0", minutes, seconds),
+        [hours, minutes, seconds] => (hours, minutes, seconds),
+        _ => return Err(malformed()),
+    };
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let number = |part: &str| {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return Err(malformed());
+        }
+        part.parse::<i64>().map_err(|_| malformed())
+    };
+    let hours = number(hours)?;
+    let minutes = number(minutes)?;
+    let seconds = number(seconds)?;
+    if seconds >= 60 || (parts.len() == 3 && minutes >= 60) {
+        return Err(malformed());
+    }
+    let milliseconds = match fraction.len() {
+        0 => 0,
+        // "4" is 400 ms and "45" is 450 ms.
+        1..=3 => number(fraction)? * 10i64.pow(3 - fraction.len() as u32),
+        _ => return Err(malformed()),
+    };
+    hours
+        .checked_mul(60)
+        .and_then(|x| x.checked_add(minutes))
+        .and_then(|x| x.checked_mul(60))
+        .and_then(|x| x.checked_add(seconds))
+        .and_then(|x| x.checked_mul(1000))
+        .and_then(|x| x.checked_add(milliseconds))
+        .and_then(Duration::try_milliseconds)
+        .ok_or_else(malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_timestamp_forms() {
+        let cases = [
+            ("1:02", 62_000),
+            ("01:02.5", 62_500),
+            ("01:02.45", 62_450),
+            ("01:02.345", 62_345),
+            (" 1:00:00.001 ", 3_600_001),
+            ("90:00", 5_400_000),
+        ];
+        for (input, milliseconds) in cases {
+            assert_eq!(
+                parse_timestamp(input),
+                Ok(Duration::milliseconds(milliseconds)),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn parse_timestamp_rejects_malformed() {
+        for input in [
+            "",
+            "12",
+            "1:60",
+            "1:60:00",
+            "-1:00",
+            "1:00.1234",
+            "a:00",
+            "1:2:3:4",
+            "99999999999999999:00",
+            "9223372036854775807:00:00",
+        ] {
+            assert!(parse_timestamp(input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn format_timestamp_round_trip() {
+        let cases = [
+            (0, "0:00.000"),
+            (62_345, "1:02.345"),
+            (3_599_999, "59:59.999"),
+            (3_600_001, "1:00:00.001"),
+            (37_230_450, "10:20:30.450"),
+        ];
+        for (milliseconds, text) in cases {
+            let timestamp = Duration::milliseconds(milliseconds);
+            assert_eq!(format_timestamp(timestamp), text);
+            assert_eq!(parse_timestamp(text), Ok(timestamp));
+        }
+    }
+}